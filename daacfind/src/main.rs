@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use clap::Parser;
-use daachorse::{DoubleArrayAhoCorasick, Empty};
+use daachorse::{CoveredSpanIterator, DoubleArrayAhoCorasick, Empty};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 #[derive(Clone, Copy, Debug)]
@@ -58,6 +58,7 @@ struct Args {
 /// When no pattern is found, this function does not print any string.
 fn find_and_output(
     pma: &DoubleArrayAhoCorasick<Empty>,
+    max_length: usize,
     line: &str,
     filename: Option<&str>,
     line_no: Option<usize>,
@@ -66,7 +67,7 @@ fn find_and_output(
 ) -> Result<(), std::io::Error> {
     match color {
         ArgColor::Never => {
-            if pma.find_iter(line).next().is_some() {
+            if pma.is_match(line) {
                 if let Some(filename) = filename {
                     write!(stream, "{filename}:")?;
                 }
@@ -77,34 +78,24 @@ fn find_and_output(
             }
         }
         ArgColor::Always | ArgColor::Auto => {
-            let mut color_counts = vec![0isize; line.len() + 1];
-            let mut matched = false;
-            for m in pma.find_overlapping_no_suffix_iter(line) {
-                matched = true;
-                color_counts[m.start()] += 1;
-                color_counts[m.end()] -= 1;
-            }
-            if matched {
+            // Suffixes of a match are covered by the match itself.
+            let mut spans =
+                CoveredSpanIterator::new(pma.find_overlapping_no_suffix_iter(line), max_length)
+                    .peekable();
+            if spans.peek().is_some() {
                 if let Some(filename) = filename {
                     write!(stream, "{filename}:")?;
                 }
                 if let Some(line_no) = line_no {
                     write!(stream, "{line_no}:")?;
                 }
-                let mut depth = 0;
                 let mut prev_pos = 0;
-                for (pos, c) in color_counts.into_iter().enumerate() {
-                    let new_depth = depth + c;
-                    if depth == 0 && new_depth != 0 {
-                        stream.reset()?;
-                        write!(stream, "{}", &line[prev_pos..pos])?;
-                        prev_pos = pos;
-                    } else if depth != 0 && new_depth == 0 {
-                        stream.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
-                        write!(stream, "{}", &line[prev_pos..pos])?;
-                        prev_pos = pos;
-                    }
-                    depth = new_depth;
+                for span in spans {
+                    stream.reset()?;
+                    write!(stream, "{}", &line[prev_pos..span.start])?;
+                    stream.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+                    write!(stream, "{}", &line[span.clone()])?;
+                    prev_pos = span.end;
                 }
                 stream.reset()?;
                 writeln!(stream, "{}", &line[prev_pos..])?;
@@ -135,7 +126,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     }
-    let pma = DoubleArrayAhoCorasick::new(patterns).map_err(|e| format!("{e}"))?;
+    let max_length = patterns.iter().map(String::len).max().unwrap_or(0);
+    let pma = DoubleArrayAhoCorasick::new(patterns)?;
 
     // Initialize the stream of termcolor.
     let mut stdout = match args.color {
@@ -149,7 +141,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let lines = stdin().lock().lines().enumerate();
         for (i, line) in lines {
             let line_number = if args.line_number { Some(i) } else { None };
-            find_and_output(&pma, &line?, None, line_number, args.color, &mut stdout)?;
+            find_and_output(
+                &pma,
+                max_length,
+                &line?,
+                None,
+                line_number,
+                args.color,
+                &mut stdout,
+            )?;
         }
     }
 
@@ -181,7 +181,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             break;
                         }
                     };
-                    find_and_output(&pma, &line, filename, line_number, args.color, &mut stdout)?;
+                    find_and_output(
+                        &pma,
+                        max_length,
+                        &line,
+                        filename,
+                        line_number,
+                        args.color,
+                        &mut stdout,
+                    )?;
                 }
             }
             Err(err) => {
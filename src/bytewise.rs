@@ -2,28 +2,39 @@
 
 mod builder;
 pub mod iter;
+mod mapper;
 
 use core::mem;
 use core::num::NonZeroU32;
+use core::ops::ControlFlow;
 
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 
 use crate::build_helper::BuildHelper;
 use crate::errors::{DaachorseError, Result};
+use crate::fuzzy;
 use crate::intpack::{U24nU8, U24};
 use crate::serializer::{Serializable, SerializableVec};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{
+    CoveredSpanIterator, FuzzyMatch, GroupSet, IntoInput, Lattice, Match, MatchCounts, MatchKind,
+    Output, ValuedCoveredSpanIterator,
+};
 pub use builder::DoubleArrayAhoCorasickBuilder;
 use iter::{
+    FilteredFindIterator, FilteredFindOverlappingIterator, FilteredLeftmostFindIterator,
     FindIterator, FindOverlappingIterator, FindOverlappingNoSuffixIterator, LeftmostFindIterator,
-    U8SliceIterator,
+    SegmentIterator, SegmentedLeftmostFindIterator, U8SliceIterator,
 };
+use mapper::ByteMapper;
 
 // The root index position.
 const ROOT_STATE_IDX: u32 = 0;
 // The dead index position.
 const DEAD_STATE_IDX: u32 = 1;
+// The flag set to the serialized match kind if the byte-class table is serialized.
+const BYTE_CLASSES_FLAG: u8 = 0x80;
 
 /// A fast multiple pattern match automaton implemented with the Aho-Corasick algorithm and compact
 /// double-array data structure.
@@ -62,6 +73,8 @@ const DEAD_STATE_IDX: u32 = 1;
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 pub struct DoubleArrayAhoCorasick<V> {
     states: Vec<State>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    mapper: ByteMapper,
     outputs: Vec<Output<V>>,
     match_kind: MatchKind,
     num_states: u32,
@@ -157,12 +170,187 @@ impl<V> DoubleArrayAhoCorasick<V> {
         DoubleArrayAhoCorasickBuilder::new().build_with_values(patvals)
     }
 
-    /// Returns an iterator of non-overlapping matches in the given haystack.
+    /// Returns `true` if any pattern occurs in the given haystack.
+    ///
+    /// This returns as soon as the first match is found, and is supported by all the match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// assert!(pma.is_match("abcd"));
+    /// assert!(!pma.is_match("bcbc"));
+    /// ```
+    #[must_use]
+    pub fn is_match<P>(&self, haystack: P) -> bool
+    where
+        P: AsRef<[u8]>,
+    {
+        let mut state_id = ROOT_STATE_IDX;
+        for &c in haystack.as_ref() {
+            // The leftmost transition is the same as the standard one on standard automata, and
+            // dead fails are never followed before reaching an output.
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            }
+            .is_some()
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the match ending earliest in the given haystack. If multiple patterns end at the
+    /// position, the longest one is returned.
+    ///
+    /// This returns as soon as the first match is found, and is supported by all the match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let m = pma.find_earliest("xabcd").unwrap();
+    /// assert_eq!((1, 2, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, pma.find_earliest("bcbc"));
+    /// ```
+    #[must_use]
+    pub fn find_earliest<P>(&self, haystack: P) -> Option<Match<V>>
+    where
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        let mut state_id = ROOT_STATE_IDX;
+        for (pos, &c) in haystack.as_ref().iter().enumerate() {
+            // The leftmost transition is the same as the standard one on standard automata, and
+            // dead fails are never followed before reaching an output.
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if let Some(output_pos) = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                // output_pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: pos + 1,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns the first match in the given haystack under the match kind specified in the
+    /// construction.
+    ///
+    /// The result is the same as the first item of [`Self::find_iter()`] under
+    /// [`MatchKind::Standard`] and of [`Self::leftmost_find_iter()`] under the other match
+    /// kinds.
     ///
     /// # Arguments
     ///
     /// * `haystack` - String to search for.
     ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "a", "abcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let m = pma.find_first("xabcd").unwrap();
+    /// assert_eq!((1, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = pma.find_earliest("xabcd").unwrap();
+    /// assert_eq!((1, 2, 1), (m.start(), m.end(), m.value()));
+    /// ```
+    #[must_use]
+    pub fn find_first<P>(&self, haystack: P) -> Option<Match<V>>
+    where
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        if self.match_kind.is_standard() {
+            return self.find_earliest(haystack);
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let mut last_output = None;
+        for (pos, &c) in haystack.as_ref().iter().enumerate() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if state_id == ROOT_STATE_IDX {
+                if last_output.is_some() {
+                    break;
+                }
+            } else if let Some(output_pos) = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                last_output = Some((output_pos, pos + 1));
+            }
+        }
+
+        last_output.map(|(output_pos, end)| {
+            // output_pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            Match {
+                length: usize::from_u32(out.length()),
+                end,
+                value: out.value(),
+            }
+        })
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
     /// # Panics
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
@@ -186,17 +374,22 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn find_iter<P>(&self, haystack: P) -> FindIterator<'_, U8SliceIterator<P>, V>
+    pub fn find_iter<H>(&self, haystack: H) -> FindIterator<'_, U8SliceIterator<H::Haystack>, V>
     where
-        P: AsRef<[u8]>,
+        H: IntoInput<[u8]>,
     {
         assert!(
             self.match_kind.is_standard(),
             "Error: match_kind must be standard."
         );
+        let (haystack, span, anchored) = haystack.into_parts();
         FindIterator {
             pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
+            haystack: U8SliceIterator::with_span(haystack, span.start, span.end).enumerate(),
+            start: span.start,
+            anchored,
+            pos: span.start,
+            output_pos: None,
         }
     }
 
@@ -242,6 +435,10 @@ impl<V> DoubleArrayAhoCorasick<V> {
         FindIterator {
             pma: self,
             haystack: haystack.enumerate(),
+            start: 0,
+            anchored: false,
+            pos: 0,
+            output_pos: None,
         }
     }
 
@@ -249,7 +446,8 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// # Arguments
     ///
-    /// * `haystack` - String to search for.
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
     ///
     /// # Panics
     ///
@@ -277,35 +475,898 @@ impl<V> DoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn find_overlapping_iter<P>(
+    pub fn find_overlapping_iter<H>(
+        &self,
+        haystack: H,
+    ) -> FindOverlappingIterator<'_, U8SliceIterator<H::Haystack>, V>
+    where
+        H: IntoInput<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        let (haystack, span, anchored) = haystack.into_parts();
+        FindOverlappingIterator {
+            pma: self,
+            haystack: U8SliceIterator::with_span(haystack, span.start, span.end).enumerate(),
+            start: span.start,
+            anchored,
+            state_id: ROOT_STATE_IDX,
+            output_pos: None,
+            pos: span.start,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack iterator.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - [`u8`] iterator to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
+    ///
+    /// let mut it = pma.find_overlapping_iter_from_iter(haystack);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> FindOverlappingIterator<'_, P, V>
+    where
+        P: Iterator<Item = u8>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FindOverlappingIterator {
+            pma: self,
+            haystack: haystack.enumerate(),
+            start: 0,
+            anchored: false,
+            state_id: ROOT_STATE_IDX,
+            output_pos: None,
+            pos: 0,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
+    ///
+    /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
+    /// matches when it reaches the end of each pattern. In the overlapping match, more than one
+    /// pattern can be returned per report.
+    ///
+    /// This iterator returns the first match on each report.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "cd", "abc"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_no_suffix_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_no_suffix_iter<P>(
         &self,
         haystack: P,
-    ) -> FindOverlappingIterator<'_, U8SliceIterator<P>, V>
+    ) -> FindOverlappingNoSuffixIterator<'_, U8SliceIterator<P>, V>
     where
         P: AsRef<[u8]>,
     {
-        assert!(
-            self.match_kind.is_standard(),
-            "Error: match_kind must be standard."
-        );
-        FindOverlappingIterator {
-            pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
-            state_id: ROOT_STATE_IDX,
-            output_pos: None,
-            pos: 0,
-        }
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FindOverlappingNoSuffixIterator {
+            pma: self,
+            haystack: U8SliceIterator::new(haystack).enumerate(),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
+    ///
+    /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
+    /// matches when it reaches the end of each pattern. In the overlapping match, more than one
+    /// pattern can be returned per report.
+    ///
+    /// This iterator returns the first match on each report.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - [`u8`] to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "cd", "abc"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
+    ///
+    /// let mut it = pma.find_overlapping_no_suffix_iter_from_iter(haystack);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_no_suffix_iter_from_iter<P>(
+        &self,
+        haystack: P,
+    ) -> FindOverlappingNoSuffixIterator<'_, P, V>
+    where
+        P: Iterator<Item = u8>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FindOverlappingNoSuffixIterator {
+            pma: self,
+            haystack: haystack.enumerate(),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack.
+    ///
+    /// The leftmost match greedily searches the longest possible match at each iteration, and
+    /// the match results do not overlap positionally such as
+    /// [`DoubleArrayAhoCorasick::find_iter()`].
+    ///
+    /// According to the [`MatchKind`] option you specified in the construction, the behavior is
+    /// changed for multiple possible matches, as follows.
+    ///
+    ///  - If you set [`MatchKind::LeftmostLongest`], it reports the match
+    ///    corresponding to the longest pattern.
+    ///
+    ///  - If you set [`MatchKind::LeftmostFirst`], it reports the match
+    ///    corresponding to the pattern earlier registered to the automaton.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`], [`MatchKind::LeftmostFirst`], or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and the
+    /// function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ## LeftmostLongest
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "a", "abcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 4, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    ///
+    /// ## LeftmostFirst
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "a", "abcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter<H>(&self, haystack: H) -> LeftmostFindIterator<'_, H::Haystack, V>
+    where
+        H: IntoInput<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        let (haystack, span, anchored) = haystack.into_parts();
+        LeftmostFindIterator {
+            pma: self,
+            haystack,
+            end: span.end,
+            anchored,
+            pos: span.start,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack split into
+    /// segments, such as the chunks of a rope.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter_from_segments(["ab", "c", "d"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> FindIterator<'_, SegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        self.find_iter_from_iter(SegmentIterator::new(segments.into_iter()))
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack split into segments,
+    /// such as the chunks of a rope.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter_from_segments(["a", "bc", "d"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> FindOverlappingIterator<'_, SegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        self.find_overlapping_iter_from_iter(SegmentIterator::new(segments.into_iter()))
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack split into segments, such
+    /// as the chunks of a rope.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries. The iterator buffers the bytes read beyond the end of the current
+    /// match, which are at most as many as the longest pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostFirst`], [`MatchKind::LeftmostLongest`] or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and
+    /// the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "a", "abcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter_from_segments(["ab", "c", "dab"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 4, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 6, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> SegmentedLeftmostFindIterator<'_, SegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        SegmentedLeftmostFindIterator {
+            pma: self,
+            haystack: SegmentIterator::new(segments.into_iter()),
+            lookahead: VecDeque::new(),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack, skipping patterns
+    /// whose values are rejected by `filter`.
+    ///
+    /// The matches are the same as those of [`Self::find_iter()`] on the automaton built
+    /// without the rejected patterns, which allows one automaton to be shared by searches using
+    /// different subsets of patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter_filtered("abcd", |v| v != 2);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredFindIterator<'_, U8SliceIterator<P>, V, F>
+    where
+        P: AsRef<[u8]>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FilteredFindIterator {
+            pma: self,
+            haystack: U8SliceIterator::new(haystack).enumerate(),
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack, skipping patterns whose
+    /// values are rejected by `filter`.
+    ///
+    /// The automaton built without values stores the pattern indices as values, so a bitset over
+    /// pattern IDs can be given as a predicate looking up the bit of each value, as in the example
+    /// below.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let enabled = [true, false, true];
+    /// let mut it = pma.find_overlapping_iter_filtered("abcd", |v: u32| enabled[v as usize]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredFindOverlappingIterator<'_, U8SliceIterator<P>, V, F>
+    where
+        P: AsRef<[u8]>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FilteredFindOverlappingIterator {
+            pma: self,
+            haystack: U8SliceIterator::new(haystack).enumerate(),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns an iterator of leftmost-longest matches in the given haystack, skipping patterns
+    /// whose values are rejected by `filter`.
+    ///
+    /// When the longest pattern is rejected, the longest one among the enabled patterns starting
+    /// at the same position is reported instead. The matches are the same as those of
+    /// [`Self::leftmost_find_iter()`] on the automaton built without the rejected patterns.
+    ///
+    /// Unlike [`Self::leftmost_find_iter()`], this takes `O(nm)` time in the worst case, where
+    /// `n` is the length of `haystack` and `m` is the maximum length of the patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`] in the construction, the iterator is
+    /// not supported and the function will panic.
+    /// [`MatchKind::LeftmostFirst`] and [`MatchKind::LeftmostShortest`] are rejected because
+    /// patterns shadowed by other patterns are discarded in the construction, so the next
+    /// preferred pattern may not be in the automaton when the preferred one is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["ab", "a", "abcd", "bcd"];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter_filtered("abcd", |v| v != 2);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredLeftmostFindIterator<'_, P, V, F>
+    where
+        P: AsRef<[u8]>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind == MatchKind::LeftmostLongest,
+            "Error: match_kind must be leftmost-longest."
+        );
+        FilteredLeftmostFindIterator {
+            pma: self,
+            haystack,
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns the set of groups having patterns that occur in the given haystack.
+    ///
+    /// The group of each pattern is given by `group_of` applied to its value, so the group IDs
+    /// are independent of the values. The patterns occurring in the haystack are those reported
+    /// by [`Self::find_overlapping_iter()`] under [`MatchKind::Standard`] and by
+    /// [`Self::leftmost_find_iter()`] under the other match kinds. The search stops as soon as all
+    /// the groups are found. Group IDs not smaller than `num_groups` are ignored.
+    ///
+    /// To make a pattern belong to multiple groups, register it repeatedly with
+    /// [`DuplicatePolicy::KeepAll`](crate::DuplicatePolicy::KeepAll) and map each value to a
+    /// group.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_groups` - The number of groups.
+    /// * `group_of` - Function returning the group ID of a pattern from its value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["apple", "orange", "cat"];
+    /// let pma = DoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// // "fruit" = 0, "color" = 1, "animal" = 2
+    /// let pattern_groups = [0, 1, 2];
+    ///
+    /// let groups = pma.matched_groups("an orange cat", 3, |i| pattern_groups[i]);
+    /// assert_eq!(vec![1, 2], groups.iter().collect::<Vec<_>>());
+    ///
+    /// let groups = pma.matched_groups("apple pie", 3, |i| pattern_groups[i]);
+    /// assert_eq!(vec![0], groups.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn matched_groups<P, F>(&self, haystack: P, num_groups: usize, mut group_of: F) -> GroupSet
+    where
+        P: AsRef<[u8]>,
+        V: Copy,
+        F: FnMut(V) -> usize,
+    {
+        let mut groups = GroupSet::new(num_groups);
+        if groups.is_full() {
+            return groups;
+        }
+        if !self.match_kind.is_standard() {
+            for m in self.leftmost_find_iter(haystack) {
+                let group = group_of(m.value());
+                if group < num_groups && groups.insert(group) && groups.is_full() {
+                    break;
+                }
+            }
+            return groups;
+        }
+        let mut state_id = ROOT_STATE_IDX;
+        for &c in haystack.as_ref() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+            let mut output_pos = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and Output::parent() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                let group = group_of(out.value());
+                if group < num_groups && groups.insert(group) && groups.is_full() {
+                    return groups;
+                }
+                output_pos = out.parent();
+            }
+        }
+        groups
+    }
+
+    /// Counts non-overlapping matches in the given haystack for each value, and adds them to
+    /// `counts`.
+    ///
+    /// The matches are the same as those of [`Self::find_iter()`] under [`MatchKind::Standard`]
+    /// and of [`Self::leftmost_find_iter()`] under the other match kinds. Since the counts are
+    /// accumulated, they can be collected across multiple haystacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `counts` - Collection to accumulate the numbers of matches, such as [`Vec<u64>`] indexed
+    ///   by values. See [`MatchCounts`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let mut counts = vec![0; 3];
+    /// pma.count_matches("abcd", &mut counts);
+    /// pma.count_matches("aab", &mut counts);
+    /// assert_eq!(vec![1, 0, 3], counts);
+    /// ```
+    pub fn count_matches<P, C>(&self, haystack: P, counts: &mut C)
+    where
+        P: AsRef<[u8]>,
+        V: Copy,
+        C: MatchCounts<V>,
+    {
+        if self.match_kind.is_standard() {
+            self.find_iter(haystack)
+                .for_each(|m| counts.increment(m.value()));
+        } else {
+            self.leftmost_find_iter(haystack)
+                .for_each(|m| counts.increment(m.value()));
+        }
+    }
+
+    /// Counts overlapping matches in the given haystack for each value, and adds them to
+    /// `counts`.
+    ///
+    /// Since the counts are accumulated, they can be collected across multiple haystacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `counts` - Collection to accumulate the numbers of matches, such as [`Vec<u64>`] indexed
+    ///   by values. See [`MatchCounts`] for details.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the function is not
+    /// supported and will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patvals = vec![("bcd", 'x'), ("ab", 'y'), ("a", 'z')];
+    /// let pma = DoubleArrayAhoCorasick::with_values(patvals).unwrap();
+    ///
+    /// let mut counts = BTreeMap::new();
+    /// pma.count_overlapping_matches("abcd", &mut counts);
+    /// pma.count_overlapping_matches("aab", &mut counts);
+    /// assert_eq!(vec![('x', 1), ('y', 2), ('z', 3)], counts.into_iter().collect::<Vec<_>>());
+    /// ```
+    pub fn count_overlapping_matches<P, C>(&self, haystack: P, counts: &mut C)
+    where
+        P: AsRef<[u8]>,
+        V: Copy,
+        C: MatchCounts<V>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        let mut state_id = ROOT_STATE_IDX;
+        for &c in haystack.as_ref() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+            let mut output_pos = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and Output::parent() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                counts.increment(out.value());
+                output_pos = out.parent();
+            }
+        }
+    }
+
+    /// Returns an iterator of the merged spans covered by the overlapping matches in the given
+    /// haystack, which is useful for highlighting.
+    ///
+    /// The spans are non-overlapping, non-adjacent, and in ascending order. See
+    /// [`CoveredSpanIterator`] for details.
+    ///
+    /// The length of the longest pattern is computed on every call, which takes time linear in
+    /// the number of patterns. To search many short haystacks, compute it once and create
+    /// [`CoveredSpanIterator`] directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a", "e"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let spans: Vec<_> = pma.covered_spans("abcdxef").collect();
+    /// assert_eq!(vec![0..4, 5..6], spans);
+    /// ```
+    pub fn covered_spans<H>(
+        &self,
+        haystack: H,
+    ) -> CoveredSpanIterator<FindOverlappingIterator<'_, U8SliceIterator<H::Haystack>, V>>
+    where
+        H: IntoInput<[u8]>,
+        V: Copy,
+    {
+        CoveredSpanIterator::new(
+            self.find_overlapping_iter(haystack),
+            self.max_pattern_length(),
+        )
+    }
+
+    /// Returns an iterator of the spans covered by the overlapping matches in the given
+    /// haystack, annotated with the values of the matches covering each span.
+    ///
+    /// The spans are non-overlapping and in ascending order, and adjacent spans have different
+    /// values. See [`ValuedCoveredSpanIterator`] for details.
+    ///
+    /// The length of the longest pattern is computed on every call, which takes time linear in
+    /// the number of patterns. To search many short haystacks, compute it once and create
+    /// [`ValuedCoveredSpanIterator`] directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a", "e"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let spans: Vec<_> = pma.covered_spans_with_values("abcdxef").collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         (0..1, vec![1, 2]),
+    ///         (1..2, vec![0, 1]),
+    ///         (2..4, vec![0]),
+    ///         (5..6, vec![3]),
+    ///     ],
+    ///     spans,
+    /// );
+    /// ```
+    pub fn covered_spans_with_values<H>(
+        &self,
+        haystack: H,
+    ) -> ValuedCoveredSpanIterator<FindOverlappingIterator<'_, U8SliceIterator<H::Haystack>, V>, V>
+    where
+        H: IntoInput<[u8]>,
+        V: Copy + Ord,
+    {
+        ValuedCoveredSpanIterator::new(
+            self.find_overlapping_iter(haystack),
+            self.max_pattern_length(),
+        )
     }
 
-    /// Returns an iterator of overlapping matches in the given haystack iterator.
+    /// Builds a lattice of the overlapping matches in the given haystack, indexed by their
+    /// starting positions.
+    ///
+    /// The edges outgoing from each position are in ascending order of their ends. See
+    /// [`Lattice`] for details.
     ///
     /// # Arguments
     ///
-    /// * `haystack` - [`u8`] iterator to search for.
+    /// * `haystack` - String to search for.
     ///
     /// # Panics
     ///
-    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the lattice is not
     /// supported and the function will panic.
     ///
     /// # Examples
@@ -314,58 +1375,38 @@ impl<V> DoubleArrayAhoCorasick<V> {
     /// use daachorse::DoubleArrayAhoCorasick;
     ///
     /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
-    ///
-    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
-    ///
-    /// let mut it = pma.find_overlapping_iter_from_iter(haystack);
-    ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
     ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 2, 1), (m.start(), m.end(), m.value()));
+    /// let lattice = pma.build_lattice("abcd");
     ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    /// let edges: Vec<_> = lattice.edges(0).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(1, 2), (2, 1)], edges);
     ///
-    /// assert_eq!(None, it.next());
+    /// let edges: Vec<_> = lattice.edges(1).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(4, 0)], edges);
     /// ```
-    pub fn find_overlapping_iter_from_iter<P>(
-        &self,
-        haystack: P,
-    ) -> FindOverlappingIterator<'_, P, V>
+    pub fn build_lattice<P>(&self, haystack: P) -> Lattice<V>
     where
-        P: Iterator<Item = u8>,
+        P: AsRef<[u8]>,
+        V: Copy,
     {
-        assert!(
-            self.match_kind.is_standard(),
-            "Error: match_kind must be standard."
-        );
-        FindOverlappingIterator {
-            pma: self,
-            haystack: haystack.enumerate(),
-            state_id: ROOT_STATE_IDX,
-            output_pos: None,
-            pos: 0,
-        }
+        let haystack = haystack.as_ref();
+        Lattice::new(haystack.len(), self.find_overlapping_iter(haystack))
     }
 
-    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
-    ///
-    /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
-    /// matches when it reaches the end of each pattern. In the overlapping match, more than one
-    /// pattern can be returned per report.
+    /// Builds a lattice of the overlapping matches in the given haystack, filling each maximal
+    /// span covered by no match with an unknown-word edge.
     ///
-    /// This iterator returns the first match on each report.
+    /// See [`Lattice::with_unknown()`] for details.
     ///
     /// # Arguments
     ///
     /// * `haystack` - String to search for.
+    /// * `unknown` - Value associated with the unknown-word edges.
     ///
     /// # Panics
     ///
-    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the lattice is not
     /// supported and the function will panic.
     ///
     /// # Examples
@@ -373,52 +1414,50 @@ impl<V> DoubleArrayAhoCorasick<V> {
     /// ```
     /// use daachorse::DoubleArrayAhoCorasick;
     ///
-    /// let patterns = vec!["bcd", "cd", "abc"];
-    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
-    ///
-    /// let mut it = pma.find_overlapping_no_suffix_iter("abcd");
-    ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
     ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    /// let lattice = pma.build_lattice_with_unknown("xxabcd", u32::MAX);
     ///
-    /// assert_eq!(None, it.next());
+    /// let edges: Vec<_> = lattice.edges(0).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(2, u32::MAX)], edges);
     /// ```
-    pub fn find_overlapping_no_suffix_iter<P>(
-        &self,
-        haystack: P,
-    ) -> FindOverlappingNoSuffixIterator<'_, U8SliceIterator<P>, V>
+    pub fn build_lattice_with_unknown<P>(&self, haystack: P, unknown: V) -> Lattice<V>
     where
         P: AsRef<[u8]>,
+        V: Copy,
     {
-        assert!(
-            self.match_kind.is_standard(),
-            "Error: match_kind must be standard."
-        );
-        FindOverlappingNoSuffixIterator {
-            pma: self,
-            haystack: U8SliceIterator::new(haystack).enumerate(),
-            state_id: ROOT_STATE_IDX,
-        }
+        let haystack = haystack.as_ref();
+        Lattice::with_unknown(
+            haystack.len(),
+            self.find_overlapping_iter(haystack),
+            unknown,
+        )
     }
 
-    /// Returns an iterator of overlapping matches without suffixes in the given haystack iterator.
+    /// Returns the approximate matches of the patterns in the given haystack, which are
+    /// substrings within the Levenshtein distance `max_distance` of the patterns.
     ///
-    /// The Aho-Corasick algorithm reads through the haystack from left to right and reports
-    /// matches when it reaches the end of each pattern. In the overlapping match, more than one
-    /// pattern can be returned per report.
+    /// The automaton's trie is traversed while updating the edit distances between the
+    /// patterns and the substrings from each starting position, so variants of the patterns
+    /// are never enumerated. For each starting position and each pattern, the substring with
+    /// the minimum distance is reported, and the longest one is chosen when several
+    /// substrings have the minimum distance. Since the substrings are non-empty, a pattern
+    /// whose length is at most `max_distance` matches at every starting position.
     ///
-    /// This iterator returns the first match on each report.
+    /// The matches are ordered by their starting positions and then by their ending
+    /// positions, and the matches of a pattern starting at different positions can overlap.
+    /// The time grows rapidly with `max_distance`, so it is intended for small distances such
+    /// as 1 or 2.
     ///
     /// # Arguments
     ///
-    /// * `haystack` - [`u8`] to search for.
+    /// * `haystack` - String to search for.
+    /// * `max_distance` - Maximum edit distance in bytes.
     ///
     /// # Panics
     ///
-    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the search is not
     /// supported and the function will panic.
     ///
     /// # Examples
@@ -426,114 +1465,221 @@ impl<V> DoubleArrayAhoCorasick<V> {
     /// ```
     /// use daachorse::DoubleArrayAhoCorasick;
     ///
-    /// let patterns = vec!["bcd", "cd", "abc"];
-    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
-    ///
-    /// let haystack = "ab".as_bytes().iter().chain("cd".as_bytes()).copied();
-    ///
-    /// let mut it = pma.find_overlapping_no_suffix_iter_from_iter(haystack);
-    ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 3, 2), (m.start(), m.end(), m.value()));
+    /// let patterns = vec!["Mississippi", "Missouri"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
     ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    /// let matches = pma.find_fuzzy("Mississipi river", 1);
     ///
-    /// assert_eq!(None, it.next());
+    /// assert_eq!(1, matches.len());
+    /// let m = matches[0];
+    /// assert_eq!((0, 10, 1, 0), (m.start(), m.end(), m.distance(), m.value()));
     /// ```
-    pub fn find_overlapping_no_suffix_iter_from_iter<P>(
-        &self,
-        haystack: P,
-    ) -> FindOverlappingNoSuffixIterator<'_, P, V>
+    pub fn find_fuzzy<P>(&self, haystack: P, max_distance: usize) -> Vec<FuzzyMatch<V>>
     where
-        P: Iterator<Item = u8>,
+        P: AsRef<[u8]>,
+        V: Copy,
     {
         assert!(
             self.match_kind.is_standard(),
             "Error: match_kind must be standard."
         );
-        FindOverlappingNoSuffixIterator {
-            pma: self,
-            haystack: haystack.enumerate(),
-            state_id: ROOT_STATE_IDX,
+        let labels: Vec<u8> = haystack
+            .as_ref()
+            .iter()
+            .map(|&c| self.mapper.get(c))
+            .collect();
+        let all_labels: Vec<u8> = (0..self.mapper.alphabet_size())
+            .map(|c| u8::try_from(c).unwrap())
+            .collect();
+        let mut matches = vec![];
+        // Each element has a state id, the depth, and the offset of its row in `rows`.
+        let mut stack = vec![];
+        let mut rows = vec![];
+        let mut row = vec![];
+        let mut new_row = vec![];
+        for start in 0..labels.len() {
+            let suffix = &labels[start..];
+            let first = matches.len();
+            rows.clear();
+            rows.extend(0..=max_distance.min(suffix.len()));
+            stack.push((ROOT_STATE_IDX, 0, 0));
+            while let Some((state_id, depth, offset)) = stack.pop() {
+                row.clear();
+                row.extend_from_slice(&rows[offset..]);
+                rows.truncate(offset);
+                let exact_labels;
+                let candidates = if row.iter().all(|&d| d >= max_distance) {
+                    exact_labels = fuzzy::exact_labels(&row, suffix, max_distance);
+                    &exact_labels
+                } else {
+                    &all_labels
+                };
+                for &c in candidates {
+                    // state_id is always smaller than self.states.len() because it is the root
+                    // or a value returned by self.child_index_unchecked().
+                    // c is always smaller than the block length because it is a byte class.
+                    let child_id = match unsafe { self.child_index_unchecked(state_id, c) } {
+                        Some(child_id) => child_id,
+                        None => continue,
+                    };
+                    let min = fuzzy::next_row(
+                        &row,
+                        suffix,
+                        c,
+                        max_distance.saturating_add(depth + 1),
+                        &mut new_row,
+                    );
+                    if min > max_distance {
+                        continue;
+                    }
+                    // child_id is always smaller than self.states.len() because
+                    // self.child_index_unchecked() ensures to return such a value.
+                    if let Some(output_pos) = unsafe { self.own_output_pos(child_id) } {
+                        if let Some((len, distance)) = fuzzy::best_end(&new_row, max_distance) {
+                            let mut output_pos = Some(output_pos);
+                            while let Some(pos) = output_pos {
+                                // pos.get() is always smaller than self.outputs.len() because
+                                // State::output_pos() and next_value_pos() ensure to return
+                                // such a value.
+                                let out = unsafe {
+                                    self.outputs.get_unchecked(usize::from_u32(pos.get() - 1))
+                                };
+                                matches.push(FuzzyMatch::new(
+                                    start,
+                                    start + len,
+                                    distance,
+                                    out.value(),
+                                ));
+                                output_pos = self.next_value_pos(out);
+                            }
+                        }
+                    }
+                    stack.push((child_id, depth + 1, rows.len()));
+                    rows.extend_from_slice(&new_row);
+                }
+            }
+            matches[first..].sort_by_key(FuzzyMatch::end);
         }
+        matches
     }
 
-    /// Returns an iterator of leftmost matches in the given haystack.
-    ///
-    /// The leftmost match greedily searches the longest possible match at each iteration, and
-    /// the match results do not overlap positionally such as
-    /// [`DoubleArrayAhoCorasick::find_iter()`].
-    ///
-    /// According to the [`MatchKind`] option you specified in the construction, the behavior is
-    /// changed for multiple possible matches, as follows.
-    ///
-    ///  - If you set [`MatchKind::LeftmostLongest`], it reports the match
-    ///    corresponding to the longest pattern.
+    /// Calls `f` on each match in the given haystack until it returns [`ControlFlow::Break`].
     ///
-    ///  - If you set [`MatchKind::LeftmostFirst`], it reports the match
-    ///    corresponding to the pattern earlier registered to the automaton.
+    /// The matches are the same as those of [`Self::find_overlapping_iter()`] under
+    /// [`MatchKind::Standard`] and of [`Self::leftmost_find_iter()`] under the other match
+    /// kinds. Since the automaton is driven in a single loop, this is faster than the
+    /// iterators, and stops immediately when `f` returns [`ControlFlow::Break`], even in the
+    /// middle of the matches ending at the same position.
     ///
     /// # Arguments
     ///
     /// * `haystack` - String to search for.
+    /// * `f` - Function called on each match.
     ///
-    /// # Panics
+    /// # Returns
     ///
-    /// If you do not specify [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostLongest`] in
-    /// the construction, the iterator is not supported and the function will panic.
+    /// The value returned by `f` if it breaks, or [`ControlFlow::Continue`] otherwise.
     ///
     /// # Examples
     ///
-    /// ## LeftmostLongest
-    ///
-    /// ```
-    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
-    ///
-    /// let patterns = vec!["ab", "a", "abcd"];
-    /// let pma = DoubleArrayAhoCorasickBuilder::new()
-    ///     .match_kind(MatchKind::LeftmostLongest)
-    ///     .build(&patterns)
-    ///     .unwrap();
-    ///
-    /// let mut it = pma.leftmost_find_iter("abcd");
-    ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 4, 2), (m.start(), m.end(), m.value()));
-    ///
-    /// assert_eq!(None, it.next());
-    /// ```
-    ///
-    /// ## LeftmostFirst
-    ///
     /// ```
-    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
-    ///
-    /// let patterns = vec!["ab", "a", "abcd"];
-    /// let pma = DoubleArrayAhoCorasickBuilder::new()
-    ///     .match_kind(MatchKind::LeftmostFirst)
-    ///     .build(&patterns)
-    ///     .unwrap();
+    /// use core::ops::ControlFlow;
     ///
-    /// let mut it = pma.leftmost_find_iter("abcd");
+    /// use daachorse::DoubleArrayAhoCorasick;
     ///
-    /// let m = it.next().unwrap();
-    /// assert_eq!((0, 2, 0), (m.start(), m.end(), m.value()));
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
     ///
-    /// assert_eq!(None, it.next());
+    /// let mut matches = vec![];
+    /// let result = pma.for_each_match("abcdab", |m| {
+    ///     matches.push((m.start(), m.end(), m.value()));
+    ///     if m.value() == 0 {
+    ///         ControlFlow::Break(m.end())
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(ControlFlow::Break(4), result);
+    /// assert_eq!(vec![(0, 1, 2), (0, 2, 1), (1, 4, 0)], matches);
     /// ```
-    pub fn leftmost_find_iter<P>(&self, haystack: P) -> LeftmostFindIterator<'_, P, V>
+    pub fn for_each_match<P, F, B>(&self, haystack: P, mut f: F) -> ControlFlow<B>
     where
         P: AsRef<[u8]>,
+        V: Copy,
+        F: FnMut(Match<V>) -> ControlFlow<B>,
     {
-        assert!(
-            self.match_kind.is_leftmost(),
-            "Error: match_kind must be leftmost."
-        );
-        LeftmostFindIterator {
-            pma: self,
-            haystack,
-            pos: 0,
+        if self.match_kind.is_standard() {
+            let mut state_id = ROOT_STATE_IDX;
+            for (pos, &c) in haystack.as_ref().iter().enumerate() {
+                // state_id is always smaller than self.states.len() because
+                // self.next_state_id_unchecked() ensures to return such a value.
+                state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+                let mut output_pos = unsafe {
+                    self.states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                let end = pos + 1;
+                while let Some(pos) = output_pos {
+                    // pos.get() is always smaller than self.outputs.len() because
+                    // State::output_pos() and Output::parent() ensure to return such a value.
+                    let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                    f(Match {
+                        length: usize::from_u32(out.length()),
+                        end,
+                        value: out.value(),
+                    })?;
+                    output_pos = out.parent();
+                }
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let haystack = haystack.as_ref();
+        let mut start = 0;
+        loop {
+            let mut state_id = ROOT_STATE_IDX;
+            let mut last_output_pos = None;
+            let mut end = start;
+            // start is always a position in haystack.
+            for (i, &c) in unsafe { haystack.get_unchecked(start..) }
+                .iter()
+                .enumerate()
+            {
+                // state_id is always smaller than self.states.len() because
+                // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+                state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+                if state_id == ROOT_STATE_IDX {
+                    if last_output_pos.is_some() {
+                        break;
+                    }
+                } else if let Some(output_pos) = unsafe {
+                    self.states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                } {
+                    last_output_pos = Some(output_pos);
+                    end = start + i + 1;
+                }
+            }
+
+            let mut output_pos = match last_output_pos {
+                Some(output_pos) => Some(output_pos),
+                None => return ControlFlow::Continue(()),
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and next_value_pos() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                f(Match {
+                    length: usize::from_u32(out.length()),
+                    end,
+                    value: out.value(),
+                })?;
+                output_pos = self.next_value_pos(out);
+            }
+            start = end;
         }
     }
 
@@ -592,12 +1738,20 @@ impl<V> DoubleArrayAhoCorasick<V> {
             self.states.serialized_bytes()
                 + self.outputs.serialized_bytes()
                 + MatchKind::serialized_bytes()
-                + u32::serialized_bytes(),
+                + u32::serialized_bytes()
+                + ByteMapper::serialized_bytes(),
         );
         self.states.serialize_to_vec(&mut result);
         self.outputs.serialize_to_vec(&mut result);
-        self.match_kind.serialize_to_vec(&mut result);
-        self.num_states.serialize_to_vec(&mut result);
+        // The identity mapping is omitted to keep the layout of older versions.
+        if self.mapper.is_identity() {
+            self.match_kind.serialize_to_vec(&mut result);
+            self.num_states.serialize_to_vec(&mut result);
+        } else {
+            result.push(u8::from(self.match_kind) | BYTE_CLASSES_FLAG);
+            self.num_states.serialize_to_vec(&mut result);
+            self.mapper.serialize_to_vec(&mut result);
+        }
         result
     }
 
@@ -647,11 +1801,18 @@ impl<V> DoubleArrayAhoCorasick<V> {
     {
         let (states, source) = Vec::<State>::deserialize_from_slice(source);
         let (outputs, source) = Vec::<Output<V>>::deserialize_from_slice(source);
-        let (match_kind, source) = MatchKind::deserialize_from_slice(source);
+        let (match_kind, source) = u8::deserialize_from_slice(source);
         let (num_states, source) = u32::deserialize_from_slice(source);
+        let (mapper, source) = if match_kind & BYTE_CLASSES_FLAG == 0 {
+            (ByteMapper::identity(), source)
+        } else {
+            ByteMapper::deserialize_from_slice(source)
+        };
+        let match_kind = MatchKind::from(match_kind & !BYTE_CLASSES_FLAG);
         (
             Self {
                 states,
+                mapper,
                 outputs,
                 match_kind,
                 num_states,
@@ -660,13 +1821,22 @@ impl<V> DoubleArrayAhoCorasick<V> {
         )
     }
 
+    /// Returns an iterator of the values associated with outputs.
+    pub(crate) fn output_values(&self) -> impl Iterator<Item = V> + '_
+    where
+        V: Copy,
+    {
+        self.outputs.iter().map(|out| out.value())
+    }
+
     /// # Safety
     ///
     /// `state_id` must be smaller than the length of states.
     #[inline(always)]
     unsafe fn child_index_unchecked(&self, state_id: u32, c: u8) -> Option<u32> {
         // child_idx is always smaller than states.len() because
-        //  - states.len() is 256 * k for some integer k, and
+        //  - states.len() is block_len * k for some integer k,
+        //  - c is smaller than block_len because it is a byte class given by the mapper, and
         //  - base() returns smaller than states.len() when it is Some.
         self.states
             .get_unchecked(usize::from_u32(state_id))
@@ -683,6 +1853,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     /// `state_id` must be smaller than the length of states.
     #[inline(always)]
     unsafe fn next_state_id_unchecked(&self, mut state_id: u32, c: u8) -> u32 {
+        let c = self.mapper.get(c);
         // In the loop, state_id is always set to values smaller than states.len(),
         // because child_index_unchecked() and fail() return such values.
         loop {
@@ -701,6 +1872,7 @@ impl<V> DoubleArrayAhoCorasick<V> {
     /// `state_id` must be smaller than the length of states.
     #[inline(always)]
     unsafe fn next_state_id_leftmost_unchecked(&self, mut state_id: u32, c: u8) -> u32 {
+        let c = self.mapper.get(c);
         // In the loop, state_id is always set to values smaller than states.len(),
         // because child_index_unchecked() and fail() return such values.
         loop {
@@ -717,6 +1889,127 @@ impl<V> DoubleArrayAhoCorasick<V> {
             state_id = fail_id;
         }
     }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the length of states.
+    #[inline(always)]
+    unsafe fn next_state_id_anchored_unchecked(&self, state_id: u32, c: u8) -> Option<u32> {
+        self.child_index_unchecked(state_id, self.mapper.get(c))
+    }
+
+    /// Returns `output_pos` if its output has the given length, i.e., it is not an output of a
+    /// proper suffix of the string spelled by the current state.
+    #[inline(always)]
+    fn anchored_output_pos(
+        &self,
+        output_pos: Option<NonZeroU32>,
+        length: usize,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+    {
+        output_pos.filter(|output_pos| {
+            // output_pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and Output::parent() ensure to return such a value.
+            let out = unsafe {
+                self.outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            usize::from_u32(out.length()) == length
+        })
+    }
+
+    /// Returns the output position of the pattern spelled by the state itself, excluding the
+    /// outputs of its proper suffixes.
+    ///
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the length of states.
+    #[inline(always)]
+    unsafe fn own_output_pos(&self, state_id: u32) -> Option<NonZeroU32> {
+        let state = self.states.get_unchecked(usize::from_u32(state_id));
+        // fail() is always smaller than self.states.len() because it is a state id.
+        let fail = self.states.get_unchecked(usize::from_u32(state.fail()));
+        // A state without its own pattern shares the output position with its failure state.
+        state
+            .output_pos()
+            .filter(|&pos| Some(pos) != fail.output_pos())
+    }
+
+    /// Returns the length of the longest pattern in bytes.
+    fn max_pattern_length(&self) -> usize
+    where
+        V: Copy,
+    {
+        self.outputs
+            .iter()
+            .map(|out| usize::from_u32(out.length()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the position of the output storing the next value of the same pattern as `out`,
+    /// which exists only if multiple values are attached to the pattern.
+    #[inline(always)]
+    fn next_value_pos(&self, out: &Output<V>) -> Option<NonZeroU32> {
+        out.parent.filter(|pos| {
+            // pos.get() is always smaller than self.outputs.len() because
+            // Output::parent() ensures to return such a value when it is Some.
+            // Since a pattern is followed by its suffixes, which are shorter, only the outputs of
+            // the same pattern have the same length.
+            unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) }.length
+                == out.length
+        })
+    }
+
+    /// Returns the position of the first output accepted by `filter` in the chain of outputs
+    /// starting at `output_pos`.
+    #[inline(always)]
+    fn accepted_output_pos<F>(
+        &self,
+        mut output_pos: Option<NonZeroU32>,
+        filter: &mut F,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+        F: FnMut(V) -> bool,
+    {
+        while let Some(pos) = output_pos {
+            // pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and Output::parent() ensure to return such a value.
+            let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+            if filter(out.value()) {
+                return Some(pos);
+            }
+            output_pos = out.parent();
+        }
+        None
+    }
+
+    /// Returns the position of the first output accepted by `filter` among the values of the
+    /// same pattern, starting at `output_pos`.
+    #[inline(always)]
+    fn accepted_value_pos<F>(
+        &self,
+        mut output_pos: Option<NonZeroU32>,
+        filter: &mut F,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+        F: FnMut(V) -> bool,
+    {
+        while let Some(pos) = output_pos {
+            // pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and next_value_pos() ensure to return such a value.
+            let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+            if filter(out.value()) {
+                return Some(pos);
+            }
+            output_pos = self.next_value_pos(out);
+        }
+        None
+    }
 }
 
 #[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
@@ -1035,6 +2328,53 @@ mod tests {
         assert_eq!(pma.num_states, other.num_states);
     }
 
+    #[test]
+    fn test_serialize_pma_255_bytes() {
+        // The byte classes are a permutation of all the bytes, not the identity.
+        let mut patterns: Vec<Vec<u8>> = (0..0xff).map(|c| vec![c]).collect();
+        patterns.push(b"ab".to_vec());
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .byte_classes(true)
+            .build::<_, _, u32>(&patterns)
+            .unwrap();
+        assert!(!pma.mapper.is_identity());
+
+        let bytes = pma.serialize();
+        let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+        assert!(rest.is_empty());
+        assert_eq!(pma.mapper, other.mapper);
+
+        let haystack = [b'a', b'b', 0x00, 0xfe, 0xff];
+        let matches: Vec<_> = other
+            .find_overlapping_iter(haystack)
+            .map(|m| (m.start(), m.end(), m.value()))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, 1, 0x61),
+                (0, 2, 0xff),
+                (1, 2, 0x62),
+                (2, 3, 0x00),
+                (3, 4, 0xfe)
+            ],
+            matches
+        );
+    }
+
+    #[test]
+    fn test_serialize_pma_identity_layout() {
+        let patterns = vec!["abba", "baaba", "ababa"];
+        let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+
+        // Without byte classes, the layout is the same as older versions.
+        let mut expected = vec![];
+        pma.states.serialize_to_vec(&mut expected);
+        pma.outputs.serialize_to_vec(&mut expected);
+        pma.match_kind.serialize_to_vec(&mut expected);
+        pma.num_states.serialize_to_vec(&mut expected);
+        assert_eq!(expected, pma.serialize());
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_serde_state() {
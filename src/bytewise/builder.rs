@@ -1,17 +1,17 @@
+use core::borrow::Borrow;
 use core::num::NonZeroU32;
 
 use alloc::vec::Vec;
 
 use crate::bytewise::{
-    BuildHelper, DoubleArrayAhoCorasick, MatchKind, State, DEAD_STATE_IDX, ROOT_STATE_IDX,
+    BuildHelper, ByteMapper, DoubleArrayAhoCorasick, MatchKind, State, DEAD_STATE_IDX,
+    ROOT_STATE_IDX,
 };
-use crate::errors::{DaachorseError, Result};
+use crate::errors::{DaachorseError, PatternProblem, Result};
 use crate::intpack::U24;
 use crate::nfa_builder::{NfaBuilder, DEAD_STATE_ID, ROOT_STATE_ID};
 use crate::utils::FromU32;
-
-// The length of each double-array block.
-const BLOCK_LEN: u32 = 256;
+use crate::{DuplicatePolicy, WildcardPattern};
 
 // Specialized [`NfaBuilder`] handling labels of `u8`.
 type BytewiseNfaBuilder<V> = NfaBuilder<u8, V>;
@@ -19,8 +19,14 @@ type BytewiseNfaBuilder<V> = NfaBuilder<u8, V>;
 /// Builder of [`DoubleArrayAhoCorasick`].
 pub struct DoubleArrayAhoCorasickBuilder {
     states: Vec<State>,
+    mapper: ByteMapper,
     match_kind: MatchKind,
+    duplicate_policy: DuplicatePolicy,
+    reject_unreachable: bool,
+    byte_classes: bool,
+    block_len: u32,
     num_free_blocks: u32,
+    max_wildcard_states: u32,
 }
 
 impl Default for DoubleArrayAhoCorasickBuilder {
@@ -56,8 +62,14 @@ impl DoubleArrayAhoCorasickBuilder {
     pub const fn new() -> Self {
         Self {
             states: vec![],
+            mapper: ByteMapper::identity(),
             match_kind: MatchKind::Standard,
+            duplicate_policy: DuplicatePolicy::Error,
+            reject_unreachable: false,
+            byte_classes: false,
+            block_len: 0,
             num_free_blocks: 16,
+            max_wildcard_states: 1 << 20,
         }
     }
 
@@ -91,6 +103,121 @@ impl DoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies [`DuplicatePolicy`] for patterns registered more than once.
+    ///
+    /// By default, [`DuplicatePolicy::Error`] is used, and a repeated pattern is rejected. If
+    /// [`DuplicatePolicy::KeepAll`] is specified, all values attached to the same pattern are kept,
+    /// and every iterator reports one match for each of them in registration order.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Duplicate policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, DuplicatePolicy};
+    ///
+    /// let patvals = vec![("Paris", 0), ("Paris", 1), ("Paris", 2)];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .duplicate_policy(DuplicatePolicy::KeepAll)
+    ///     .build_with_values(patvals)
+    ///     .unwrap();
+    ///
+    /// let values: Vec<_> = pma.find_iter("Paris").map(|m| m.value()).collect();
+    /// assert_eq!(vec![0, 1, 2], values);
+    /// ```
+    #[must_use]
+    pub const fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Specifies whether to reject patterns that are never reported under
+    /// [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostShortest`].
+    ///
+    /// Under [`MatchKind::LeftmostFirst`], a pattern is never reported if a prefix of it is
+    /// registered earlier. Under [`MatchKind::LeftmostShortest`], a pattern is never reported if
+    /// a prefix of it is registered. Such a pattern is ignored by default. If this option is
+    /// enabled, the construction fails with
+    /// [`UnreachablePatternError`](crate::errors::UnreachablePatternError) instead. This option
+    /// has no effect under the other match kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to reject unreachable patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::DaachorseError;
+    /// use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["Par", "Paris"];
+    /// let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .reject_unreachable(true)
+    ///     .build(&patterns);
+    ///
+    /// match result {
+    ///     Err(DaachorseError::UnreachablePattern(e)) => {
+    ///         assert_eq!((1, 0), (e.index(), e.prefix()));
+    ///         assert_eq!(b"Paris", e.pattern());
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    #[must_use]
+    pub const fn reject_unreachable(mut self, enabled: bool) -> Self {
+        self.reject_unreachable = enabled;
+        self
+    }
+
+    /// Specifies whether to compress the alphabet into byte classes.
+    ///
+    /// By default, raw byte values are used as transition labels, so each block of the double
+    /// array has 256 elements. If this option is enabled, bytes appearing in patterns are mapped
+    /// to dense class numbers, and the other bytes are mapped to a single class. Since the block
+    /// length is reduced to the smallest power of two not less than the number of classes, the
+    /// double array can be packed more densely when patterns consist of a small number of distinct
+    /// bytes. The trade-off is a table lookup for each byte in matching and 256 bytes of heap for
+    /// the mapping table, which can be checked with
+    /// [`DoubleArrayAhoCorasick::heap_bytes()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to use byte classes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+    ///     .byte_classes(true)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("abcd");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 1, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    ///
+    /// let default_pma = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+    /// assert!(pma.heap_bytes() < default_pma.heap_bytes());
+    /// ```
+    #[must_use]
+    pub const fn byte_classes(mut self, enabled: bool) -> Self {
+        self.byte_classes = enabled;
+        self
+    }
+
     /// Specifies the number of last blocks to search bases.
     ///
     /// The smaller the number is, the faster the construction time will be;
@@ -114,6 +241,76 @@ impl DoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies the maximum number of states created in
+    /// [`DoubleArrayAhoCorasickBuilder::build_with_wildcards()`]. The default value is 1048576.
+    ///
+    /// The states are counted over all the patterns, excluding the root.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of states.
+    #[must_use]
+    pub const fn max_wildcard_states(mut self, n: u32) -> Self {
+        self.max_wildcard_states = n;
+        self
+    }
+
+    /// Checks input patterns and returns all the problems found, without building an automaton.
+    ///
+    /// Each problem has the index and the raw bytes of the pattern, and the problems are returned
+    /// in the order of the input. The following problems are reported:
+    ///   - empty patterns,
+    ///   - duplicate patterns, only under [`DuplicatePolicy::Error`],
+    ///   - patterns never reported because a prefix of them is registered earlier, only under
+    ///     [`MatchKind::LeftmostFirst`], or registered anywhere, only under
+    ///     [`MatchKind::LeftmostShortest`], and
+    ///   - patterns exceeding the scale limits of the input.
+    ///
+    /// Note that the scale of the resulting automaton is not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::PatternProblemKind;
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["Par", "", "Paris", "Par"];
+    /// let problems = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .validate(&patterns);
+    ///
+    /// assert_eq!(3, problems.len());
+    /// assert_eq!(1, problems[0].index());
+    /// assert_eq!(PatternProblemKind::Empty, problems[0].kind());
+    /// assert_eq!(2, problems[1].index());
+    /// assert_eq!(PatternProblemKind::Unreachable { prefix: 0 }, problems[1].kind());
+    /// assert_eq!(3, problems[2].index());
+    /// assert_eq!(PatternProblemKind::Duplicate { first: 0 }, problems[2].kind());
+    /// ```
+    pub fn validate<I, P>(&self, patterns: I) -> Vec<PatternProblem>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut nfa = NfaBuilder::new_validator(self.match_kind, self.duplicate_policy);
+        if self.match_kind.is_leftmost_shortest() {
+            let mut patterns: Vec<_> = patterns.into_iter().enumerate().collect();
+            patterns.sort_by_key(|(_, p)| p.as_ref().len());
+            for (index, pattern) in patterns {
+                nfa.validate(index, pattern.as_ref(), U24::MAX);
+            }
+        } else {
+            for (index, pattern) in patterns.into_iter().enumerate() {
+                nfa.validate(index, pattern.as_ref(), U24::MAX);
+            }
+        }
+        nfa.finish_validation()
+    }
+
     /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input patterns. The value `i` is
     /// automatically associated with `patterns[i]`.
     ///
@@ -126,7 +323,7 @@ impl DoubleArrayAhoCorasickBuilder {
     /// [`DaachorseError`] is returned when
     ///   - `patterns` is empty,
     ///   - `patterns` contains entries of length zero,
-    ///   - `patterns` contains duplicate entries,
+    ///   - `patterns` contains duplicate entries under [`DuplicatePolicy::Error`],
     ///   - the conversion from the index `i` to the specified type `V` fails,
     ///   - the scale of `patterns` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
@@ -179,7 +376,7 @@ impl DoubleArrayAhoCorasickBuilder {
     /// [`DaachorseError`] is returned when
     ///   - `patvals` is empty,
     ///   - `patvals` contains patterns of length zero,
-    ///   - `patvals` contains duplicate patterns,
+    ///   - `patvals` contains duplicate patterns under [`DuplicatePolicy::Error`],
     ///   - the scale of `patvals` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
     ///
@@ -212,7 +409,244 @@ impl DoubleArrayAhoCorasickBuilder {
         P: AsRef<[u8]>,
         V: Copy,
     {
-        let nfa = self.build_sparse_nfa(patvals)?;
+        let nfa = self.build_sparse_nfa(patvals, None::<fn(V, V) -> V>, false)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs,
+    /// merging the values of a repeated pattern with the given function.
+    ///
+    /// When a pattern is registered again, `merge` is called with the current value and the new
+    /// one, and the returned value replaces the current one. The duplicate policy specified in
+    /// [`DoubleArrayAhoCorasickBuilder::duplicate_policy`] is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    /// * `merge` - Function merging two values of the same pattern.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `patvals` is empty,
+    ///   - `patvals` contains patterns of length zero,
+    ///   - the scale of `patvals` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patvals = vec![("Paris", 1), ("Lyon", 2), ("Paris", 4)];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_values_merged(patvals, |a, b| a | b)
+    ///     .unwrap();
+    ///
+    /// let values: Vec<_> = pma.find_iter("Paris").map(|m| m.value()).collect();
+    /// assert_eq!(vec![5], values);
+    /// ```
+    pub fn build_with_values_merged<I, P, V, F>(
+        mut self,
+        patvals: I,
+        merge: F,
+    ) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<[u8]>,
+        V: Copy,
+        F: FnMut(V, V) -> V,
+    {
+        let nfa = self.build_sparse_nfa(patvals, Some(merge), false)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input tuples of a pattern, a value,
+    /// and a priority, for leftmost matching with custom priorities.
+    ///
+    /// Among patterns starting at the leftmost position, the one with the highest priority is
+    /// reported, and ties are broken by choosing the longest one. This is equivalent to registering
+    /// patterns in descending order of priority and length under [`MatchKind::LeftmostFirst`].
+    /// Duplicate patterns are handled according to
+    /// [`DoubleArrayAhoCorasickBuilder::duplicate_policy()`] in the same order, so the value with
+    /// the highest priority comes first.
+    ///
+    /// # Match kind
+    ///
+    /// **The match kind specified with [`DoubleArrayAhoCorasickBuilder::match_kind()`] is
+    /// ignored.** The resulting automaton always has [`MatchKind::LeftmostFirst`], so it supports
+    /// only [`DoubleArrayAhoCorasick::leftmost_find_iter()`] and the other leftmost searches.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvalpris` - List of tuples of a pattern, a value, and a priority.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`DoubleArrayAhoCorasickBuilder::build_with_values()`]. The indices in the error refer to
+    /// the order of `patvalpris`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::DoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patvalpris = vec![("apple", 0, 1), ("apple pie", 1, 1), ("Apple", 2, 5), ("pie", 3, 1)];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_priorities(patvalpris)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter("Apple pie and apple pie");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((6, 9, 3), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((14, 23, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build_with_priorities<I, P, V>(
+        mut self,
+        patvalpris: I,
+    ) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V, u32)>,
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        let mut patvalpris: Vec<_> = patvalpris.into_iter().enumerate().collect();
+        // Note: The stable sort keeps the input order of patterns with the same priority and
+        // length.
+        patvalpris.sort_by(|(_, (p1, _, r1)), (_, (p2, _, r2))| {
+            r2.cmp(r1)
+                .then_with(|| p2.as_ref().len().cmp(&p1.as_ref().len()))
+        });
+        let order: Vec<_> = patvalpris.iter().map(|&(i, _)| i).collect();
+        self.match_kind = MatchKind::LeftmostFirst;
+        self.build_with_values(patvalpris.into_iter().map(|(_, (p, v, _))| (p, v)))
+            .map_err(|e| e.map_pattern_indices(|i| order[i]))
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pairs of a pattern with
+    /// wildcards and character classes and a value.
+    ///
+    /// All the concrete patterns matched by each pattern are registered with the same value,
+    /// without being enumerated. See [`WildcardPattern`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`DoubleArrayAhoCorasickBuilder::build_with_values()`] for the concrete patterns, when a
+    /// pattern has an empty character class, or when more states than
+    /// [`DoubleArrayAhoCorasickBuilder::max_wildcard_states()`] are created. The indices in the
+    /// error refer to the order of `patvals`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, WildcardPattern};
+    ///
+    /// let patvals = vec![
+    ///     (WildcardPattern::new().literal("gr").one_of("ae").literal("y"), 0),
+    ///     (WildcardPattern::new().literal("v").any(), 1),
+    /// ];
+    /// let pma = DoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_wildcards(patvals)
+    ///     .unwrap();
+    ///
+    /// let matches: Vec<_> = pma
+    ///     .find_iter("grey v1 gray")
+    ///     .map(|m| (m.start(), m.end(), m.value()))
+    ///     .collect();
+    /// assert_eq!(vec![(0, 4, 0), (5, 7, 1), (8, 12, 0)], matches);
+    /// ```
+    pub fn build_with_wildcards<I, P, V>(mut self, patvals: I) -> Result<DoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: Borrow<WildcardPattern>,
+        V: Copy,
+    {
+        let patvals = patvals
+            .into_iter()
+            .map(|(pattern, value)| Ok((pattern.borrow().byte_alternatives()?, value)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut nfa = self.new_nfa(false);
+        nfa.add_all_alternatives(&patvals, self.max_wildcard_states)?;
+        let mut freqs = [0u32; 256];
+        if self.byte_classes {
+            for alternative in patvals.iter().flat_map(|(elements, _)| elements).flatten() {
+                for &c in alternative.as_ref() {
+                    freqs[usize::from(c)] = freqs[usize::from(c)].saturating_add(1);
+                }
+            }
+        }
+        let nfa = self.finish_nfa(nfa, &freqs)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Builds and returns a new [`DoubleArrayAhoCorasick`] from input pattern-value pairs,
+    /// together with the report of patterns that are never reported.
+    ///
+    /// Under [`MatchKind::LeftmostFirst`], a pattern is never reported if a prefix of it is
+    /// registered earlier. Under [`MatchKind::LeftmostShortest`], a pattern is never reported if
+    /// a prefix of it is registered. Such patterns are returned as [`PatternProblem`]s with
+    /// [`PatternProblemKind::Unreachable`](crate::errors::PatternProblemKind::Unreachable), which
+    /// has the index of the shadowing pattern. The report is always empty under the other match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`DoubleArrayAhoCorasickBuilder::build_with_values()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::PatternProblemKind;
+    /// use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patvals = vec![("Par", 0), ("Paris", 1)];
+    /// let (pma, report) = DoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .build_with_values_and_report(patvals)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(1, report.len());
+    /// assert_eq!(1, report[0].index());
+    /// assert_eq!(b"Paris", report[0].pattern());
+    /// assert_eq!(PatternProblemKind::Unreachable { prefix: 0 }, report[0].kind());
+    /// ```
+    pub fn build_with_values_and_report<I, P, V>(
+        mut self,
+        patvals: I,
+    ) -> Result<(DoubleArrayAhoCorasick<V>, Vec<PatternProblem>)>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<[u8]>,
+        V: Copy,
+    {
+        let mut nfa = self.build_sparse_nfa(patvals, None::<fn(V, V) -> V>, true)?;
+        let report = nfa.unreachable.take().unwrap_or_default();
+        Ok((self.build_from_nfa(nfa)?, report))
+    }
+
+    fn build_from_nfa<V>(
+        mut self,
+        nfa: BytewiseNfaBuilder<V>,
+    ) -> Result<DoubleArrayAhoCorasick<V>> {
         self.build_double_array(&nfa)?;
 
         // -1 is for dead state
@@ -221,22 +655,85 @@ impl DoubleArrayAhoCorasickBuilder {
 
         Ok(DoubleArrayAhoCorasick {
             states: self.states,
+            mapper: self.mapper,
             outputs: nfa.outputs,
             match_kind: self.match_kind,
             num_states,
         })
     }
 
-    fn build_sparse_nfa<I, P, V>(&self, patvals: I) -> Result<BytewiseNfaBuilder<V>>
+    fn build_sparse_nfa<I, P, V, F>(
+        &mut self,
+        patvals: I,
+        mut merge: Option<F>,
+        report_unreachable: bool,
+    ) -> Result<BytewiseNfaBuilder<V>>
     where
         I: IntoIterator<Item = (P, V)>,
         P: AsRef<[u8]>,
         V: Copy,
+        F: FnMut(V, V) -> V,
+    {
+        let mut nfa = self.new_nfa(report_unreachable);
+        let mut freqs = [0u32; 256];
+        let byte_classes = self.byte_classes;
+        let mut add = |index, pattern: P, value| -> Result<()> {
+            let pattern = pattern.as_ref();
+            nfa.add(index, pattern, value, merge.as_mut())?;
+            if byte_classes {
+                for &c in pattern {
+                    freqs[usize::from(c)] = freqs[usize::from(c)].saturating_add(1);
+                }
+            }
+            Ok(())
+        };
+        if self.match_kind.is_leftmost_shortest() {
+            // Registers shorter patterns earlier so that they shadow the longer ones.
+            let mut patvals: Vec<_> = patvals.into_iter().enumerate().collect();
+            patvals.sort_by_key(|(_, (p, _))| p.as_ref().len());
+            for (index, (pattern, value)) in patvals {
+                add(index, pattern, value)?;
+            }
+            if let Some(unreachable) = nfa.unreachable.as_mut() {
+                unreachable.sort_by_key(PatternProblem::index);
+            }
+        } else {
+            for (index, (pattern, value)) in patvals.into_iter().enumerate() {
+                add(index, pattern, value)?;
+            }
+        }
+        self.finish_nfa(nfa, &freqs)
+    }
+
+    fn new_nfa<V>(&self, report_unreachable: bool) -> BytewiseNfaBuilder<V>
+    where
+        V: Copy,
     {
-        let mut nfa = BytewiseNfaBuilder::new(self.match_kind);
-        for (pattern, value) in patvals {
-            nfa.add(pattern.as_ref(), value)?;
+        let mut nfa = BytewiseNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        if self.match_kind.shadows_extensions() {
+            nfa.reject_unreachable = self.reject_unreachable;
+            if report_unreachable {
+                nfa.unreachable = Some(vec![]);
+            }
         }
+        nfa
+    }
+
+    /// Builds the mapper from the frequencies of bytes in patterns, and the failure links and
+    /// outputs of the NFA.
+    fn finish_nfa<V>(
+        &mut self,
+        mut nfa: BytewiseNfaBuilder<V>,
+        freqs: &[u32; 256],
+    ) -> Result<BytewiseNfaBuilder<V>>
+    where
+        V: Copy,
+    {
+        self.mapper = if self.byte_classes {
+            ByteMapper::new(freqs)
+        } else {
+            ByteMapper::identity()
+        };
         if nfa.len == 0 {
             return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
         }
@@ -245,7 +742,9 @@ impl DoubleArrayAhoCorasickBuilder {
         }
         let q = match self.match_kind {
             MatchKind::Standard => nfa.build_fails(),
-            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => nfa.build_fails_leftmost(),
+            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst | MatchKind::LeftmostShortest => {
+                nfa.build_fails_leftmost()
+            }
         };
         nfa.build_outputs(&q);
         Ok(nfa)
@@ -274,7 +773,9 @@ impl DoubleArrayAhoCorasickBuilder {
             }
 
             labels.clear();
-            s.edges.keys().for_each(|&k| labels.push(k));
+            s.edges
+                .keys()
+                .for_each(|&k| labels.push(self.mapper.get(k)));
 
             let base = self.find_base(&labels, &helper);
             if usize::from_u32(base.get()) >= self.states.len() {
@@ -282,6 +783,7 @@ impl DoubleArrayAhoCorasickBuilder {
             }
 
             for (&c, &child_id) in &s.edges {
+                let c = self.mapper.get(c);
                 let child_idx = base.get() ^ u32::from(c);
                 helper.use_index(child_idx);
                 self.states[usize::from_u32(child_idx)].set_check(c);
@@ -323,9 +825,10 @@ impl DoubleArrayAhoCorasickBuilder {
     }
 
     fn init_array(&mut self) -> Result<BuildHelper> {
+        self.block_len = self.mapper.alphabet_size().next_power_of_two().max(2);
         self.states
-            .resize(usize::from_u32(BLOCK_LEN), State::default());
-        let mut helper = BuildHelper::new(BLOCK_LEN, self.num_free_blocks)?;
+            .resize(usize::from_u32(self.block_len), State::default());
+        let mut helper = BuildHelper::new(self.block_len, self.num_free_blocks)?;
         helper.push_block().unwrap();
         helper.use_index(ROOT_STATE_IDX);
         helper.use_index(DEAD_STATE_IDX);
@@ -340,7 +843,7 @@ impl DoubleArrayAhoCorasickBuilder {
                 return base;
             }
         }
-        // len() is not 0 since states has at least BLOCK_LEN items.
+        // len() is not 0 since states has at least block_len items.
         NonZeroU32::new(u32::try_from(self.states.len()).unwrap()).unwrap()
     }
 
@@ -359,7 +862,7 @@ impl DoubleArrayAhoCorasickBuilder {
     }
 
     fn extend_array(&mut self, helper: &mut BuildHelper) -> Result<()> {
-        if self.states.len() > usize::from_u32(u32::MAX - BLOCK_LEN) {
+        if self.states.len() > usize::from_u32(u32::MAX - self.block_len) {
             return Err(DaachorseError::automaton_scale("states.len()", u32::MAX));
         }
 
@@ -369,7 +872,7 @@ impl DoubleArrayAhoCorasickBuilder {
 
         helper.push_block()?;
         self.states.resize(
-            self.states.len() + usize::from_u32(BLOCK_LEN),
+            self.states.len() + usize::from_u32(self.block_len),
             State::default(),
         );
 
@@ -379,7 +882,9 @@ impl DoubleArrayAhoCorasickBuilder {
     /// Embeds valid CHECK values for all vacant elements in the block to avoid invalid transitions.
     fn remove_invalid_checks(&mut self, block_idx: u32, helper: &BuildHelper) {
         if let Some(unused_base) = helper.unused_base_in_block(block_idx) {
-            for c in u8::MIN..=u8::MAX {
+            // block_len - 1 always fits in u8 because block_len is at most 256.
+            let max_label = u8::try_from(self.block_len - 1).unwrap();
+            for c in u8::MIN..=max_label {
                 let idx = unused_base ^ u32::from(c);
                 if idx == ROOT_STATE_IDX || idx == DEAD_STATE_IDX || !helper.is_used_index(idx) {
                     self.states[usize::from_u32(idx)].set_check(c);
@@ -3,17 +3,22 @@
 use core::iter::Enumerate;
 use core::num::NonZeroU32;
 
+use alloc::collections::VecDeque;
+
 use crate::bytewise::DoubleArrayAhoCorasick;
 use crate::Match;
 
 use crate::bytewise::ROOT_STATE_IDX;
 use crate::utils::FromU32;
 
+pub use crate::segment::SegmentIterator;
+
 /// Iterator for some struct that implements [`AsRef<[u8]>`].
 #[doc(hidden)]
 pub struct U8SliceIterator<P> {
     inner: P,
     pos: usize,
+    end: usize,
 }
 
 impl<P> U8SliceIterator<P>
@@ -22,7 +27,18 @@ where
 {
     #[allow(clippy::missing_const_for_fn)]
     pub(crate) fn new(inner: P) -> Self {
-        Self { inner, pos: 0 }
+        let end = inner.as_ref().len();
+        Self { inner, pos: 0, end }
+    }
+
+    /// Creates an iterator of the bytes in `start..end`.
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn with_span(inner: P, start: usize, end: usize) -> Self {
+        Self {
+            inner,
+            pos: start,
+            end,
+        }
     }
 }
 
@@ -34,6 +50,9 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
         let ret = *self.inner.as_ref().get(self.pos)?;
         self.pos += 1;
         Some(ret)
@@ -44,6 +63,10 @@ where
 pub struct FindIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
     pub(crate) haystack: Enumerate<P>,
+    pub(crate) start: usize,
+    pub(crate) anchored: bool,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 impl<'a, P, V> Iterator for FindIterator<'a, P, V>
@@ -55,17 +78,59 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        let mut state_id = ROOT_STATE_IDX;
-        for (pos, c) in self.haystack.by_ref() {
-            // state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
-            if let Some(output_pos) = unsafe {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
                 self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(state_id))
-                    .output_pos()
-            } {
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let start = self.pos;
+        for (i, c) in self.haystack.by_ref() {
+            let pos = self.start + i + 1;
+            let output_pos = if self.anchored {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                if let Some(next_state_id) =
+                    unsafe { self.pma.next_state_id_anchored_unchecked(state_id, c) }
+                {
+                    state_id = next_state_id;
+                } else {
+                    // No more matches start at the current position.
+                    self.haystack.by_ref().for_each(|_| ());
+                    return None;
+                }
+                self.pma.anchored_output_pos(
+                    unsafe {
+                        self.pma
+                            .states
+                            .get_unchecked(usize::from_u32(state_id))
+                            .output_pos()
+                    },
+                    pos - start,
+                )
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_unchecked() ensures to return such a value.
+                state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
+                unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                }
+            };
+            if let Some(output_pos) = output_pos {
                 // output_pos is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
                 let out = unsafe {
@@ -73,9 +138,11 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
+                self.pos = pos;
+                self.output_pos = self.pma.next_value_pos(out);
                 return Some(Match {
                     length: usize::from_u32(out.length()),
-                    end: pos + 1,
+                    end: self.pos,
                     value: out.value(),
                 });
             }
@@ -113,7 +180,7 @@ impl<V: Copy> DoubleArrayAhoCorasick<V> {
         state_id: u32,
         pos: usize,
         c: u8,
-    ) -> (u32, OverlappingStepperIterator<V>) {
+    ) -> (u32, OverlappingStepperIterator<'_, V>) {
         // self.state_id is always smaller than self.pma.states.len() because
         // self.pma.next_state_id_unchecked() ensures to return such a value.
         let state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
@@ -159,6 +226,8 @@ impl<'a, V: Copy> Iterator for OverlappingStepperIterator<'a, V> {
 pub struct FindOverlappingIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
     pub(crate) haystack: Enumerate<P>,
+    pub(crate) start: usize,
+    pub(crate) anchored: bool,
     pub(crate) state_id: u32,
     pub(crate) pos: usize,
     pub(crate) output_pos: Option<NonZeroU32>,
@@ -182,23 +251,53 @@ where
                     .get_unchecked(usize::from_u32(output_pos.get() - 1))
             };
             self.output_pos = out.parent();
+            if self.anchored {
+                self.output_pos = self
+                    .pma
+                    .anchored_output_pos(self.output_pos, self.pos - self.start);
+            }
             return Some(Match {
                 length: usize::from_u32(out.length()),
                 end: self.pos,
                 value: out.value(),
             });
         }
-        for (pos, c) in self.haystack.by_ref() {
-            // self.state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_unchecked() ensures to return such a value.
-            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(self.state_id))
-                    .output_pos()
-            } {
-                self.pos = pos + 1;
+        for (i, c) in self.haystack.by_ref() {
+            let pos = self.start + i + 1;
+            let output_pos = if self.anchored {
+                // self.state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                if let Some(state_id) =
+                    unsafe { self.pma.next_state_id_anchored_unchecked(self.state_id, c) }
+                {
+                    self.state_id = state_id;
+                } else {
+                    // No more matches start at the beginning of the span.
+                    self.haystack.by_ref().for_each(|_| ());
+                    return None;
+                }
+                self.pma.anchored_output_pos(
+                    unsafe {
+                        self.pma
+                            .states
+                            .get_unchecked(usize::from_u32(self.state_id))
+                            .output_pos()
+                    },
+                    pos - self.start,
+                )
+            } else {
+                // self.state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_unchecked() ensures to return such a value.
+                self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
+                unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(self.state_id))
+                        .output_pos()
+                }
+            };
+            if let Some(output_pos) = output_pos {
+                self.pos = pos;
                 // output_pos.get() is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
                 let out = unsafe {
@@ -207,6 +306,11 @@ where
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
                 self.output_pos = out.parent();
+                if self.anchored {
+                    self.output_pos = self
+                        .pma
+                        .anchored_output_pos(self.output_pos, self.pos - self.start);
+                }
                 return Some(Match {
                     length: usize::from_u32(out.length()),
                     end: self.pos,
@@ -223,6 +327,8 @@ pub struct FindOverlappingNoSuffixIterator<'a, P, V> {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
     pub(crate) haystack: Enumerate<P>,
     pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 impl<'a, P, V> Iterator for FindOverlappingNoSuffixIterator<'a, P, V>
@@ -234,6 +340,22 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
         for (pos, c) in self.haystack.by_ref() {
             // self.state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
@@ -251,9 +373,11 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
+                self.pos = pos + 1;
+                self.output_pos = self.pma.next_value_pos(out);
                 return Some(Match {
                     length: usize::from_u32(out.length()),
-                    end: pos + 1,
+                    end: self.pos,
                     value: out.value(),
                 });
             }
@@ -269,7 +393,10 @@ where
 {
     pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
     pub(crate) haystack: P,
+    pub(crate) end: usize,
+    pub(crate) anchored: bool,
     pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 impl<'a, P, V> Iterator for LeftmostFindIterator<'a, P, V>
@@ -281,14 +408,40 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
         let mut state_id = ROOT_STATE_IDX;
         let mut last_output_pos: Option<NonZeroU32> = None;
 
+        let start = self.pos;
         let haystack = self.haystack.as_ref();
-        for (pos, &c) in haystack.iter().enumerate().skip(self.pos) {
-            // state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+        for (pos, &c) in haystack.iter().enumerate().take(self.end).skip(self.pos) {
+            if self.anchored {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                match unsafe { self.pma.next_state_id_anchored_unchecked(state_id, c) } {
+                    Some(next_state_id) => state_id = next_state_id,
+                    None => break,
+                }
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+                state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            }
             if state_id == ROOT_STATE_IDX {
                 if let Some(output_pos) = last_output_pos {
                     // last_output_pos is always smaller than self.pma.outputs.len() because
@@ -298,14 +451,112 @@ where
                             .outputs
                             .get_unchecked(usize::from_u32(output_pos.get() - 1))
                     };
+                    self.output_pos = self.pma.next_value_pos(out);
                     return Some(Match {
                         length: usize::from_u32(out.length()),
                         end: self.pos,
                         value: out.value(),
                     });
                 }
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // the transition functions ensure to return such a value.
+                let mut output_pos = unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                if self.anchored {
+                    output_pos = self.pma.anchored_output_pos(output_pos, pos + 1 - start);
+                }
+                if let Some(output_pos) = output_pos {
+                    last_output_pos.replace(output_pos);
+                    self.pos = pos + 1;
+                }
+            }
+        }
+
+        last_output_pos.map(|output_pos| {
+            // last_output_pos is always smaller than self.pma.outputs.len() because
+            // State::output_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            }
+        })
+    }
+}
+
+/// Iterator created by [`DoubleArrayAhoCorasick::leftmost_find_iter_from_segments()`].
+pub struct SegmentedLeftmostFindIterator<'a, P, V> {
+    pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: P,
+    pub(crate) lookahead: VecDeque<u8>,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<'a, P, V> Iterator for SegmentedLeftmostFindIterator<'a, P, V>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let mut last_output_pos: Option<NonZeroU32> = None;
+
+        // self.lookahead keeps the bytes read after the last output, which are scanned again
+        // from the end of the match. self.pos is the position of the front byte.
+        let mut i = 0;
+        loop {
+            if self.lookahead.len() == i {
+                match self.haystack.next() {
+                    Some(c) => self.lookahead.push_back(c),
+                    None => break,
+                }
+            }
+            let c = self.lookahead[i];
+            if last_output_pos.is_some() {
+                i += 1;
+            } else {
+                self.lookahead.pop_front();
+                self.pos += 1;
+            }
+
             // state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            if state_id == ROOT_STATE_IDX {
+                if last_output_pos.is_some() {
+                    break;
+                }
             } else if let Some(output_pos) = unsafe {
                 self.pma
                     .states
@@ -313,7 +564,9 @@ where
                     .output_pos()
             } {
                 last_output_pos.replace(output_pos);
-                self.pos = pos + 1;
+                self.lookahead.drain(..i);
+                self.pos += i;
+                i = 0;
             }
         }
 
@@ -325,6 +578,7 @@ where
                     .outputs
                     .get_unchecked(usize::from_u32(output_pos.get() - 1))
             };
+            self.output_pos = self.pma.next_value_pos(out);
             Match {
                 length: usize::from_u32(out.length()),
                 end: self.pos,
@@ -333,3 +587,234 @@ where
         })
     }
 }
+
+/// Iterator created by [`DoubleArrayAhoCorasick::find_iter_filtered()`].
+pub struct FilteredFindIterator<'a, P, V, F> {
+    pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: Enumerate<P>,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
+
+impl<'a, P, V, F> Iterator for FilteredFindIterator<'a, P, V, F>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // accepted_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self
+                .pma
+                .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        for (pos, c) in self.haystack.by_ref() {
+            // state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
+            let output_pos = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            if let Some(output_pos) = self.pma.accepted_output_pos(output_pos, &mut self.filter) {
+                // output_pos is always smaller than self.pma.outputs.len() because
+                // accepted_output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos = pos + 1;
+                self.output_pos = self
+                    .pma
+                    .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Iterator created by [`DoubleArrayAhoCorasick::find_overlapping_iter_filtered()`].
+pub struct FilteredFindOverlappingIterator<'a, P, V, F> {
+    pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: Enumerate<P>,
+    pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
+
+impl<'a, P, V, F> Iterator for FilteredFindOverlappingIterator<'a, P, V, F>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(output_pos) = self.output_pos {
+                // output_pos.get() is always smaller than self.pma.outputs.len() because
+                // accepted_output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.output_pos = self.pma.accepted_output_pos(out.parent(), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+
+            let (pos, c) = self.haystack.next()?;
+            self.pos = pos + 1;
+            // self.state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
+            let output_pos = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(self.state_id))
+                    .output_pos()
+            };
+            self.output_pos = self.pma.accepted_output_pos(output_pos, &mut self.filter);
+        }
+    }
+}
+
+/// Iterator created by [`DoubleArrayAhoCorasick::leftmost_find_iter_filtered()`].
+pub struct FilteredLeftmostFindIterator<'a, P, V, F>
+where
+    P: AsRef<[u8]>,
+{
+    pub(crate) pma: &'a DoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: P,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
+
+impl<'a, P, V, F> Iterator for FilteredLeftmostFindIterator<'a, P, V, F>
+where
+    P: AsRef<[u8]>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // accepted_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self
+                .pma
+                .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        // The fail links of the automaton assume that all the patterns are enabled, so the
+        // patterns starting at each position are examined by following only the trie edges.
+        let haystack = self.haystack.as_ref();
+        while self.pos < haystack.len() {
+            let mut state_id = ROOT_STATE_IDX;
+            let mut last_output_pos = None;
+            // self.pos is always smaller than haystack.len() in the loop.
+            for (depth, &c) in unsafe { haystack.get_unchecked(self.pos..) }
+                .iter()
+                .enumerate()
+            {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.child_index_unchecked() ensures to return such a value.
+                match unsafe {
+                    self.pma
+                        .child_index_unchecked(state_id, self.pma.mapper.get(c))
+                } {
+                    Some(child_id) => state_id = child_id,
+                    None => break,
+                }
+                let output_pos = unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                // output_pos.get() is always smaller than self.pma.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                // Only outputs as long as the depth are patterns starting at self.pos.
+                let output_pos = output_pos.filter(|pos| {
+                    let out = unsafe {
+                        self.pma
+                            .outputs
+                            .get_unchecked(usize::from_u32(pos.get() - 1))
+                    };
+                    usize::from_u32(out.length()) == depth + 1
+                });
+                if let Some(output_pos) = self.pma.accepted_value_pos(output_pos, &mut self.filter)
+                {
+                    last_output_pos = Some(output_pos);
+                }
+            }
+
+            if let Some(output_pos) = last_output_pos {
+                // last_output_pos is always smaller than self.pma.outputs.len() because
+                // accepted_value_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos += usize::from_u32(out.length());
+                self.output_pos = self
+                    .pma
+                    .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+            self.pos += 1;
+        }
+        None
+    }
+}
@@ -0,0 +1,200 @@
+use alloc::vec::Vec;
+
+use crate::serializer::Serializable;
+
+/// Mapper from bytes to byte classes.
+///
+/// `table` always has 256 elements so that the search loops look up a byte class without
+/// branching, even for the identity mapping. Bytes not appearing in patterns are mapped to the
+/// same class that is never used as a transition label.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde"))]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+pub struct ByteMapper {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serializer::byte_table_serde"))]
+    table: [u8; 256],
+    alphabet_size: u32,
+}
+
+impl Default for ByteMapper {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl ByteMapper {
+    /// Creates a mapper mapping each byte to itself.
+    pub const fn identity() -> Self {
+        let mut table = [0; 256];
+        let mut c = 0;
+        while c < 256 {
+            table[c] = c as u8;
+            c += 1;
+        }
+        Self {
+            table,
+            alphabet_size: 256,
+        }
+    }
+
+    /// Creates a mapper assigning classes to bytes in descending order of frequency.
+    ///
+    /// Bytes with zero `freqs[c]` are mapped to the last class.
+    pub fn new(freqs: &[u32; 256]) -> Self {
+        let sorted = {
+            let mut sorted = vec![];
+            for (c, &f) in freqs.iter().enumerate().filter(|(_, &f)| f != 0) {
+                sorted.push((c, f));
+            }
+            // Note: `c1.cmp(c2)` is necessary to uniquely determine the sort result.
+            sorted.sort_unstable_by(|(c1, f1), (c2, f2)| f2.cmp(f1).then_with(|| c1.cmp(c2)));
+            sorted
+        };
+        if sorted.len() == 256 {
+            return Self::identity();
+        }
+        let other = u8::try_from(sorted.len()).unwrap();
+        let mut table = [other; 256];
+        for (i, &(c, _)) in sorted.iter().enumerate() {
+            table[c] = u8::try_from(i).unwrap();
+        }
+        Self {
+            table,
+            alphabet_size: u32::from(other) + 1,
+        }
+    }
+
+    #[inline(always)]
+    pub const fn get(&self, c: u8) -> u8 {
+        self.table[c as usize]
+    }
+
+    /// Returns `true` if each byte is mapped to itself.
+    #[inline(always)]
+    pub const fn is_identity(&self) -> bool {
+        // With 255 bytes in patterns, the table is a permutation of 256 classes.
+        let mut c = 0;
+        while c < 256 {
+            if self.table[c] as usize != c {
+                return false;
+            }
+            c += 1;
+        }
+        true
+    }
+
+    /// Returns the number of classes, which is 256 for the identity mapping.
+    #[inline(always)]
+    pub const fn alphabet_size(&self) -> u32 {
+        self.alphabet_size
+    }
+}
+
+impl Serializable for ByteMapper {
+    #[inline(always)]
+    fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(&self.table);
+        self.alphabet_size.serialize_to_vec(dst);
+    }
+
+    #[inline(always)]
+    fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
+        let mut table = [0; 256];
+        table.copy_from_slice(&src[..256]);
+        let (alphabet_size, src) = u32::deserialize_from_slice(&src[256..]);
+        (
+            Self {
+                table,
+                alphabet_size,
+            },
+            src,
+        )
+    }
+
+    #[inline(always)]
+    fn serialized_bytes() -> usize {
+        256 + u32::serialized_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_mapper() {
+        let mut freqs = [0; 256];
+        freqs[usize::from(b'a')] = 3;
+        freqs[usize::from(b'b')] = 6;
+        freqs[usize::from(b'c')] = 3;
+        let mapper = ByteMapper::new(&freqs);
+
+        assert_eq!(mapper.get(b'a'), 1);
+        assert_eq!(mapper.get(b'b'), 0);
+        assert_eq!(mapper.get(b'c'), 2);
+        assert_eq!(mapper.get(b'd'), 3);
+        assert_eq!(mapper.get(0xff), 3);
+        assert_eq!(mapper.alphabet_size(), 4);
+        assert!(!mapper.is_identity());
+    }
+
+    #[test]
+    fn test_byte_mapper_full() {
+        let freqs = [1; 256];
+        let mapper = ByteMapper::new(&freqs);
+
+        assert_eq!(mapper, ByteMapper::identity());
+        for c in u8::MIN..=u8::MAX {
+            assert_eq!(mapper.get(c), c);
+        }
+        assert_eq!(mapper.alphabet_size(), 256);
+        assert!(mapper.is_identity());
+    }
+
+    #[test]
+    fn test_byte_mapper_255() {
+        let mut freqs = [1; 256];
+        freqs[usize::from(b'a')] = 2;
+        freqs[0xff] = 0;
+        let mapper = ByteMapper::new(&freqs);
+
+        assert_eq!(mapper.get(b'a'), 0);
+        assert_eq!(mapper.get(0), 1);
+        assert_eq!(mapper.get(0xfe), 0xfe);
+        assert_eq!(mapper.get(0xff), 0xff);
+        assert_eq!(mapper.alphabet_size(), 256);
+        assert!(!mapper.is_identity());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut freqs = [0; 256];
+        freqs[usize::from(b'a')] = 3;
+        freqs[usize::from(b'b')] = 6;
+        let mapper = ByteMapper::new(&freqs);
+
+        let mut data = vec![];
+        mapper.serialize_to_vec(&mut data);
+        assert_eq!(data.len(), ByteMapper::serialized_bytes());
+        let (other, rest) = ByteMapper::deserialize_from_slice(&data);
+        assert!(rest.is_empty());
+        assert_eq!(mapper, other);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut freqs = [0; 256];
+        freqs[usize::from(b'a')] = 3;
+        freqs[usize::from(b'b')] = 6;
+        let mapper = ByteMapper::new(&freqs);
+
+        let serialized = serde_json::to_string(&mapper).unwrap();
+        let other: ByteMapper = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(mapper, other);
+
+        let short = r#"{"table":[0,1],"alphabet_size":2}"#;
+        assert!(serde_json::from_str::<ByteMapper>(short).is_err());
+    }
+}
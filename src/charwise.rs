@@ -6,17 +6,27 @@ mod mapper;
 
 use core::mem;
 use core::num::NonZeroU32;
+use core::ops::ControlFlow;
 
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 
 use crate::errors::Result;
+use crate::fuzzy;
 use crate::serializer::{Serializable, SerializableVec};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{
+    CoveredSpanIterator, FuzzyMatch, GroupSet, IntoInput, Lattice, Match, MatchCounts, MatchKind,
+    Output, PositionIterator, ValuedCoveredSpanIterator,
+};
 pub use builder::CharwiseDoubleArrayAhoCorasickBuilder;
 use iter::{
-    CharWithEndOffsetIterator, FindIterator, FindOverlappingIterator,
-    FindOverlappingNoSuffixIterator, LeftmostFindIterator, StrIterator,
+    CharWithEndOffsetIterator, DecodedFindIterator, DecodedFindOverlappingIterator,
+    DecodedFindOverlappingNoSuffixIterator, DecodedLeftmostFindIterator, FilteredFindIterator,
+    FilteredFindOverlappingIterator, FilteredLeftmostFindIterator, FindIterator,
+    FindOverlappingIterator, FindOverlappingNoSuffixIterator, LeftmostFindIterator,
+    LossyUtf8CharWithEndOffsetIterator, SegmentIterator, SegmentedLeftmostFindIterator,
+    StrIterator, StrSegmentIterator, StrSegments, Utf16CharWithEndOffsetIterator,
 };
 use mapper::CodeMapper;
 
@@ -160,12 +170,187 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         CharwiseDoubleArrayAhoCorasickBuilder::new().build_with_values(patvals)
     }
 
-    /// Returns an iterator of non-overlapping matches in the given haystack.
+    /// Returns `true` if any pattern occurs in the given haystack.
+    ///
+    /// This returns as soon as the first match is found, and is supported by all the match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// assert!(pma.is_match("全世界中に"));
+    /// assert!(!pma.is_match("世の中"));
+    /// ```
+    #[must_use]
+    pub fn is_match<P>(&self, haystack: P) -> bool
+    where
+        P: AsRef<str>,
+    {
+        let mut state_id = ROOT_STATE_IDX;
+        for c in haystack.as_ref().chars() {
+            // The leftmost transition is the same as the standard one on standard automata, and
+            // dead fails are never followed before reaching an output.
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            }
+            .is_some()
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the match ending earliest in the given haystack. If multiple patterns end at the
+    /// position, the longest one is returned.
+    ///
+    /// This returns as soon as the first match is found, and is supported by all the match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let m = pma.find_earliest("この世界に").unwrap();
+    /// assert_eq!((6, 12, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, pma.find_earliest("世の中"));
+    /// ```
+    #[must_use]
+    pub fn find_earliest<P>(&self, haystack: P) -> Option<Match<V>>
+    where
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let mut state_id = ROOT_STATE_IDX;
+        for (pos, c) in haystack.as_ref().char_indices() {
+            // The leftmost transition is the same as the standard one on standard automata, and
+            // dead fails are never followed before reaching an output.
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if let Some(output_pos) = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                // output_pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: pos + c.len_utf8(),
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns the first match in the given haystack under the match kind specified in the
+    /// construction.
+    ///
+    /// The result is the same as the first item of [`Self::find_iter()`] under
+    /// [`MatchKind::Standard`] and of [`Self::leftmost_find_iter()`] under the other match
+    /// kinds.
     ///
     /// # Arguments
     ///
     /// * `haystack` - String to search for.
     ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let m = pma.find_first("全世界中に").unwrap();
+    /// assert_eq!((3, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = pma.find_earliest("全世界中に").unwrap();
+    /// assert_eq!((3, 6, 1), (m.start(), m.end(), m.value()));
+    /// ```
+    #[must_use]
+    pub fn find_first<P>(&self, haystack: P) -> Option<Match<V>>
+    where
+        P: AsRef<str>,
+        V: Copy,
+    {
+        if self.match_kind.is_standard() {
+            return self.find_earliest(haystack);
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let mut last_output = None;
+        for (pos, c) in haystack.as_ref().char_indices() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+            if state_id == ROOT_STATE_IDX {
+                if last_output.is_some() {
+                    break;
+                }
+            } else if let Some(output_pos) = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                last_output = Some((output_pos, pos + c.len_utf8()));
+            }
+        }
+
+        last_output.map(|(output_pos, end)| {
+            // output_pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            Match {
+                length: usize::from_u32(out.length()),
+                end,
+                value: out.value(),
+            }
+        })
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
     /// # Panics
     ///
     /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
@@ -189,17 +374,27 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn find_iter<P>(&self, haystack: P) -> FindIterator<'_, StrIterator<P>, V>
+    pub fn find_iter<H>(&self, haystack: H) -> FindIterator<'_, StrIterator<H::Haystack>, V>
     where
-        P: AsRef<str>,
+        H: IntoInput<str>,
     {
         assert!(
             self.match_kind.is_standard(),
             "Error: match_kind must be standard."
         );
+        let (haystack, span, anchored) = haystack.into_parts();
         FindIterator {
             pma: self,
-            haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
+            // The span is always on character boundaries because IntoInput ensures it.
+            haystack: unsafe {
+                CharWithEndOffsetIterator::with_offset(
+                    StrIterator::with_span(haystack, span.start, span.end),
+                    span.start,
+                )
+            },
+            anchored,
+            pos: span.start,
+            output_pos: None,
         }
     }
 
@@ -249,6 +444,9 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         FindIterator {
             pma: self,
             haystack: CharWithEndOffsetIterator::new(haystack),
+            anchored: false,
+            pos: 0,
+            output_pos: None,
         }
     }
 
@@ -256,7 +454,8 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// # Arguments
     ///
-    /// * `haystack` - String to search for.
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
     ///
     /// # Panics
     ///
@@ -284,22 +483,31 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn find_overlapping_iter<P>(
+    pub fn find_overlapping_iter<H>(
         &self,
-        haystack: P,
-    ) -> FindOverlappingIterator<'_, StrIterator<P>, V>
+        haystack: H,
+    ) -> FindOverlappingIterator<'_, StrIterator<H::Haystack>, V>
     where
-        P: AsRef<str>,
+        H: IntoInput<str>,
     {
         assert!(
             self.match_kind.is_standard(),
             "Error: match_kind must be standard."
         );
+        let (haystack, span, anchored) = haystack.into_parts();
         FindOverlappingIterator {
             pma: self,
-            haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
+            // The span is always on character boundaries because IntoInput ensures it.
+            haystack: unsafe {
+                CharWithEndOffsetIterator::with_offset(
+                    StrIterator::with_span(haystack, span.start, span.end),
+                    span.start,
+                )
+            },
+            start: span.start,
+            anchored,
             state_id: ROOT_STATE_IDX,
-            pos: 0,
+            pos: span.start,
             output_pos: None,
         }
     }
@@ -356,6 +564,8 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         FindOverlappingIterator {
             pma: self,
             haystack: CharWithEndOffsetIterator::new(haystack),
+            start: 0,
+            anchored: false,
             state_id: ROOT_STATE_IDX,
             pos: 0,
             output_pos: None,
@@ -412,6 +622,8 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             pma: self,
             haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
             state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
         }
     }
 
@@ -471,6 +683,8 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             pma: self,
             haystack: CharWithEndOffsetIterator::new(haystack),
             state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
         }
     }
 
@@ -491,12 +705,14 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// # Arguments
     ///
-    /// * `haystack` - String to search for.
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
     ///
     /// # Panics
     ///
-    /// If you do not specify [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostLongest`] in the
-    /// construction, the iterator is not supported and the function will call panic!.
+    /// If you do not specify [`MatchKind::LeftmostLongest`], [`MatchKind::LeftmostFirst`], or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and the
+    /// function will call panic!.
     ///
     /// # Examples
     ///
@@ -537,75 +753,137 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    pub fn leftmost_find_iter<P>(&self, haystack: P) -> LeftmostFindIterator<'_, P, V>
+    pub fn leftmost_find_iter<H>(&self, haystack: H) -> LeftmostFindIterator<'_, H::Haystack, V>
     where
-        P: AsRef<str>,
+        H: IntoInput<str>,
     {
         assert!(
             self.match_kind.is_leftmost(),
             "Error: match_kind must be leftmost."
         );
+        let (haystack, span, anchored) = haystack.into_parts();
         LeftmostFindIterator {
             pma: self,
             haystack,
-            pos: 0,
+            end: span.end,
+            anchored,
+            pos: span.start,
+            output_pos: None,
         }
     }
 
-    /// Returns the total number of states this automaton has.
+    /// Returns an iterator of non-overlapping matches in the given haystack split into
+    /// segments, such as the chunks of a rope.
     ///
-    /// # Examples
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries. To search segments splitting characters in the middle of their UTF-8
+    /// sequences, use [`Self::find_iter_from_segments_unchecked()`].
     ///
-    /// ```
-    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    /// # Arguments
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    /// * `segments` - Segments of the haystack to search for.
     ///
-    /// assert_eq!(pma.num_states(), 6);
-    /// ```
-    #[must_use]
-    pub fn num_states(&self) -> usize {
-        usize::from_u32(self.num_states)
-    }
-
-    /// Returns the total number of elements of the double array.
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
     ///
     /// # Examples
     ///
     /// ```
     /// use daachorse::CharwiseDoubleArrayAhoCorasick;
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
     ///
-    /// assert_eq!(pma.num_elements(), 8);
+    /// let mut it = pma.find_iter_from_segments(["全世", "界中", "に"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
     /// ```
-    #[must_use]
-    pub fn num_elements(&self) -> usize {
-        self.states.len()
+    pub fn find_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> FindIterator<'_, StrSegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // The concatenation of string segments is always a valid UTF-8 string.
+        unsafe { self.find_iter_from_segments_unchecked(StrSegments(segments.into_iter())) }
     }
 
-    /// Returns the total amount of heap used by this automaton in bytes.
+    /// Returns an iterator of non-overlapping matches in the given haystack split into byte
+    /// segments, which may split characters in the middle of their UTF-8 sequences.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Safety
+    ///
+    /// The concatenation of `segments` must be a valid UTF-8 string. Each segment alone need not
+    /// be valid UTF-8, so a character may be split between segments, but its bytes must be
+    /// complete and in order in the concatenation.
     ///
     /// # Examples
     ///
     /// ```
     /// use daachorse::CharwiseDoubleArrayAhoCorasick;
     ///
-    /// let patterns = vec!["bcd", "ab", "a"];
-    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
     ///
-    /// assert_eq!(568, pma.heap_bytes());
+    /// let bytes = "全世界中に".as_bytes();
+    /// let mut it = unsafe { pma.find_iter_from_segments_unchecked(bytes.chunks(2)) };
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
     /// ```
-    #[must_use]
-    pub fn heap_bytes(&self) -> usize {
-        self.states.len() * mem::size_of::<State>()
-            + self.mapper.heap_bytes()
-            + self.outputs.len() * mem::size_of::<Output<V>>()
+    pub unsafe fn find_iter_from_segments_unchecked<I, S>(
+        &self,
+        segments: I,
+    ) -> FindIterator<'_, SegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        self.find_iter_from_iter(SegmentIterator::new(segments.into_iter()))
     }
 
-    /// Serializes the automaton into a [`Vec`].
+    /// Returns an iterator of overlapping matches in the given haystack split into segments,
+    /// such as the chunks of a rope.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries. To search segments splitting characters in the middle of their UTF-8
+    /// sequences, use [`Self::find_overlapping_iter_from_segments_unchecked()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
     ///
     /// # Examples
     ///
@@ -613,43 +891,55 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     /// use daachorse::CharwiseDoubleArrayAhoCorasick;
     ///
     /// let patterns = vec!["全世界", "世界", "に"];
-    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
-    /// let bytes = pma.serialize();
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter_from_segments(["全世", "界中に"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
     /// ```
-    #[must_use]
-    pub fn serialize(&self) -> Vec<u8>
+    pub fn find_overlapping_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> FindOverlappingIterator<'_, StrSegmentIterator<I::IntoIter, S>, V>
     where
-        V: Serializable,
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
     {
-        let mut result = Vec::with_capacity(
-            self.states.serialized_bytes()
-                + self.mapper.serialized_bytes()
-                + self.outputs.serialized_bytes()
-                + MatchKind::serialized_bytes()
-                + u32::serialized_bytes(),
-        );
-        self.states.serialize_to_vec(&mut result);
-        self.mapper.serialize_to_vec(&mut result);
-        self.outputs.serialize_to_vec(&mut result);
-        self.match_kind.serialize_to_vec(&mut result);
-        self.num_states.serialize_to_vec(&mut result);
-        result
+        // The concatenation of string segments is always a valid UTF-8 string.
+        unsafe {
+            self.find_overlapping_iter_from_segments_unchecked(StrSegments(segments.into_iter()))
+        }
     }
 
-    /// Deserializes the automaton from a given slice.
+    /// Returns an iterator of overlapping matches in the given haystack split into byte
+    /// segments, which may split characters in the middle of their UTF-8 sequences.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries.
     ///
     /// # Arguments
     ///
-    /// * `source` - A source slice.
+    /// * `segments` - Segments of the haystack to search for.
     ///
-    /// # Returns
+    /// # Panics
     ///
-    /// A tuple of the automaton and the slice not used for the deserialization.
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
     ///
     /// # Safety
     ///
-    /// The given data must be a correct automaton exported by
-    /// [`CharwiseDoubleArrayAhoCorasick::serialize()`] function.
+    /// The concatenation of `segments` must be a valid UTF-8 string. Each segment alone need not
+    /// be valid UTF-8, so a character may be split between segments, but its bytes must be
+    /// complete and in order in the concatenation.
     ///
     /// # Examples
     ///
@@ -657,12 +947,10 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     /// use daachorse::CharwiseDoubleArrayAhoCorasick;
     ///
     /// let patterns = vec!["全世界", "世界", "に"];
-    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
-    /// let bytes = pma.serialize();
-    ///
-    /// let (pma, _) = unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
     ///
-    /// let mut it = pma.find_overlapping_iter("全世界中に");
+    /// let bytes = "全世界中に".as_bytes();
+    /// let mut it = unsafe { pma.find_overlapping_iter_from_segments_unchecked(bytes.chunks(4)) };
     ///
     /// let m = it.next().unwrap();
     /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
@@ -675,31 +963,1667 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
     ///
     /// assert_eq!(None, it.next());
     /// ```
-    #[must_use]
-    pub unsafe fn deserialize_unchecked(source: &[u8]) -> (Self, &[u8])
+    pub unsafe fn find_overlapping_iter_from_segments_unchecked<I, S>(
+        &self,
+        segments: I,
+    ) -> FindOverlappingIterator<'_, SegmentIterator<I::IntoIter, S>, V>
     where
-        V: Serializable,
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
     {
-        let (states, source) = Vec::<State>::deserialize_from_slice(source);
-        let (mapper, source) = CodeMapper::deserialize_from_slice(source);
-        let (outputs, source) = Vec::<Output<V>>::deserialize_from_slice(source);
-        let (match_kind, source) = MatchKind::deserialize_from_slice(source);
-        let (num_states, source) = u32::deserialize_from_slice(source);
-        (
-            Self {
-                states,
-                mapper,
-                outputs,
-                match_kind,
-                num_states,
-            },
-            source,
-        )
+        self.find_overlapping_iter_from_iter(SegmentIterator::new(segments.into_iter()))
     }
 
-    /// # Safety
+    /// Returns an iterator of leftmost matches in the given haystack split into segments, such
+    /// as the chunks of a rope.
     ///
-    /// `state_id` must be smaller than the length of states.
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries. The iterator buffers the characters read beyond the end of the
+    /// current match, which are at most as many as the longest pattern. To search segments
+    /// splitting characters in the middle of their UTF-8 sequences, use
+    /// [`Self::leftmost_find_iter_from_segments_unchecked()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostFirst`], [`MatchKind::LeftmostLongest`] or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and
+    /// the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter_from_segments(["世界中", "に世", "界"]);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 12, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 18, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_from_segments<I, S>(
+        &self,
+        segments: I,
+    ) -> SegmentedLeftmostFindIterator<'_, StrSegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // The concatenation of string segments is always a valid UTF-8 string.
+        unsafe {
+            self.leftmost_find_iter_from_segments_unchecked(StrSegments(segments.into_iter()))
+        }
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack split into byte segments,
+    /// which may split characters in the middle of their UTF-8 sequences.
+    ///
+    /// Match positions are offsets in the concatenation of the segments, and matches may cross
+    /// segment boundaries. The iterator buffers the characters read beyond the end of the
+    /// current match, which are at most as many as the longest pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments of the haystack to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostFirst`], [`MatchKind::LeftmostLongest`] or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and
+    /// the function will panic.
+    ///
+    /// # Safety
+    ///
+    /// The concatenation of `segments` must be a valid UTF-8 string. Each segment alone need not
+    /// be valid UTF-8, so a character may be split between segments, but its bytes must be
+    /// complete and in order in the concatenation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let bytes = "世界中に世界".as_bytes();
+    /// let mut it = unsafe { pma.leftmost_find_iter_from_segments_unchecked(bytes.chunks(5)) };
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 12, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 18, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub unsafe fn leftmost_find_iter_from_segments_unchecked<I, S>(
+        &self,
+        segments: I,
+    ) -> SegmentedLeftmostFindIterator<'_, SegmentIterator<I::IntoIter, S>, V>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        SegmentedLeftmostFindIterator {
+            pma: self,
+            haystack: CharWithEndOffsetIterator::new(SegmentIterator::new(segments.into_iter())),
+            lookahead: VecDeque::new(),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack with their
+    /// positions in bytes, characters, and lines and columns.
+    ///
+    /// The positions are counted in a second pass over the haystack that is interleaved with
+    /// the search. See [`PositionIterator`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter_with_positions("世界\n全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 1), (m.start().char(), m.end().char(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 6, 0), (m.start().char(), m.end().char(), m.value()));
+    /// assert_eq!((1, 0), (m.start().line(), m.start().column()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 2), (m.start().line(), m.start().column(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_with_positions<'h>(
+        &self,
+        haystack: &'h str,
+    ) -> PositionIterator<'h, FindIterator<'_, StrIterator<&'h str>, V>> {
+        PositionIterator::new(haystack, self.find_iter(haystack))
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack with their positions
+    /// in bytes, characters, and lines and columns.
+    ///
+    /// The positions are counted in a second pass over the haystack that is interleaved with
+    /// the search. See [`PositionIterator`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter_with_positions("\n全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 0, 0), (m.start().line(), m.start().column(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 1, 1), (m.start().line(), m.start().column(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 4, 2), (m.start().line(), m.start().column(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_with_positions<'h>(
+        &self,
+        haystack: &'h str,
+    ) -> PositionIterator<'h, FindOverlappingIterator<'_, StrIterator<&'h str>, V>> {
+        PositionIterator::new(haystack, self.find_overlapping_iter(haystack))
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack with their positions in
+    /// bytes, characters, and lines and columns.
+    ///
+    /// The positions are counted in a second pass over the haystack that is interleaved with
+    /// the search. See [`PositionIterator`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostFirst`], [`MatchKind::LeftmostLongest`] or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and
+    /// the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter_with_positions("世界中に\n世界");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 4, 2), (m.start().char(), m.end().char(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((5, 7, 0), (m.start().char(), m.end().char(), m.value()));
+    /// assert_eq!((1, 0), (m.start().line(), m.start().column()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_with_positions<'h>(
+        &self,
+        haystack: &'h str,
+    ) -> PositionIterator<'h, LeftmostFindIterator<'_, &'h str, V>> {
+        PositionIterator::new(haystack, self.leftmost_find_iter(haystack))
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given haystack, skipping patterns
+    /// whose values are rejected by `filter`.
+    ///
+    /// The matches are the same as those of [`Self::find_iter()`] on the automaton built
+    /// without the rejected patterns, which allows one automaton to be shared by searches using
+    /// different subsets of patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_iter_filtered("全世界中に", |v| v != 0);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredFindIterator<'_, StrIterator<P>, V, F>
+    where
+        P: AsRef<str>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FilteredFindIterator {
+            pma: self,
+            haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack, skipping patterns whose
+    /// values are rejected by `filter`.
+    ///
+    /// The automaton built without values stores the pattern indices as values, so a bitset over
+    /// pattern IDs can be given as a predicate looking up the bit of each value, as in the example
+    /// below.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let enabled = [false, true, true];
+    /// let filter = |v: u32| enabled[v as usize];
+    /// let mut it = pma.find_overlapping_iter_filtered("全世界中に", filter);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredFindOverlappingIterator<'_, StrIterator<P>, V, F>
+    where
+        P: AsRef<str>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        FilteredFindOverlappingIterator {
+            pma: self,
+            haystack: unsafe { CharWithEndOffsetIterator::new(StrIterator::new(haystack)) },
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns an iterator of leftmost-longest matches in the given haystack, skipping patterns
+    /// whose values are rejected by `filter`.
+    ///
+    /// When the longest pattern is rejected, the longest one among the enabled patterns starting
+    /// at the same position is reported instead. The matches are the same as those of
+    /// [`Self::leftmost_find_iter()`] on the automaton built without the rejected patterns.
+    ///
+    /// Unlike [`Self::leftmost_find_iter()`], this takes `O(nm)` time in the worst case, where
+    /// `n` is the length of `haystack` and `m` is the maximum length of the patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `filter` - Predicate returning `true` for the values of enabled patterns.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`] in the construction, the iterator is
+    /// not supported and the function will call panic!.
+    /// [`MatchKind::LeftmostFirst`] and [`MatchKind::LeftmostShortest`] are rejected because
+    /// patterns shadowed by other patterns are discarded in the construction, so the next
+    /// preferred pattern may not be in the automaton when the preferred one is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter_filtered("世界中に", |v| v != 2);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 6, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_filtered<P, F>(
+        &self,
+        haystack: P,
+        filter: F,
+    ) -> FilteredLeftmostFindIterator<'_, P, V, F>
+    where
+        P: AsRef<str>,
+        F: FnMut(V) -> bool,
+    {
+        assert!(
+            self.match_kind == MatchKind::LeftmostLongest,
+            "Error: match_kind must be leftmost-longest."
+        );
+        FilteredLeftmostFindIterator {
+            pma: self,
+            haystack,
+            pos: 0,
+            output_pos: None,
+            filter,
+        }
+    }
+
+    /// Returns the set of groups having patterns that occur in the given haystack.
+    ///
+    /// The group of each pattern is given by `group_of` applied to its value, so the group IDs
+    /// are independent of the values. The patterns occurring in the haystack are those reported
+    /// by [`Self::find_overlapping_iter()`] under [`MatchKind::Standard`] and by
+    /// [`Self::leftmost_find_iter()`] under the other match kinds. The search stops as soon as all
+    /// the groups are found. Group IDs not smaller than `num_groups` are ignored.
+    ///
+    /// To make a pattern belong to multiple groups, register it repeatedly with
+    /// [`DuplicatePolicy::KeepAll`](crate::DuplicatePolicy::KeepAll) and map each value to a
+    /// group.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `num_groups` - The number of groups.
+    /// * `group_of` - Function returning the group ID of a pattern from its value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["りんご", "オレンジ", "猫"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// // "fruit" = 0, "color" = 1, "animal" = 2
+    /// let pattern_groups = [0, 1, 2];
+    ///
+    /// let groups = pma.matched_groups("オレンジ色の猫", 3, |i| pattern_groups[i]);
+    /// assert_eq!(vec![1, 2], groups.iter().collect::<Vec<_>>());
+    ///
+    /// let groups = pma.matched_groups("りんごの木", 3, |i| pattern_groups[i]);
+    /// assert_eq!(vec![0], groups.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn matched_groups<P, F>(&self, haystack: P, num_groups: usize, mut group_of: F) -> GroupSet
+    where
+        P: AsRef<str>,
+        V: Copy,
+        F: FnMut(V) -> usize,
+    {
+        let mut groups = GroupSet::new(num_groups);
+        if groups.is_full() {
+            return groups;
+        }
+        if !self.match_kind.is_standard() {
+            for m in self.leftmost_find_iter(haystack) {
+                let group = group_of(m.value());
+                if group < num_groups && groups.insert(group) && groups.is_full() {
+                    break;
+                }
+            }
+            return groups;
+        }
+        let mut state_id = ROOT_STATE_IDX;
+        for c in haystack.as_ref().chars() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+            let mut output_pos = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and Output::parent() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                let group = group_of(out.value());
+                if group < num_groups && groups.insert(group) && groups.is_full() {
+                    return groups;
+                }
+                output_pos = out.parent();
+            }
+        }
+        groups
+    }
+
+    /// Counts non-overlapping matches in the given haystack for each value, and adds them to
+    /// `counts`.
+    ///
+    /// The matches are the same as those of [`Self::find_iter()`] under [`MatchKind::Standard`]
+    /// and of [`Self::leftmost_find_iter()`] under the other match kinds. Since the counts are
+    /// accumulated, they can be collected across multiple haystacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `counts` - Collection to accumulate the numbers of matches, such as [`Vec<u64>`] indexed
+    ///   by values. See [`MatchCounts`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let mut counts = vec![0; 3];
+    /// pma.count_matches("全世界中に", &mut counts);
+    /// pma.count_matches("世界に", &mut counts);
+    /// assert_eq!(vec![1, 1, 2], counts);
+    /// ```
+    pub fn count_matches<P, C>(&self, haystack: P, counts: &mut C)
+    where
+        P: AsRef<str>,
+        V: Copy,
+        C: MatchCounts<V>,
+    {
+        if self.match_kind.is_standard() {
+            self.find_iter(haystack)
+                .for_each(|m| counts.increment(m.value()));
+        } else {
+            self.leftmost_find_iter(haystack)
+                .for_each(|m| counts.increment(m.value()));
+        }
+    }
+
+    /// Counts overlapping matches in the given haystack for each value, and adds them to
+    /// `counts`.
+    ///
+    /// Since the counts are accumulated, they can be collected across multiple haystacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `counts` - Collection to accumulate the numbers of matches, such as [`Vec<u64>`] indexed
+    ///   by values. See [`MatchCounts`] for details.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the function is not
+    /// supported and will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patvals = vec![("全世界", 'x'), ("世界", 'y'), ("に", 'z')];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::with_values(patvals).unwrap();
+    ///
+    /// let mut counts = BTreeMap::new();
+    /// pma.count_overlapping_matches("全世界中に", &mut counts);
+    /// pma.count_overlapping_matches("世界に", &mut counts);
+    /// assert_eq!(vec![('x', 1), ('y', 2), ('z', 2)], counts.into_iter().collect::<Vec<_>>());
+    /// ```
+    pub fn count_overlapping_matches<P, C>(&self, haystack: P, counts: &mut C)
+    where
+        P: AsRef<str>,
+        V: Copy,
+        C: MatchCounts<V>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        let mut state_id = ROOT_STATE_IDX;
+        for c in haystack.as_ref().chars() {
+            // state_id is always smaller than self.states.len() because
+            // self.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+            let mut output_pos = unsafe {
+                self.states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and Output::parent() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                counts.increment(out.value());
+                output_pos = out.parent();
+            }
+        }
+    }
+
+    /// Returns an iterator of the merged spans covered by the overlapping matches in the given
+    /// haystack, which is useful for highlighting.
+    ///
+    /// The spans are non-overlapping, non-adjacent, and in ascending order. See
+    /// [`CoveredSpanIterator`] for details.
+    ///
+    /// The length of the longest pattern is computed on every call, which takes time linear in
+    /// the number of patterns. To search many short haystacks, compute it once and create
+    /// [`CoveredSpanIterator`] directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let spans: Vec<_> = pma.covered_spans("全世界中に").collect();
+    /// assert_eq!(vec![0..9, 12..15], spans);
+    /// ```
+    pub fn covered_spans<H>(
+        &self,
+        haystack: H,
+    ) -> CoveredSpanIterator<FindOverlappingIterator<'_, StrIterator<H::Haystack>, V>>
+    where
+        H: IntoInput<str>,
+        V: Copy,
+    {
+        CoveredSpanIterator::new(
+            self.find_overlapping_iter(haystack),
+            self.max_pattern_length(),
+        )
+    }
+
+    /// Returns an iterator of the spans covered by the overlapping matches in the given
+    /// haystack, annotated with the values of the matches covering each span.
+    ///
+    /// The spans are non-overlapping and in ascending order, and adjacent spans have different
+    /// values. See [`ValuedCoveredSpanIterator`] for details.
+    ///
+    /// The length of the longest pattern is computed on every call, which takes time linear in
+    /// the number of patterns. To search many short haystacks, compute it once and create
+    /// [`ValuedCoveredSpanIterator`] directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span and
+    ///   anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let spans: Vec<_> = pma.covered_spans_with_values("全世界中に").collect();
+    /// assert_eq!(
+    ///     vec![(0..3, vec![0]), (3..9, vec![0, 1]), (12..15, vec![2])],
+    ///     spans,
+    /// );
+    /// ```
+    pub fn covered_spans_with_values<H>(
+        &self,
+        haystack: H,
+    ) -> ValuedCoveredSpanIterator<FindOverlappingIterator<'_, StrIterator<H::Haystack>, V>, V>
+    where
+        H: IntoInput<str>,
+        V: Copy + Ord,
+    {
+        ValuedCoveredSpanIterator::new(
+            self.find_overlapping_iter(haystack),
+            self.max_pattern_length(),
+        )
+    }
+
+    /// Builds a lattice of the overlapping matches in the given haystack, indexed by their
+    /// starting positions.
+    ///
+    /// The edges outgoing from each position are in ascending order of their ends. See
+    /// [`Lattice`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the lattice is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["世界中", "全世", "全"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let lattice = pma.build_lattice("全世界中");
+    ///
+    /// let edges: Vec<_> = lattice.edges(0).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(3, 2), (6, 1)], edges);
+    ///
+    /// let edges: Vec<_> = lattice.edges(3).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(12, 0)], edges);
+    /// ```
+    pub fn build_lattice<P>(&self, haystack: P) -> Lattice<V>
+    where
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let haystack = haystack.as_ref();
+        Lattice::new(haystack.len(), self.find_overlapping_iter(haystack))
+    }
+
+    /// Builds a lattice of the overlapping matches in the given haystack, filling each maximal
+    /// span covered by no match with an unknown-word edge.
+    ///
+    /// See [`Lattice::with_unknown()`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `unknown` - Value associated with the unknown-word edges.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the lattice is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["世界中", "全世", "全"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let lattice = pma.build_lattice_with_unknown("大大全世界中", u32::MAX);
+    ///
+    /// let edges: Vec<_> = lattice.edges(0).iter().map(|m| (m.end(), m.value())).collect();
+    /// assert_eq!(vec![(6, u32::MAX)], edges);
+    /// ```
+    pub fn build_lattice_with_unknown<P>(&self, haystack: P, unknown: V) -> Lattice<V>
+    where
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let haystack = haystack.as_ref();
+        Lattice::with_unknown(
+            haystack.len(),
+            self.find_overlapping_iter(haystack),
+            unknown,
+        )
+    }
+
+    /// Returns the approximate matches of the patterns in the given haystack, which are
+    /// substrings within the Levenshtein distance `max_distance` of the patterns.
+    ///
+    /// The automaton's trie is traversed while updating the edit distances between the
+    /// patterns and the substrings from each starting position, so variants of the patterns
+    /// are never enumerated. For each starting position and each pattern, the substring with
+    /// the minimum distance is reported, and the longest one is chosen when several
+    /// substrings have the minimum distance. Since the substrings are non-empty, a pattern
+    /// whose length is at most `max_distance` matches at every starting position.
+    ///
+    /// The matches are ordered by their starting positions and then by their ending
+    /// positions, and the matches of a pattern starting at different positions can overlap.
+    /// The time grows rapidly with `max_distance`, so it is intended for small distances such
+    /// as 1 or 2. In addition, the children of all the states are listed once per call, which
+    /// takes time linear in the number of states.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `max_distance` - Maximum edit distance in characters.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the search is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["東京都", "京都"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// let matches: Vec<_> = pma
+    ///     .find_fuzzy("東京部", 1)
+    ///     .iter()
+    ///     .map(|m| (m.start(), m.end(), m.distance(), m.value()))
+    ///     .collect();
+    ///
+    /// assert_eq!(vec![(0, 9, 1, 0), (3, 9, 1, 1)], matches);
+    /// ```
+    pub fn find_fuzzy<P>(&self, haystack: P, max_distance: usize) -> Vec<FuzzyMatch<V>>
+    where
+        P: AsRef<str>,
+        V: Copy,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        let haystack = haystack.as_ref();
+        let mut offsets = vec![];
+        let mut labels = vec![];
+        for (pos, c) in haystack.char_indices() {
+            offsets.push(pos);
+            labels.push(self.mapper.get(c));
+        }
+        offsets.push(haystack.len());
+        let (child_offsets, child_edges) = self.children();
+        let mut matches = vec![];
+        // Each element has a state id, the depth, and the offset of its row in `rows`.
+        let mut stack = vec![];
+        let mut rows = vec![];
+        let mut row = vec![];
+        let mut new_row = vec![];
+        for start in 0..labels.len() {
+            let suffix = &labels[start..];
+            let first = matches.len();
+            rows.clear();
+            rows.extend(0..=max_distance.min(suffix.len()));
+            stack.push((ROOT_STATE_IDX, 0, 0));
+            while let Some((state_id, depth, offset)) = stack.pop() {
+                row.clear();
+                row.extend_from_slice(&rows[offset..]);
+                rows.truncate(offset);
+                let exact_children: Vec<(u32, u32)>;
+                let children = if row.iter().all(|&d| d >= max_distance) {
+                    exact_children = fuzzy::exact_labels(&row, suffix, max_distance)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|mapped_c| {
+                            // state_id is always smaller than self.states.len() because it is
+                            // the root or a child id.
+                            // mapped_c is always smaller than the alphabet size of the mapper.
+                            unsafe { self.child_index_unchecked(state_id, mapped_c) }
+                                .map(|child_id| (mapped_c, child_id))
+                        })
+                        .collect();
+                    &exact_children
+                } else {
+                    let state_idx = usize::from_u32(state_id);
+                    &child_edges[child_offsets[state_idx]..child_offsets[state_idx + 1]]
+                };
+                for &(mapped_c, child_id) in children {
+                    let min = fuzzy::next_row(
+                        &row,
+                        suffix,
+                        Some(mapped_c),
+                        max_distance.saturating_add(depth + 1),
+                        &mut new_row,
+                    );
+                    if min > max_distance {
+                        continue;
+                    }
+                    // child_id is always smaller than self.states.len() because
+                    // self.children() and self.child_index_unchecked() ensure to return such a
+                    // value.
+                    if let Some(output_pos) = unsafe { self.own_output_pos(child_id) } {
+                        if let Some((len, distance)) = fuzzy::best_end(&new_row, max_distance) {
+                            let mut output_pos = Some(output_pos);
+                            while let Some(pos) = output_pos {
+                                // pos.get() is always smaller than self.outputs.len() because
+                                // State::output_pos() and next_value_pos() ensure to return
+                                // such a value.
+                                let out = unsafe {
+                                    self.outputs.get_unchecked(usize::from_u32(pos.get() - 1))
+                                };
+                                matches.push(FuzzyMatch::new(
+                                    offsets[start],
+                                    offsets[start + len],
+                                    distance,
+                                    out.value(),
+                                ));
+                                output_pos = self.next_value_pos(out);
+                            }
+                        }
+                    }
+                    stack.push((child_id, depth + 1, rows.len()));
+                    rows.extend_from_slice(&new_row);
+                }
+            }
+            matches[first..].sort_by_key(FuzzyMatch::end);
+        }
+        matches
+    }
+
+    /// Calls `f` on each match in the given haystack until it returns [`ControlFlow::Break`].
+    ///
+    /// The matches are the same as those of [`Self::find_overlapping_iter()`] under
+    /// [`MatchKind::Standard`] and of [`Self::leftmost_find_iter()`] under the other match
+    /// kinds. Since the automaton is driven in a single loop, this is faster than the
+    /// iterators, and stops immediately when `f` returns [`ControlFlow::Break`], even in the
+    /// middle of the matches ending at the same position.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    /// * `f` - Function called on each match.
+    ///
+    /// # Returns
+    ///
+    /// The value returned by `f` if it breaks, or [`ControlFlow::Continue`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::ops::ControlFlow;
+    ///
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut matches = vec![];
+    /// let result = pma.for_each_match("全世界中に", |m| {
+    ///     matches.push((m.start(), m.end(), m.value()));
+    ///     if m.value() == 1 {
+    ///         ControlFlow::Break(m.end())
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(ControlFlow::Break(9), result);
+    /// assert_eq!(vec![(0, 9, 0), (3, 9, 1)], matches);
+    /// ```
+    pub fn for_each_match<P, F, B>(&self, haystack: P, mut f: F) -> ControlFlow<B>
+    where
+        P: AsRef<str>,
+        V: Copy,
+        F: FnMut(Match<V>) -> ControlFlow<B>,
+    {
+        if self.match_kind.is_standard() {
+            let mut state_id = ROOT_STATE_IDX;
+            for (pos, c) in haystack.as_ref().char_indices() {
+                // state_id is always smaller than self.states.len() because
+                // self.next_state_id_unchecked() ensures to return such a value.
+                state_id = unsafe { self.next_state_id_unchecked(state_id, c) };
+                let mut output_pos = unsafe {
+                    self.states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                let end = pos + c.len_utf8();
+                while let Some(pos) = output_pos {
+                    // pos.get() is always smaller than self.outputs.len() because
+                    // State::output_pos() and Output::parent() ensure to return such a value.
+                    let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                    f(Match {
+                        length: usize::from_u32(out.length()),
+                        end,
+                        value: out.value(),
+                    })?;
+                    output_pos = out.parent();
+                }
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let haystack = haystack.as_ref();
+        let mut start = 0;
+        loop {
+            let mut state_id = ROOT_STATE_IDX;
+            let mut last_output_pos = None;
+            let mut end = start;
+            // start is always a position in haystack.
+            for (i, c) in unsafe { haystack.get_unchecked(start..) }.char_indices() {
+                // state_id is always smaller than self.states.len() because
+                // self.next_state_id_leftmost_unchecked() ensures to return such a value.
+                state_id = unsafe { self.next_state_id_leftmost_unchecked(state_id, c) };
+                if state_id == ROOT_STATE_IDX {
+                    if last_output_pos.is_some() {
+                        break;
+                    }
+                } else if let Some(output_pos) = unsafe {
+                    self.states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                } {
+                    last_output_pos = Some(output_pos);
+                    end = start + i + c.len_utf8();
+                }
+            }
+
+            let mut output_pos = match last_output_pos {
+                Some(output_pos) => Some(output_pos),
+                None => return ControlFlow::Continue(()),
+            };
+            while let Some(pos) = output_pos {
+                // pos.get() is always smaller than self.outputs.len() because
+                // State::output_pos() and next_value_pos() ensure to return such a value.
+                let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+                f(Match {
+                    length: usize::from_u32(out.length()),
+                    end,
+                    value: out.value(),
+                })?;
+                output_pos = self.next_value_pos(out);
+            }
+            start = end;
+        }
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given UTF-16 haystack.
+    ///
+    /// The positions of matches are reported in UTF-16 code units.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - UTF-16 string to search for.
+    /// * `invalid` - How to handle unpaired surrogates.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let haystack: Vec<u16> = "全世界中に".encode_utf16().collect();
+    /// let mut it = pma.find_iter_utf16(&haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_utf16<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindIterator<'_, Utf16CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u16]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindIterator {
+            pma: self,
+            haystack: Utf16CharWithEndOffsetIterator::new(haystack, invalid),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches in the given UTF-16 haystack.
+    ///
+    /// The positions of matches are reported in UTF-16 code units.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - UTF-16 string to search for.
+    /// * `invalid` - How to handle unpaired surrogates.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["😀", "a😀", "\u{fffd}"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// // "a😀" followed by an unpaired high surrogate
+    /// let haystack = [0x0061, 0xd83d, 0xde00, 0xd83d];
+    /// let mut it = pma.find_overlapping_iter_utf16(&haystack, InvalidSequence::Replace);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 3, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 4, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_utf16<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindOverlappingIterator<'_, Utf16CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u16]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindOverlappingIterator {
+            pma: self,
+            haystack: Utf16CharWithEndOffsetIterator::new(haystack, invalid),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given UTF-16 haystack.
+    ///
+    /// The positions of matches are reported in UTF-16 code units.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - UTF-16 string to search for.
+    /// * `invalid` - How to handle unpaired surrogates.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let haystack: Vec<u16> = "全世界中に".encode_utf16().collect();
+    /// let mut it = pma.find_overlapping_no_suffix_iter_utf16(&haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 3, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_no_suffix_iter_utf16<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindOverlappingNoSuffixIterator<'_, Utf16CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u16]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindOverlappingNoSuffixIterator {
+            pma: self,
+            haystack: Utf16CharWithEndOffsetIterator::new(haystack, invalid),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of leftmost matches in the given UTF-16 haystack.
+    ///
+    /// The positions of matches are reported in UTF-16 code units. See
+    /// [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter()`] for the match semantics.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - UTF-16 string to search for.
+    /// * `invalid` - How to handle unpaired surrogates.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`], [`MatchKind::LeftmostFirst`], or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and the
+    /// function will call panic!.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// let haystack: Vec<u16> = "全世界中に".encode_utf16().collect();
+    /// let mut it = pma.leftmost_find_iter_utf16(&haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_utf16<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedLeftmostFindIterator<'_, Utf16CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u16]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        DecodedLeftmostFindIterator {
+            pma: self,
+            haystack: Utf16CharWithEndOffsetIterator::new(haystack, invalid),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of non-overlapping matches in the given byte sequence, which may
+    /// contain invalid UTF-8 sequences.
+    ///
+    /// The haystack is decoded as UTF-8 on the fly, and each byte that is not a part of a valid
+    /// character is handled as one invalid code unit according to `invalid`. The positions of
+    /// matches are reported in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte sequence to search for.
+    /// * `invalid` - How to handle invalid bytes.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// // "全世界中に" with an invalid byte 0xff after "全"
+    /// let haystack = b"\xe5\x85\xa8\xff\xe4\xb8\x96\xe7\x95\x8c\xe4\xb8\xad\xe3\x81\xab";
+    /// let mut it = pma.find_iter_lossy(haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((4, 10, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((13, 16, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_iter_lossy<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindIterator<'_, LossyUtf8CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindIterator {
+            pma: self,
+            haystack: LossyUtf8CharWithEndOffsetIterator::new(haystack, invalid),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches in the given byte sequence, which may contain
+    /// invalid UTF-8 sequences.
+    ///
+    /// The haystack is decoded as UTF-8 on the fly, and each byte that is not a part of a valid
+    /// character is handled as one invalid code unit according to `invalid`. The positions of
+    /// matches are reported in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte sequence to search for.
+    /// * `invalid` - How to handle invalid bytes.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["a\u{fffd}", "\u{fffd}\u{fffd}"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let haystack = b"a\xff\xe3\x81";
+    /// let mut it = pma.find_overlapping_iter_lossy(haystack, InvalidSequence::Replace);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 3, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((2, 4, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_iter_lossy<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindOverlappingIterator<'_, LossyUtf8CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindOverlappingIterator {
+            pma: self,
+            haystack: LossyUtf8CharWithEndOffsetIterator::new(haystack, invalid),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given byte sequence,
+    /// which may contain invalid UTF-8 sequences.
+    ///
+    /// The haystack is decoded as UTF-8 on the fly, and each byte that is not a part of a valid
+    /// character is handled as one invalid code unit according to `invalid`. The positions of
+    /// matches are reported in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte sequence to search for.
+    /// * `invalid` - How to handle invalid bytes.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`] in the construction, the iterator is not
+    /// supported and the function will panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// // "全世界中に" with an invalid byte 0xff after "中"
+    /// let haystack = b"\xe5\x85\xa8\xe4\xb8\x96\xe7\x95\x8c\xe4\xb8\xad\xff\xe3\x81\xab";
+    /// let mut it = pma.find_overlapping_no_suffix_iter_lossy(haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((13, 16, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn find_overlapping_no_suffix_iter_lossy<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedFindOverlappingNoSuffixIterator<'_, LossyUtf8CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_standard(),
+            "Error: match_kind must be standard."
+        );
+        DecodedFindOverlappingNoSuffixIterator {
+            pma: self,
+            haystack: LossyUtf8CharWithEndOffsetIterator::new(haystack, invalid),
+            state_id: ROOT_STATE_IDX,
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns an iterator of leftmost matches in the given byte sequence, which may contain
+    /// invalid UTF-8 sequences.
+    ///
+    /// The haystack is decoded as UTF-8 on the fly, and each byte that is not a part of a valid
+    /// character is handled as one invalid code unit according to `invalid`. The positions of
+    /// matches are reported in bytes. See [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter()`]
+    /// for the match semantics.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte sequence to search for.
+    /// * `invalid` - How to handle invalid bytes.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`], [`MatchKind::LeftmostFirst`], or
+    /// [`MatchKind::LeftmostShortest`] in the construction, the iterator is not supported and the
+    /// function will call panic!.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::charwise::InvalidSequence;
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["世界", "世", "世界中に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostLongest)
+    ///     .build(&patterns)
+    ///     .unwrap();
+    ///
+    /// // "全世界中に" with an invalid byte 0xff after "中"
+    /// let haystack = b"\xe5\x85\xa8\xe4\xb8\x96\xe7\x95\x8c\xe4\xb8\xad\xff\xe3\x81\xab";
+    /// let mut it = pma.leftmost_find_iter_lossy(haystack, InvalidSequence::Reset);
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn leftmost_find_iter_lossy<P>(
+        &self,
+        haystack: P,
+        invalid: InvalidSequence,
+    ) -> DecodedLeftmostFindIterator<'_, LossyUtf8CharWithEndOffsetIterator<P>, V>
+    where
+        P: AsRef<[u8]>,
+    {
+        assert!(
+            self.match_kind.is_leftmost(),
+            "Error: match_kind must be leftmost."
+        );
+        DecodedLeftmostFindIterator {
+            pma: self,
+            haystack: LossyUtf8CharWithEndOffsetIterator::new(haystack, invalid),
+            pos: 0,
+            output_pos: None,
+        }
+    }
+
+    /// Returns the total number of states this automaton has.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// assert_eq!(pma.num_states(), 6);
+    /// ```
+    #[must_use]
+    pub fn num_states(&self) -> usize {
+        usize::from_u32(self.num_states)
+    }
+
+    /// Returns the total number of elements of the double array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<usize>::new(patterns).unwrap();
+    ///
+    /// assert_eq!(pma.num_elements(), 8);
+    /// ```
+    #[must_use]
+    pub fn num_elements(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the total amount of heap used by this automaton in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// assert_eq!(568, pma.heap_bytes());
+    /// ```
+    #[must_use]
+    pub fn heap_bytes(&self) -> usize {
+        self.states.len() * mem::size_of::<State>()
+            + self.mapper.heap_bytes()
+            + self.outputs.len() * mem::size_of::<Output<V>>()
+    }
+
+    /// Serializes the automaton into a [`Vec`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let bytes = pma.serialize();
+    /// ```
+    #[must_use]
+    pub fn serialize(&self) -> Vec<u8>
+    where
+        V: Serializable,
+    {
+        let mut result = Vec::with_capacity(
+            self.states.serialized_bytes()
+                + self.mapper.serialized_bytes()
+                + self.outputs.serialized_bytes()
+                + MatchKind::serialized_bytes()
+                + u32::serialized_bytes(),
+        );
+        self.states.serialize_to_vec(&mut result);
+        self.mapper.serialize_to_vec(&mut result);
+        self.outputs.serialize_to_vec(&mut result);
+        self.match_kind.serialize_to_vec(&mut result);
+        self.num_states.serialize_to_vec(&mut result);
+        result
+    }
+
+    /// Deserializes the automaton from a given slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the automaton and the slice not used for the deserialization.
+    ///
+    /// # Safety
+    ///
+    /// The given data must be a correct automaton exported by
+    /// [`CharwiseDoubleArrayAhoCorasick::serialize()`] function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasick;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    /// let bytes = pma.serialize();
+    ///
+    /// let (pma, _) = unsafe { CharwiseDoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    ///
+    /// let mut it = pma.find_overlapping_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((3, 9, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub unsafe fn deserialize_unchecked(source: &[u8]) -> (Self, &[u8])
+    where
+        V: Serializable,
+    {
+        let (states, source) = Vec::<State>::deserialize_from_slice(source);
+        let (mapper, source) = CodeMapper::deserialize_from_slice(source);
+        let (outputs, source) = Vec::<Output<V>>::deserialize_from_slice(source);
+        let (match_kind, source) = MatchKind::deserialize_from_slice(source);
+        let (num_states, source) = u32::deserialize_from_slice(source);
+        (
+            Self {
+                states,
+                mapper,
+                outputs,
+                match_kind,
+                num_states,
+            },
+            source,
+        )
+    }
+
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the length of states.
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
     unsafe fn child_index_unchecked(&self, state_id: u32, mapped_c: u32) -> Option<u32> {
@@ -724,6 +2648,47 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         }
     }
 
+    /// Returns the children of all the states as offsets and edges, where the edges from state
+    /// `i` are `edges[offsets[i]..offsets[i + 1]]`, each of which is a pair of the mapped
+    /// character and the child id, in ascending order of the mapped characters.
+    fn children(&self) -> (Vec<usize>, Vec<(u32, u32)>) {
+        // The root, the dead state, and the vacant states have DEAD_STATE_IDX as their parent.
+        let parents = || {
+            self.states
+                .iter()
+                .zip(0..)
+                .filter(|(state, _)| state.check() != DEAD_STATE_IDX)
+                .map(|(state, child_id)| (usize::from_u32(state.check()), child_id))
+        };
+        let mut offsets = vec![0; self.states.len() + 1];
+        for (parent_idx, _) in parents() {
+            offsets[parent_idx + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        let mut edges = vec![(0, 0); offsets[self.states.len()]];
+        let mut heads = offsets.clone();
+        for (parent_idx, child_id) in parents() {
+            // A state is a child of its parent, which has a base.
+            let base = self.states[parent_idx].base().unwrap();
+            edges[heads[parent_idx]] = (base.get() ^ child_id, child_id);
+            heads[parent_idx] += 1;
+        }
+        for range in offsets.windows(2) {
+            edges[range[0]..range[1]].sort_unstable();
+        }
+        (offsets, edges)
+    }
+
+    /// Returns an iterator of the values associated with outputs.
+    pub(crate) fn output_values(&self) -> impl Iterator<Item = V> + '_
+    where
+        V: Copy,
+    {
+        self.outputs.iter().map(|out| out.value())
+    }
+
     /// # Safety
     ///
     /// `state_id` must be smaller than the length of states.
@@ -744,6 +2709,38 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
         }
     }
 
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the length of states.
+    #[inline(always)]
+    unsafe fn next_state_id_anchored_unchecked(&self, state_id: u32, c: char) -> Option<u32> {
+        self.mapper
+            .get(c)
+            .and_then(|mapped_c| self.child_index_unchecked(state_id, mapped_c))
+    }
+
+    /// Returns `output_pos` if its output has the given length, i.e., it is not an output of a
+    /// proper suffix of the string spelled by the current state.
+    #[inline(always)]
+    fn anchored_output_pos(
+        &self,
+        output_pos: Option<NonZeroU32>,
+        length: usize,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+    {
+        output_pos.filter(|output_pos| {
+            // output_pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and Output::parent() ensure to return such a value.
+            let out = unsafe {
+                self.outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            usize::from_u32(out.length()) == length
+        })
+    }
+
     /// # Safety
     ///
     /// `state_id` must be smaller than the length of states.
@@ -767,6 +2764,112 @@ impl<V> CharwiseDoubleArrayAhoCorasick<V> {
             ROOT_STATE_IDX
         }
     }
+
+    /// Returns the output position of the pattern spelled by the state itself, excluding the
+    /// outputs of its proper suffixes.
+    ///
+    /// # Safety
+    ///
+    /// `state_id` must be smaller than the length of states.
+    #[inline(always)]
+    unsafe fn own_output_pos(&self, state_id: u32) -> Option<NonZeroU32> {
+        let state = self.states.get_unchecked(usize::from_u32(state_id));
+        // fail() is always smaller than self.states.len() because it is a state id.
+        let fail = self.states.get_unchecked(usize::from_u32(state.fail()));
+        // A state without its own pattern shares the output position with its failure state.
+        state
+            .output_pos()
+            .filter(|&pos| Some(pos) != fail.output_pos())
+    }
+
+    /// Returns the length of the longest pattern in bytes.
+    fn max_pattern_length(&self) -> usize
+    where
+        V: Copy,
+    {
+        self.outputs
+            .iter()
+            .map(|out| usize::from_u32(out.length()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the position of the output storing the next value of the same pattern as `out`,
+    /// which exists only if multiple values are attached to the pattern.
+    #[inline(always)]
+    fn next_value_pos(&self, out: &Output<V>) -> Option<NonZeroU32> {
+        out.parent.filter(|pos| {
+            // pos.get() is always smaller than self.outputs.len() because
+            // Output::parent() ensures to return such a value when it is Some.
+            // Since a pattern is followed by its suffixes, which are shorter, only the outputs of
+            // the same pattern have the same length.
+            unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) }.length
+                == out.length
+        })
+    }
+
+    /// Returns the position of the first output accepted by `filter` in the chain of outputs
+    /// starting at `output_pos`.
+    #[inline(always)]
+    fn accepted_output_pos<F>(
+        &self,
+        mut output_pos: Option<NonZeroU32>,
+        filter: &mut F,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+        F: FnMut(V) -> bool,
+    {
+        while let Some(pos) = output_pos {
+            // pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and Output::parent() ensure to return such a value.
+            let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+            if filter(out.value()) {
+                return Some(pos);
+            }
+            output_pos = out.parent();
+        }
+        None
+    }
+
+    /// Returns the position of the first output accepted by `filter` among the values of the
+    /// same pattern, starting at `output_pos`.
+    #[inline(always)]
+    fn accepted_value_pos<F>(
+        &self,
+        mut output_pos: Option<NonZeroU32>,
+        filter: &mut F,
+    ) -> Option<NonZeroU32>
+    where
+        V: Copy,
+        F: FnMut(V) -> bool,
+    {
+        while let Some(pos) = output_pos {
+            // pos.get() is always smaller than self.outputs.len() because
+            // State::output_pos() and next_value_pos() ensure to return such a value.
+            let out = unsafe { self.outputs.get_unchecked(usize::from_u32(pos.get() - 1)) };
+            if filter(out.value()) {
+                return Some(pos);
+            }
+            output_pos = self.next_value_pos(out);
+        }
+        None
+    }
+}
+
+/// A strategy for code units that do not represent a valid character, such as unpaired
+/// surrogates in UTF-16 strings and invalid bytes in UTF-8 strings.
+///
+/// In UTF-8 byte sequences, each byte that is not a part of a valid character is handled as one
+/// invalid code unit.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InvalidSequence {
+    /// Resets the automaton to the root state, so that no match contains the invalid units.
+    Reset,
+
+    /// Decodes each invalid code unit as `U+FFFD REPLACEMENT CHARACTER`, which can be matched by
+    /// patterns containing the character.
+    Replace,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -1,12 +1,15 @@
+use core::borrow::Borrow;
 use core::num::NonZeroU32;
 
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 use crate::charwise::{CharwiseDoubleArrayAhoCorasick, CodeMapper, MatchKind, State};
-use crate::errors::{DaachorseError, Result};
+use crate::errors::{DaachorseError, PatternProblem, Result};
 use crate::nfa_builder::NfaBuilder;
 use crate::utils::FromU32;
-use crate::BuildHelper;
+use crate::wildcard;
+use crate::{BuildHelper, DuplicatePolicy, WildcardPattern};
 
 use crate::charwise::{DEAD_STATE_IDX, ROOT_STATE_IDX};
 use crate::nfa_builder::{DEAD_STATE_ID, ROOT_STATE_ID};
@@ -18,9 +21,13 @@ type CharwiseNfaBuilder<V> = NfaBuilder<char, V>;
 pub struct CharwiseDoubleArrayAhoCorasickBuilder {
     states: Vec<State>,
     mapper: CodeMapper,
+    text_freqs: BTreeMap<char, u64>,
     match_kind: MatchKind,
+    duplicate_policy: DuplicatePolicy,
+    reject_unreachable: bool,
     block_len: u32,
     num_free_blocks: u32,
+    max_wildcard_states: u32,
 }
 
 impl Default for CharwiseDoubleArrayAhoCorasickBuilder {
@@ -57,9 +64,13 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         Self {
             states: vec![],
             mapper: CodeMapper::default(),
+            text_freqs: BTreeMap::new(),
             match_kind: MatchKind::Standard,
+            duplicate_policy: DuplicatePolicy::Error,
+            reject_unreachable: false,
             block_len: 0,
             num_free_blocks: 16,
+            max_wildcard_states: 1 << 20,
         }
     }
 
@@ -74,6 +85,79 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies [`DuplicatePolicy`] for patterns registered more than once.
+    ///
+    /// By default, [`DuplicatePolicy::Error`] is used, and a repeated pattern is rejected. If
+    /// [`DuplicatePolicy::KeepAll`] is specified, all values attached to the same pattern are kept,
+    /// and every iterator reports one match for each of them in registration order.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Duplicate policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, DuplicatePolicy};
+    ///
+    /// let patvals = vec![("パリ", 0), ("パリ", 1), ("パリ", 2)];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .duplicate_policy(DuplicatePolicy::KeepAll)
+    ///     .build_with_values(patvals)
+    ///     .unwrap();
+    ///
+    /// let values: Vec<_> = pma.find_iter("パリ").map(|m| m.value()).collect();
+    /// assert_eq!(vec![0, 1, 2], values);
+    /// ```
+    #[must_use]
+    pub const fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Specifies whether to reject patterns that are never reported under
+    /// [`MatchKind::LeftmostFirst`] or [`MatchKind::LeftmostShortest`].
+    ///
+    /// Under [`MatchKind::LeftmostFirst`], a pattern is never reported if a prefix of it is
+    /// registered earlier. Under [`MatchKind::LeftmostShortest`], a pattern is never reported if
+    /// a prefix of it is registered. Such a pattern is ignored by default. If this option is
+    /// enabled, the construction fails with
+    /// [`UnreachablePatternError`](crate::errors::UnreachablePatternError) instead. This option
+    /// has no effect under the other match kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to reject unreachable patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::DaachorseError;
+    /// use daachorse::{
+    ///     CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind,
+    /// };
+    ///
+    /// let patterns = vec!["パ", "パリ"];
+    /// let result: Result<CharwiseDoubleArrayAhoCorasick<u32>, _> =
+    ///     CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///         .match_kind(MatchKind::LeftmostFirst)
+    ///         .reject_unreachable(true)
+    ///         .build(&patterns);
+    ///
+    /// match result {
+    ///     Err(DaachorseError::UnreachablePattern(e)) => {
+    ///         assert_eq!((1, 0), (e.index(), e.prefix()));
+    ///         assert_eq!("パリ".as_bytes(), e.pattern());
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    #[must_use]
+    pub const fn reject_unreachable(mut self, enabled: bool) -> Self {
+        self.reject_unreachable = enabled;
+        self
+    }
+
     /// Specifies the number of last blocks to search bases.
     ///
     /// The smaller the number is, the faster the construction time will be; however, the memory
@@ -93,6 +177,178 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         self
     }
 
+    /// Specifies frequencies of characters in the text to be searched.
+    ///
+    /// Characters in patterns are mapped to integer codes, and the smaller codes are assigned to
+    /// the more frequent characters to improve the locality of the double array. By default, the
+    /// codes are ranked by the frequencies in patterns, but the frequencies in haystacks are more
+    /// important for the search speed. The given frequencies are used as the primary key of the
+    /// ranking, and the frequencies in patterns are used to break ties. Characters not appearing
+    /// in patterns are ignored.
+    ///
+    /// If this method or [`Self::train_on()`] is called multiple times, the frequencies are
+    /// summed up.
+    ///
+    /// # Arguments
+    ///
+    /// * `freqs` - List of pairs of a character and its frequency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .code_frequencies([('に', 100), ('世', 20), ('界', 20)])
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub fn code_frequencies<I>(mut self, freqs: I) -> Self
+    where
+        I: IntoIterator<Item = (char, u64)>,
+    {
+        for (c, f) in freqs {
+            let e = self.text_freqs.entry(c).or_insert(0);
+            *e = e.saturating_add(f);
+        }
+        self
+    }
+
+    /// Counts frequencies of characters in the given sample texts and uses them to rank the codes
+    /// of characters.
+    ///
+    /// This is a shorthand of [`Self::code_frequencies()`] for the character counts of `corpus`.
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - List of sample texts similar to the haystacks to be searched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patterns = vec!["全世界", "世界", "に"];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .train_on(["世界中に", "世界の"])
+    ///     .build(patterns)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.find_iter("全世界中に");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 9, 0), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((12, 15, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub fn train_on<I, P>(mut self, corpus: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        for text in corpus {
+            for c in text.as_ref().chars() {
+                let e = self.text_freqs.entry(c).or_insert(0);
+                *e = e.saturating_add(1);
+            }
+        }
+        self
+    }
+
+    /// Specifies the maximum number of states created in
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::build_with_wildcards()`]. The default value is
+    /// 1048576.
+    ///
+    /// The states are counted over all the patterns, excluding the root.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The maximum number of states.
+    #[must_use]
+    pub const fn max_wildcard_states(mut self, n: u32) -> Self {
+        self.max_wildcard_states = n;
+        self
+    }
+
+    /// Checks input patterns and returns all the problems found, without building an automaton.
+    ///
+    /// Each problem has the index and the raw bytes of the pattern, and the problems are returned
+    /// in the order of the input. The following problems are reported:
+    ///   - empty patterns,
+    ///   - duplicate patterns, only under [`DuplicatePolicy::Error`],
+    ///   - patterns never reported because a prefix of them is registered earlier, only under
+    ///     [`MatchKind::LeftmostFirst`], or registered anywhere, only under
+    ///     [`MatchKind::LeftmostShortest`], and
+    ///   - patterns exceeding the scale limits of the input.
+    ///
+    /// Note that the scale of the resulting automaton is not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - List of patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::PatternProblemKind;
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patterns = vec!["パ", "", "パリ", "パ"];
+    /// let problems = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .validate(&patterns);
+    ///
+    /// assert_eq!(3, problems.len());
+    /// assert_eq!(1, problems[0].index());
+    /// assert_eq!(PatternProblemKind::Empty, problems[0].kind());
+    /// assert_eq!(2, problems[1].index());
+    /// assert_eq!(PatternProblemKind::Unreachable { prefix: 0 }, problems[1].kind());
+    /// assert_eq!(3, problems[2].index());
+    /// assert_eq!(PatternProblemKind::Duplicate { first: 0 }, problems[2].kind());
+    /// ```
+    pub fn validate<I, P>(&self, patterns: I) -> Vec<PatternProblem>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut nfa = NfaBuilder::new_validator(self.match_kind, self.duplicate_policy);
+        let mut chars = vec![];
+        let mut push = |index, pattern: P| {
+            chars.clear();
+            chars.extend(pattern.as_ref().chars());
+            nfa.validate(index, &chars, u32::MAX);
+        };
+        if self.match_kind.is_leftmost_shortest() {
+            let mut patterns: Vec<_> = patterns.into_iter().enumerate().collect();
+            patterns.sort_by_key(|(_, p)| p.as_ref().len());
+            patterns
+                .into_iter()
+                .for_each(|(index, pattern)| push(index, pattern));
+        } else {
+            patterns
+                .into_iter()
+                .enumerate()
+                .for_each(|(index, pattern)| push(index, pattern));
+        }
+        nfa.finish_validation()
+    }
+
     /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input patterns. The value
     /// `i` is automatically associated with `patterns[i]`.
     ///
@@ -105,7 +361,7 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     /// [`DaachorseError`] is returned when
     ///   - `patterns` is empty,
     ///   - `patterns` contains entries of length zero,
-    ///   - `patterns` contains duplicate entries,
+    ///   - `patterns` contains duplicate entries under [`DuplicatePolicy::Error`],
     ///   - the conversion from the index `i` to the specified type `V` fails,
     ///   - the scale of `patterns` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
@@ -158,7 +414,7 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
     /// [`DaachorseError`] is returned when
     ///   - `patvals` is empty,
     ///   - `patvals` contains patterns of length zero,
-    ///   - `patvals` contains duplicate patterns,
+    ///   - `patvals` contains duplicate patterns under [`DuplicatePolicy::Error`],
     ///   - the scale of `patvals` exceeds the expected one, or
     ///   - the scale of the resulting automaton exceeds the expected one.
     ///
@@ -191,8 +447,257 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         P: AsRef<str>,
         V: Copy,
     {
-        let nfa = self.build_original_nfa_and_mapper(patvals)?;
+        let nfa = self.build_original_nfa_and_mapper(patvals, None::<fn(V, V) -> V>, false)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input pattern-value pairs,
+    /// merging the values of a repeated pattern with the given function.
+    ///
+    /// When a pattern is registered again, `merge` is called with the current value and the new
+    /// one, and the returned value replaces the current one. The duplicate policy specified in
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::duplicate_policy`] is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    /// * `merge` - Function merging two values of the same pattern.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned when
+    ///   - `patvals` is empty,
+    ///   - `patvals` contains patterns of length zero,
+    ///   - the scale of `patvals` exceeds the expected one, or
+    ///   - the scale of the resulting automaton exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patvals = vec![("パリ", 1), ("リヨン", 2), ("パリ", 4)];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_values_merged(patvals, |a, b| a | b)
+    ///     .unwrap();
+    ///
+    /// let values: Vec<_> = pma.find_iter("パリ").map(|m| m.value()).collect();
+    /// assert_eq!(vec![5], values);
+    /// ```
+    pub fn build_with_values_merged<I, P, V, F>(
+        mut self,
+        patvals: I,
+        merge: F,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<str>,
+        V: Copy,
+        F: FnMut(V, V) -> V,
+    {
+        let nfa = self.build_original_nfa_and_mapper(patvals, Some(merge), false)?;
+        self.build_from_nfa(nfa)
+    }
 
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input tuples of a pattern,
+    /// a value, and a priority, for leftmost matching with custom priorities.
+    ///
+    /// Among patterns starting at the leftmost position, the one with the highest priority is
+    /// reported, and ties are broken by choosing the longest one. This is equivalent to registering
+    /// patterns in descending order of priority and length under [`MatchKind::LeftmostFirst`].
+    /// Duplicate patterns are handled according to
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::duplicate_policy()`] in the same order, so the
+    /// value with the highest priority comes first.
+    ///
+    /// # Match kind
+    ///
+    /// **The match kind specified with [`CharwiseDoubleArrayAhoCorasickBuilder::match_kind()`] is
+    /// ignored.** The resulting automaton always has [`MatchKind::LeftmostFirst`], so it supports
+    /// only [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter()`] and the other leftmost
+    /// searches.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvalpris` - List of tuples of a pattern, a value, and a priority.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::build_with_values()`]. The indices in the error
+    /// refer to the order of `patvalpris`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::CharwiseDoubleArrayAhoCorasickBuilder;
+    ///
+    /// let patvalpris = vec![("apple", 0, 1), ("apple pie", 1, 1), ("Apple", 2, 5), ("pie", 3, 1)];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_priorities(patvalpris)
+    ///     .unwrap();
+    ///
+    /// let mut it = pma.leftmost_find_iter("Apple pie and apple pie");
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 5, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((6, 9, 3), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((14, 23, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn build_with_priorities<I, P, V>(
+        mut self,
+        patvalpris: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V, u32)>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let mut patvalpris: Vec<_> = patvalpris.into_iter().enumerate().collect();
+        // Note: The stable sort keeps the input order of patterns with the same priority and
+        // length.
+        patvalpris.sort_by(|(_, (p1, _, r1)), (_, (p2, _, r2))| {
+            r2.cmp(r1)
+                .then_with(|| p2.as_ref().len().cmp(&p1.as_ref().len()))
+        });
+        let order: Vec<_> = patvalpris.iter().map(|&(i, _)| i).collect();
+        self.match_kind = MatchKind::LeftmostFirst;
+        self.build_with_values(patvalpris.into_iter().map(|(_, (p, v, _))| (p, v)))
+            .map_err(|e| e.map_pattern_indices(|i| order[i]))
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input pairs of a pattern
+    /// with wildcards and character classes and a value.
+    ///
+    /// All the concrete patterns matched by each pattern are registered with the same value,
+    /// without being enumerated. Characters never distinguished by the patterns are mapped to
+    /// the same code, so a wildcard adds only a few edges. See [`WildcardPattern`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::build_with_values()`] for the concrete patterns,
+    /// when a pattern has an empty character class, or when more states than
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::max_wildcard_states()`] are created. The indices
+    /// in the error refer to the order of `patvals`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, WildcardPattern};
+    ///
+    /// let patvals = vec![
+    ///     (WildcardPattern::new().literal("第").one_of("一二").literal("章"), 0),
+    ///     (WildcardPattern::new().class(['0'..='9']).literal("月"), 1),
+    ///     (WildcardPattern::new().literal("第").any().literal("部"), 2),
+    /// ];
+    /// let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .build_with_wildcards(patvals)
+    ///     .unwrap();
+    ///
+    /// let matches: Vec<_> = pma
+    ///     .find_iter("第二章は5月、第🍣部")
+    ///     .map(|m| (m.start(), m.end(), m.value()))
+    ///     .collect();
+    /// assert_eq!(vec![(0, 9, 0), (12, 16, 1), (19, 29, 2)], matches);
+    /// ```
+    pub fn build_with_wildcards<I, P, V>(
+        mut self,
+        patvals: I,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: Borrow<WildcardPattern>,
+        V: Copy,
+    {
+        let patvals: Vec<(P, V)> = patvals.into_iter().collect();
+        let classes = wildcard::char_classes(patvals.iter().map(|(pattern, _)| pattern.borrow()));
+        let patvals = patvals
+            .iter()
+            .map(|(pattern, value)| Ok((pattern.borrow().char_alternatives(&classes)?, *value)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut nfa = self.new_nfa(false);
+        nfa.add_all_alternatives(&patvals, self.max_wildcard_states)?;
+
+        let mut freqs = vec![0u32; classes.len()];
+        for [c] in patvals.iter().flat_map(|(elements, _)| elements).flatten() {
+            // Each alternative is the first character of a class.
+            let i = classes.partition_point(|&(start, _)| start < u32::from(*c));
+            freqs[i] = freqs[i].saturating_add(1);
+        }
+        let classes: Vec<_> = classes
+            .into_iter()
+            .zip(freqs)
+            .map(|((start, end), freq)| (start, end, freq))
+            .collect();
+        self.mapper = CodeMapper::with_classes(&classes, &self.text_freqs);
+        let nfa = self.finish_nfa(nfa)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Builds and returns a new [`CharwiseDoubleArrayAhoCorasick`] from input pattern-value pairs,
+    /// together with the report of patterns that are never reported.
+    ///
+    /// Under [`MatchKind::LeftmostFirst`], a pattern is never reported if a prefix of it is
+    /// registered earlier. Under [`MatchKind::LeftmostShortest`], a pattern is never reported if
+    /// a prefix of it is registered. Such patterns are returned as [`PatternProblem`]s with
+    /// [`PatternProblemKind::Unreachable`](crate::errors::PatternProblemKind::Unreachable), which
+    /// has the index of the shadowing pattern. The report is always empty under the other match
+    /// kinds.
+    ///
+    /// # Arguments
+    ///
+    /// * `patvals` - List of pattern-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`DaachorseError`] is returned in the same conditions as
+    /// [`CharwiseDoubleArrayAhoCorasickBuilder::build_with_values()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::errors::PatternProblemKind;
+    /// use daachorse::{CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
+    ///
+    /// let patvals = vec![("パ", 0), ("パリ", 1)];
+    /// let (pma, report) = CharwiseDoubleArrayAhoCorasickBuilder::new()
+    ///     .match_kind(MatchKind::LeftmostFirst)
+    ///     .build_with_values_and_report(patvals)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(1, report.len());
+    /// assert_eq!(1, report[0].index());
+    /// assert_eq!("パリ".as_bytes(), report[0].pattern());
+    /// assert_eq!(PatternProblemKind::Unreachable { prefix: 0 }, report[0].kind());
+    /// ```
+    pub fn build_with_values_and_report<I, P, V>(
+        mut self,
+        patvals: I,
+    ) -> Result<(CharwiseDoubleArrayAhoCorasick<V>, Vec<PatternProblem>)>
+    where
+        I: IntoIterator<Item = (P, V)>,
+        P: AsRef<str>,
+        V: Copy,
+    {
+        let mut nfa = self.build_original_nfa_and_mapper(patvals, None::<fn(V, V) -> V>, true)?;
+        let report = nfa.unreachable.take().unwrap_or_default();
+        Ok((self.build_from_nfa(nfa)?, report))
+    }
+
+    fn build_from_nfa<V>(
+        mut self,
+        nfa: CharwiseNfaBuilder<V>,
+    ) -> Result<CharwiseDoubleArrayAhoCorasick<V>> {
         self.build_double_array(&nfa)?;
 
         // -1 is for dead state
@@ -208,23 +713,26 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
         })
     }
 
-    fn build_original_nfa_and_mapper<I, P, V>(
+    fn build_original_nfa_and_mapper<I, P, V, F>(
         &mut self,
         patvals: I,
+        mut merge: Option<F>,
+        report_unreachable: bool,
     ) -> Result<CharwiseNfaBuilder<V>>
     where
         I: IntoIterator<Item = (P, V)>,
         P: AsRef<str>,
         V: Copy,
+        F: FnMut(V, V) -> V,
     {
-        let mut nfa = CharwiseNfaBuilder::new(self.match_kind);
+        let mut nfa = self.new_nfa(report_unreachable);
         let mut freqs = vec![];
         {
             let mut chars = vec![];
-            for (pattern, value) in patvals {
+            let mut add = |index, pattern: P, value| -> Result<()> {
                 chars.clear();
                 pattern.as_ref().chars().for_each(|c| chars.push(c));
-                nfa.add(&chars, value)?;
+                nfa.add(index, &chars, value, merge.as_mut())?;
 
                 for &c in &chars {
                     let c = usize::from_u32(u32::from(c));
@@ -233,16 +741,56 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
                     }
                     freqs[c] += 1;
                 }
+                Ok(())
+            };
+            if self.match_kind.is_leftmost_shortest() {
+                // Registers shorter patterns earlier so that they shadow the longer ones.
+                let mut patvals: Vec<_> = patvals.into_iter().enumerate().collect();
+                patvals.sort_by_key(|(_, (p, _))| p.as_ref().len());
+                for (index, (pattern, value)) in patvals {
+                    add(index, pattern, value)?;
+                }
+                if let Some(unreachable) = nfa.unreachable.as_mut() {
+                    unreachable.sort_by_key(PatternProblem::index);
+                }
+            } else {
+                for (index, (pattern, value)) in patvals.into_iter().enumerate() {
+                    add(index, pattern, value)?;
+                }
+            }
+        }
+        self.mapper = CodeMapper::new(&freqs, &self.text_freqs);
+
+        self.finish_nfa(nfa)
+    }
+
+    fn new_nfa<V>(&self, report_unreachable: bool) -> CharwiseNfaBuilder<V>
+    where
+        V: Copy,
+    {
+        let mut nfa = CharwiseNfaBuilder::new(self.match_kind, self.duplicate_policy);
+        if self.match_kind.shadows_extensions() {
+            nfa.reject_unreachable = self.reject_unreachable;
+            if report_unreachable {
+                nfa.unreachable = Some(vec![]);
             }
         }
-        self.mapper = CodeMapper::new(&freqs);
+        nfa
+    }
 
+    /// Builds the failure links and outputs of the NFA.
+    fn finish_nfa<V>(&self, mut nfa: CharwiseNfaBuilder<V>) -> Result<CharwiseNfaBuilder<V>>
+    where
+        V: Copy,
+    {
         if nfa.len == 0 {
             return Err(DaachorseError::invalid_argument("patvals.len()", ">=", 1));
         }
         let q = match self.match_kind {
             MatchKind::Standard => nfa.build_fails(),
-            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst => nfa.build_fails_leftmost(),
+            MatchKind::LeftmostLongest | MatchKind::LeftmostFirst | MatchKind::LeftmostShortest => {
+                nfa.build_fails_leftmost()
+            }
         };
         nfa.build_outputs(&q);
         Ok(nfa)
@@ -274,7 +822,7 @@ impl CharwiseDoubleArrayAhoCorasickBuilder {
             for (&label, &child_id) in &s.edges {
                 mapped.push((self.mapper.get(label).unwrap(), child_id));
             }
-            mapped.sort_by(|(c1, _), (c2, _)| c1.cmp(c2));
+            mapped.sort_by_key(|&(c, _)| c);
 
             let base = self.find_base(&mapped, &helper);
             if self.states.len() <= usize::from_u32(base.get()) {
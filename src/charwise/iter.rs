@@ -3,17 +3,22 @@
 use core::iter::Enumerate;
 use core::num::NonZeroU32;
 
-use crate::charwise::CharwiseDoubleArrayAhoCorasick;
+use alloc::collections::VecDeque;
+
+use crate::charwise::{CharwiseDoubleArrayAhoCorasick, InvalidSequence};
 
 use crate::charwise::ROOT_STATE_IDX;
 use crate::utils::FromU32;
 use crate::Match;
 
+pub use crate::segment::{SegmentIterator, StrSegment, StrSegmentIterator, StrSegments};
+
 /// Iterator for some struct that implements [`AsRef<str>`].
 #[doc(hidden)]
 pub struct StrIterator<P> {
     inner: P,
     pos: usize,
+    end: usize,
 }
 
 impl<P> StrIterator<P>
@@ -22,7 +27,18 @@ where
 {
     #[allow(clippy::missing_const_for_fn)]
     pub(crate) fn new(inner: P) -> Self {
-        Self { inner, pos: 0 }
+        let end = inner.as_ref().len();
+        Self { inner, pos: 0, end }
+    }
+
+    /// Creates an iterator of the bytes in `start..end`.
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn with_span(inner: P, start: usize, end: usize) -> Self {
+        Self {
+            inner,
+            pos: start,
+            end,
+        }
     }
 }
 
@@ -34,6 +50,9 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.end {
+            return None;
+        }
         let ret = *self.inner.as_ref().as_bytes().get(self.pos)?;
         self.pos += 1;
         Some(ret)
@@ -44,6 +63,7 @@ where
 #[doc(hidden)]
 pub struct CharWithEndOffsetIterator<I> {
     inner: Enumerate<I>,
+    offset: usize,
 }
 
 impl<I> CharWithEndOffsetIterator<I>
@@ -56,8 +76,18 @@ where
     ///
     /// `inner` must represent a correct UTF-8 string.
     pub unsafe fn new(inner: I) -> Self {
+        Self::with_offset(inner, 0)
+    }
+
+    /// Creates a new iterator whose end positions are shifted by `offset`.
+    ///
+    /// # Safety
+    ///
+    /// `inner` must represent a correct UTF-8 string.
+    pub(crate) unsafe fn with_offset(inner: I, offset: usize) -> Self {
         Self {
             inner: inner.enumerate(),
+            offset,
         }
     }
 }
@@ -94,7 +124,9 @@ where
                 }
             }
         };
-        Some((end_offset, unsafe { char::from_u32_unchecked(c) }))
+        Some((self.offset + end_offset, unsafe {
+            char::from_u32_unchecked(c)
+        }))
     }
 }
 
@@ -102,6 +134,8 @@ where
 pub struct FindOverlappingIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) start: usize,
+    pub(crate) anchored: bool,
     pub(crate) state_id: u32,
     pub(crate) pos: usize,
     pub(crate) output_pos: Option<NonZeroU32>,
@@ -111,6 +145,9 @@ pub struct FindOverlappingIterator<'a, P, V> {
 pub struct FindIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) anchored: bool,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 /// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_overlapping_no_suffix_iter()`].
@@ -118,13 +155,18 @@ pub struct FindOverlappingNoSuffixIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: CharWithEndOffsetIterator<P>,
     pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 /// Iterator created by [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter()`].
 pub struct LeftmostFindIterator<'a, P, V> {
     pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
     pub(crate) haystack: P,
+    pub(crate) end: usize,
+    pub(crate) anchored: bool,
     pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
 }
 
 impl<P, V> Iterator for FindOverlappingIterator<'_, P, V>
@@ -145,6 +187,11 @@ where
                     .get_unchecked(usize::from_u32(output_pos.get() - 1))
             };
             self.output_pos = out.parent();
+            if self.anchored {
+                self.output_pos = self
+                    .pma
+                    .anchored_output_pos(self.output_pos, self.pos - self.start);
+            }
             return Some(Match {
                 length: usize::from_u32(out.length()),
                 end: self.pos,
@@ -155,15 +202,39 @@ where
         for (pos, c) in self.haystack.by_ref() {
             self.pos = pos;
 
-            // self.state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_unchecked() ensures to return such a value.
-            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(self.state_id))
-                    .output_pos()
-            } {
+            let output_pos = if self.anchored {
+                // self.state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                if let Some(state_id) =
+                    unsafe { self.pma.next_state_id_anchored_unchecked(self.state_id, c) }
+                {
+                    self.state_id = state_id;
+                } else {
+                    // No more matches start at the beginning of the span.
+                    self.haystack.by_ref().for_each(|_| ());
+                    return None;
+                }
+                self.pma.anchored_output_pos(
+                    unsafe {
+                        self.pma
+                            .states
+                            .get_unchecked(usize::from_u32(self.state_id))
+                            .output_pos()
+                    },
+                    pos - self.start,
+                )
+            } else {
+                // self.state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_unchecked() ensures to return such a value.
+                self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
+                unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(self.state_id))
+                        .output_pos()
+                }
+            };
+            if let Some(output_pos) = output_pos {
                 // output_pos.get() is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
                 let out = unsafe {
@@ -172,6 +243,11 @@ where
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
                 self.output_pos = out.parent();
+                if self.anchored {
+                    self.output_pos = self
+                        .pma
+                        .anchored_output_pos(self.output_pos, self.pos - self.start);
+                }
                 return Some(Match {
                     length: usize::from_u32(out.length()),
                     end: pos,
@@ -192,17 +268,58 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
         let mut state_id = ROOT_STATE_IDX;
+        let start = self.pos;
         for (pos, c) in self.haystack.by_ref() {
-            // self.state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
-            if let Some(output_pos) = unsafe {
-                self.pma
-                    .states
-                    .get_unchecked(usize::from_u32(state_id))
-                    .output_pos()
-            } {
+            let output_pos = if self.anchored {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                if let Some(next_state_id) =
+                    unsafe { self.pma.next_state_id_anchored_unchecked(state_id, c) }
+                {
+                    state_id = next_state_id;
+                } else {
+                    // No more matches start at the current position.
+                    self.haystack.by_ref().for_each(|_| ());
+                    return None;
+                }
+                self.pma.anchored_output_pos(
+                    unsafe {
+                        self.pma
+                            .states
+                            .get_unchecked(usize::from_u32(state_id))
+                            .output_pos()
+                    },
+                    pos - start,
+                )
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_unchecked() ensures to return such a value.
+                state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
+                unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                }
+            };
+            if let Some(output_pos) = output_pos {
                 // output_pos is always smaller than self.pma.outputs.len() because
                 // State::output_pos() ensures to return such a value when it is Some.
                 let out = unsafe {
@@ -210,9 +327,11 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
+                self.pos = pos;
+                self.output_pos = self.pma.next_value_pos(out);
                 return Some(Match {
                     length: usize::from_u32(out.length()),
-                    end: pos,
+                    end: self.pos,
                     value: out.value(),
                 });
             }
@@ -230,6 +349,22 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
         for (pos, c) in self.haystack.by_ref() {
             // self.state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_unchecked() ensures to return such a value.
@@ -247,9 +382,11 @@ where
                         .outputs
                         .get_unchecked(usize::from_u32(output_pos.get() - 1))
                 };
+                self.pos = pos;
+                self.output_pos = self.pma.next_value_pos(out);
                 return Some(Match {
                     length: usize::from_u32(out.length()),
-                    end: pos,
+                    end: self.pos,
                     value: out.value(),
                 });
             }
@@ -267,16 +404,44 @@ where
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
         let mut state_id = ROOT_STATE_IDX;
         let mut last_output_pos: Option<NonZeroU32> = None;
 
+        let start = self.pos;
         let mut skips = 0;
-        for c in unsafe { self.haystack.as_ref().get_unchecked(self.pos..) }.chars() {
+        // self.pos and self.end are always on character boundaries in the haystack because
+        // they are validated in the construction and advanced by character lengths.
+        for c in unsafe { self.haystack.as_ref().get_unchecked(self.pos..self.end) }.chars() {
             skips += c.len_utf8();
 
-            // state_id is always smaller than self.pma.states.len() because
-            // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
-            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            if self.anchored {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_anchored_unchecked() ensures to return such a value.
+                match unsafe { self.pma.next_state_id_anchored_unchecked(state_id, c) } {
+                    Some(next_state_id) => state_id = next_state_id,
+                    None => break,
+                }
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+                state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            }
             if state_id == ROOT_STATE_IDX {
                 if let Some(output_pos) = last_output_pos {
                     // last_output_pos is always smaller than self.pma.outputs.len() because
@@ -286,14 +451,114 @@ where
                             .outputs
                             .get_unchecked(usize::from_u32(output_pos.get() - 1))
                     };
+                    self.output_pos = self.pma.next_value_pos(out);
                     return Some(Match {
                         length: usize::from_u32(out.length()),
                         end: self.pos,
                         value: out.value(),
                     });
                 }
+            } else {
+                // state_id is always smaller than self.pma.states.len() because
+                // the transition functions ensure to return such a value.
+                let mut output_pos = unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                if self.anchored {
+                    output_pos = self
+                        .pma
+                        .anchored_output_pos(output_pos, self.pos + skips - start);
+                }
+                if let Some(output_pos) = output_pos {
+                    last_output_pos.replace(output_pos);
+                    self.pos += skips;
+                    skips = 0;
+                }
+            }
+        }
+
+        last_output_pos.map(|output_pos| {
+            // last_output_pos is always smaller than self.pma.outputs.len() because
+            // State::output_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            }
+        })
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter_from_segments()`].
+pub struct SegmentedLeftmostFindIterator<'a, P, V> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) lookahead: VecDeque<(usize, char)>,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<P, V> Iterator for SegmentedLeftmostFindIterator<'_, P, V>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let mut last_output_pos: Option<NonZeroU32> = None;
+
+        // self.lookahead keeps the characters read after the last output, which are scanned
+        // again from the end of the match.
+        let mut i = 0;
+        loop {
+            if self.lookahead.len() == i {
+                match self.haystack.next() {
+                    Some(x) => self.lookahead.push_back(x),
+                    None => break,
+                }
+            }
+            let (pos, c) = self.lookahead[i];
+            if last_output_pos.is_some() {
+                i += 1;
+            } else {
+                self.lookahead.pop_front();
+            }
+
             // state_id is always smaller than self.pma.states.len() because
             // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = unsafe { self.pma.next_state_id_leftmost_unchecked(state_id, c) };
+            if state_id == ROOT_STATE_IDX {
+                if last_output_pos.is_some() {
+                    break;
+                }
             } else if let Some(output_pos) = unsafe {
                 self.pma
                     .states
@@ -301,8 +566,9 @@ where
                     .output_pos()
             } {
                 last_output_pos.replace(output_pos);
-                self.pos += skips;
-                skips = 0;
+                self.lookahead.drain(..i);
+                self.pos = pos;
+                i = 0;
             }
         }
 
@@ -314,6 +580,7 @@ where
                     .outputs
                     .get_unchecked(usize::from_u32(output_pos.get() - 1))
             };
+            self.output_pos = self.pma.next_value_pos(out);
             Match {
                 length: usize::from_u32(out.length()),
                 end: self.pos,
@@ -323,48 +590,815 @@ where
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_char_with_end_offset_iterator() {
-        let test_string =
-            "\u{0000}\u{0001}\u{0002}\u{0004}\u{0008}\u{0010}\u{001f}\u{0020}\u{0040}\
-             \u{007f}\u{0080}\u{0100}\u{01ff}\u{0200}\u{0400}\u{07ff}\u{0800}\u{1000}\
-             \u{1fff}\u{2000}\u{4000}\u{8000}\u{ffff}\u{10000}\
-             \u{1ffff}\u{20000}\u{40000}\u{80000}\u{100000}\u{10ffff}";
-        let mut it =
-            unsafe { CharWithEndOffsetIterator::new(test_string.as_bytes().iter().copied()) };
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_iter_filtered()`].
+pub struct FilteredFindIterator<'a, P, V, F> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
 
-        // 1 byte
-        assert_eq!(Some((1, '\u{0000}')), it.next());
-        assert_eq!(Some((2, '\u{0001}')), it.next());
-        assert_eq!(Some((3, '\u{0002}')), it.next());
-        assert_eq!(Some((4, '\u{0004}')), it.next());
-        assert_eq!(Some((5, '\u{0008}')), it.next());
-        assert_eq!(Some((6, '\u{0010}')), it.next());
-        assert_eq!(Some((7, '\u{001f}')), it.next());
-        assert_eq!(Some((8, '\u{0020}')), it.next());
-        assert_eq!(Some((9, '\u{0040}')), it.next());
-        assert_eq!(Some((10, '\u{007f}')), it.next());
+impl<P, V, F> Iterator for FilteredFindIterator<'_, P, V, F>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
 
-        // 2 bytes
-        assert_eq!(Some((12, '\u{0080}')), it.next());
-        assert_eq!(Some((14, '\u{0100}')), it.next());
-        assert_eq!(Some((16, '\u{01ff}')), it.next());
-        assert_eq!(Some((18, '\u{0200}')), it.next());
-        assert_eq!(Some((20, '\u{0400}')), it.next());
-        assert_eq!(Some((22, '\u{07ff}')), it.next());
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // accepted_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self
+                .pma
+                .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
 
-        // 3 bytes
-        assert_eq!(Some((25, '\u{0800}')), it.next());
-        assert_eq!(Some((28, '\u{1000}')), it.next());
-        assert_eq!(Some((31, '\u{1fff}')), it.next());
-        assert_eq!(Some((34, '\u{2000}')), it.next());
-        assert_eq!(Some((37, '\u{4000}')), it.next());
-        assert_eq!(Some((40, '\u{8000}')), it.next());
-        assert_eq!(Some((43, '\u{ffff}')), it.next());
+        let mut state_id = ROOT_STATE_IDX;
+        for (pos, c) in self.haystack.by_ref() {
+            // state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            state_id = unsafe { self.pma.next_state_id_unchecked(state_id, c) };
+            let output_pos = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            };
+            if let Some(output_pos) = self.pma.accepted_output_pos(output_pos, &mut self.filter) {
+                // output_pos is always smaller than self.pma.outputs.len() because
+                // accepted_output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos = pos;
+                self.output_pos = self
+                    .pma
+                    .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_overlapping_iter_filtered()`].
+pub struct FilteredFindOverlappingIterator<'a, P, V, F> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: CharWithEndOffsetIterator<P>,
+    pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
+
+impl<P, V, F> Iterator for FilteredFindOverlappingIterator<'_, P, V, F>
+where
+    P: Iterator<Item = u8>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(output_pos) = self.output_pos {
+                // output_pos.get() is always smaller than self.pma.outputs.len() because
+                // accepted_output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.output_pos = self.pma.accepted_output_pos(out.parent(), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+
+            let (pos, c) = self.haystack.next()?;
+            self.pos = pos;
+            // self.state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            self.state_id = unsafe { self.pma.next_state_id_unchecked(self.state_id, c) };
+            let output_pos = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(self.state_id))
+                    .output_pos()
+            };
+            self.output_pos = self.pma.accepted_output_pos(output_pos, &mut self.filter);
+        }
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter_filtered()`].
+pub struct FilteredLeftmostFindIterator<'a, P, V, F> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: P,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+    pub(crate) filter: F,
+}
+
+impl<P, V, F> Iterator for FilteredLeftmostFindIterator<'_, P, V, F>
+where
+    P: AsRef<str>,
+    V: Copy,
+    F: FnMut(V) -> bool,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // accepted_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self
+                .pma
+                .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+            return Some(Match {
+                length: usize::from_u32(out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        // The fail links of the automaton assume that all the patterns are enabled, so the
+        // patterns starting at each position are examined by following only the trie edges.
+        let haystack = self.haystack.as_ref();
+        while self.pos < haystack.len() {
+            // self.pos is always a character boundary smaller than haystack.len().
+            let rest = unsafe { haystack.get_unchecked(self.pos..) };
+            let mut state_id = ROOT_STATE_IDX;
+            let mut last_output_pos = None;
+            let mut depth = 0;
+            for c in rest.chars() {
+                depth += c.len_utf8();
+                let child_id = self.pma.mapper.get(c).and_then(|mapped_c| {
+                    // state_id is always smaller than self.pma.states.len() because
+                    // self.pma.child_index_unchecked() ensures to return such a value.
+                    unsafe { self.pma.child_index_unchecked(state_id, mapped_c) }
+                });
+                match child_id {
+                    Some(child_id) => state_id = child_id,
+                    None => break,
+                }
+                let output_pos = unsafe {
+                    self.pma
+                        .states
+                        .get_unchecked(usize::from_u32(state_id))
+                        .output_pos()
+                };
+                // output_pos.get() is always smaller than self.pma.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                // Only outputs as long as the depth are patterns starting at self.pos.
+                let output_pos = output_pos.filter(|pos| {
+                    let out = unsafe {
+                        self.pma
+                            .outputs
+                            .get_unchecked(usize::from_u32(pos.get() - 1))
+                    };
+                    usize::from_u32(out.length()) == depth
+                });
+                if let Some(output_pos) = self.pma.accepted_value_pos(output_pos, &mut self.filter)
+                {
+                    last_output_pos = Some(output_pos);
+                }
+            }
+
+            if let Some(output_pos) = last_output_pos {
+                // last_output_pos is always smaller than self.pma.outputs.len() because
+                // accepted_value_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos += usize::from_u32(out.length());
+                self.output_pos = self
+                    .pma
+                    .accepted_value_pos(self.pma.next_value_pos(out), &mut self.filter);
+                return Some(Match {
+                    length: usize::from_u32(out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+            // rest is not empty since self.pos is smaller than haystack.len().
+            self.pos += rest.chars().next().unwrap().len_utf8();
+        }
+        None
+    }
+}
+
+/// Decoder of characters from a sequence of code units that is not a valid UTF-8 string.
+#[doc(hidden)]
+pub trait CodeUnitDecoder: Iterator<Item = (usize, Option<char>)> {
+    /// Moves the decoder to the given position, which must be a character boundary.
+    fn set_pos(&mut self, pos: usize);
+
+    /// Converts the length in UTF-8 bytes of a match ending at `end` into the number of code
+    /// units.
+    fn unit_length(&self, end: usize, length: u32) -> usize;
+}
+
+/// Iterator for UTF-16 strings with end positions.
+///
+/// This iterator returns `None` as a character for each unpaired surrogate if
+/// [`InvalidSequence::Reset`] is specified.
+#[doc(hidden)]
+pub struct Utf16CharWithEndOffsetIterator<P> {
+    inner: P,
+    pos: usize,
+    invalid: InvalidSequence,
+}
+
+impl<P> Utf16CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u16]>,
+{
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn new(inner: P, invalid: InvalidSequence) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            invalid,
+        }
+    }
+}
+
+#[inline(always)]
+const fn is_high_surrogate(u: u16) -> bool {
+    0xd800 <= u && u < 0xdc00
+}
+
+#[inline(always)]
+const fn is_low_surrogate(u: u16) -> bool {
+    0xdc00 <= u && u < 0xe000
+}
+
+impl<P> Iterator for Utf16CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u16]>,
+{
+    type Item = (usize, Option<char>);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let haystack = self.inner.as_ref();
+        let first = *haystack.get(self.pos)?;
+        self.pos += 1;
+        if !is_high_surrogate(first) && !is_low_surrogate(first) {
+            // Code units other than surrogates are always valid code points.
+            return Some((self.pos, char::from_u32(u32::from(first))));
+        }
+        if is_high_surrogate(first) {
+            if let Some(&second) = haystack.get(self.pos) {
+                if is_low_surrogate(second) {
+                    self.pos += 1;
+                    let c = 0x10000
+                        + ((u32::from(first) - 0xd800) << 10)
+                        + (u32::from(second) - 0xdc00);
+                    return Some((self.pos, char::from_u32(c)));
+                }
+            }
+        }
+        match self.invalid {
+            InvalidSequence::Reset => Some((self.pos, None)),
+            InvalidSequence::Replace => Some((self.pos, Some(char::REPLACEMENT_CHARACTER))),
+        }
+    }
+}
+
+impl<P> CodeUnitDecoder for Utf16CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u16]>,
+{
+    #[inline(always)]
+    fn set_pos(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    #[inline(always)]
+    fn unit_length(&self, end: usize, length: u32) -> usize {
+        let haystack = self.inner.as_ref();
+        let mut pos = end;
+        let mut rest = usize::from_u32(length);
+        // The matched units always represent characters of `length` bytes in total, so the
+        // following loop reaches zero without underflow.
+        while rest != 0 {
+            let u = haystack[pos - 1];
+            if is_low_surrogate(u) && pos >= 2 && is_high_surrogate(haystack[pos - 2]) {
+                rest -= 4;
+                pos -= 2;
+            } else if is_high_surrogate(u) || is_low_surrogate(u) {
+                rest -= char::REPLACEMENT_CHARACTER.len_utf8();
+                pos -= 1;
+            } else {
+                rest -= char::from_u32(u32::from(u)).map_or(0, char::len_utf8);
+                pos -= 1;
+            }
+        }
+        end - pos
+    }
+}
+
+/// Iterator for possibly invalid UTF-8 byte sequences with end positions.
+///
+/// Each byte that is not a part of a valid UTF-8 character is handled as one invalid code unit,
+/// i.e., this iterator returns `None` as a character for such a byte if
+/// [`InvalidSequence::Reset`] is specified.
+#[doc(hidden)]
+pub struct LossyUtf8CharWithEndOffsetIterator<P> {
+    inner: P,
+    pos: usize,
+    invalid: InvalidSequence,
+}
+
+impl<P> LossyUtf8CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u8]>,
+{
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn new(inner: P, invalid: InvalidSequence) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            invalid,
+        }
+    }
+}
+
+#[inline(always)]
+const fn is_utf8_continuation(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+/// Decodes the UTF-8 character at the beginning of `bytes` and returns it with its length, or
+/// `None` if `bytes` does not start with a valid character.
+#[inline(always)]
+fn decode_utf8_prefix(bytes: &[u8]) -> Option<(char, usize)> {
+    let first = *bytes.first()?;
+    // The valid range of the second byte depends on the first byte to reject overlong encodings,
+    // surrogates, and code points greater than U+10FFFF.
+    let (len, lower, upper) = match first {
+        0x00..=0x7f => return Some((char::from(first), 1)),
+        0xc2..=0xdf => (2, 0x80, 0xbf),
+        0xe0 => (3, 0xa0, 0xbf),
+        0xe1..=0xec | 0xee..=0xef => (3, 0x80, 0xbf),
+        0xed => (3, 0x80, 0x9f),
+        0xf0 => (4, 0x90, 0xbf),
+        0xf1..=0xf3 => (4, 0x80, 0xbf),
+        0xf4 => (4, 0x80, 0x8f),
+        _ => return None,
+    };
+    let second = *bytes.get(1)?;
+    if second < lower || upper < second {
+        return None;
+    }
+    let mut c = (u32::from(first) & (0x7f >> len)) << 6 | u32::from(second & 0x3f);
+    for i in 2..len {
+        let b = *bytes.get(i)?;
+        if !is_utf8_continuation(b) {
+            return None;
+        }
+        c = c << 6 | u32::from(b & 0x3f);
+    }
+    char::from_u32(c).map(|c| (c, len))
+}
+
+impl<P> Iterator for LossyUtf8CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u8]>,
+{
+    type Item = (usize, Option<char>);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let haystack = self.inner.as_ref();
+        if self.pos >= haystack.len() {
+            return None;
+        }
+        if let Some((c, len)) = decode_utf8_prefix(&haystack[self.pos..]) {
+            self.pos += len;
+            return Some((self.pos, Some(c)));
+        }
+        self.pos += 1;
+        match self.invalid {
+            InvalidSequence::Reset => Some((self.pos, None)),
+            InvalidSequence::Replace => Some((self.pos, Some(char::REPLACEMENT_CHARACTER))),
+        }
+    }
+}
+
+impl<P> CodeUnitDecoder for LossyUtf8CharWithEndOffsetIterator<P>
+where
+    P: AsRef<[u8]>,
+{
+    #[inline(always)]
+    fn set_pos(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    #[inline(always)]
+    fn unit_length(&self, end: usize, length: u32) -> usize {
+        if self.invalid == InvalidSequence::Reset {
+            // Matches consist only of valid characters.
+            return usize::from_u32(length);
+        }
+        let haystack = self.inner.as_ref();
+        let mut pos = end;
+        let mut rest = usize::from_u32(length);
+        // A valid character can be found backward without ambiguity because a continuation byte
+        // never starts a character, and the decoder replaces invalid bytes one by one.
+        // The matched bytes always represent characters of `length` bytes in total, so the
+        // following loop reaches zero without underflow.
+        while rest != 0 {
+            let mut len = 1;
+            while len < 4 && len < pos && is_utf8_continuation(haystack[pos - len]) {
+                len += 1;
+            }
+            match decode_utf8_prefix(&haystack[pos - len..pos]) {
+                Some((c, l)) if l == len => {
+                    rest -= c.len_utf8();
+                    pos -= len;
+                }
+                _ => {
+                    rest -= char::REPLACEMENT_CHARACTER.len_utf8();
+                    pos -= 1;
+                }
+            }
+        }
+        end - pos
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_iter_utf16()`] or
+/// [`CharwiseDoubleArrayAhoCorasick::find_iter_lossy()`].
+pub struct DecodedFindIterator<'a, D, V> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: D,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<D, V> Iterator for DecodedFindIterator<'_, D, V>
+where
+    D: CodeUnitDecoder,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: self.haystack.unit_length(self.pos, out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        while let Some((pos, c)) = self.haystack.next() {
+            // state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            state_id = c.map_or(ROOT_STATE_IDX, |c| unsafe {
+                self.pma.next_state_id_unchecked(state_id, c)
+            });
+            if let Some(output_pos) = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                // output_pos is always smaller than self.pma.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos = pos;
+                self.output_pos = self.pma.next_value_pos(out);
+                return Some(Match {
+                    length: self.haystack.unit_length(self.pos, out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::find_overlapping_iter_utf16()`] or
+/// [`CharwiseDoubleArrayAhoCorasick::find_overlapping_iter_lossy()`].
+pub struct DecodedFindOverlappingIterator<'a, D, V> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: D,
+    pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<D, V> Iterator for DecodedFindOverlappingIterator<'_, D, V>
+where
+    D: CodeUnitDecoder,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // Output::parent() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = out.parent();
+            return Some(Match {
+                length: self.haystack.unit_length(self.pos, out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        while let Some((pos, c)) = self.haystack.next() {
+            self.pos = pos;
+
+            // self.state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            self.state_id = c.map_or(ROOT_STATE_IDX, |c| unsafe {
+                self.pma.next_state_id_unchecked(self.state_id, c)
+            });
+            if let Some(output_pos) = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(self.state_id))
+                    .output_pos()
+            } {
+                // output_pos.get() is always smaller than self.pma.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.output_pos = out.parent();
+                return Some(Match {
+                    length: self.haystack.unit_length(pos, out.length()),
+                    end: pos,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Iterator created by
+/// [`CharwiseDoubleArrayAhoCorasick::find_overlapping_no_suffix_iter_utf16()`] or
+/// [`CharwiseDoubleArrayAhoCorasick::find_overlapping_no_suffix_iter_lossy()`].
+pub struct DecodedFindOverlappingNoSuffixIterator<'a, D, V> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: D,
+    pub(crate) state_id: u32,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<D, V> Iterator for DecodedFindOverlappingNoSuffixIterator<'_, D, V>
+where
+    D: CodeUnitDecoder,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: self.haystack.unit_length(self.pos, out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        while let Some((pos, c)) = self.haystack.next() {
+            // self.state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_unchecked() ensures to return such a value.
+            self.state_id = c.map_or(ROOT_STATE_IDX, |c| unsafe {
+                self.pma.next_state_id_unchecked(self.state_id, c)
+            });
+            if let Some(output_pos) = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(self.state_id))
+                    .output_pos()
+            } {
+                // output_pos is always smaller than self.pma.outputs.len() because
+                // State::output_pos() ensures to return such a value when it is Some.
+                let out = unsafe {
+                    self.pma
+                        .outputs
+                        .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                };
+                self.pos = pos;
+                self.output_pos = self.pma.next_value_pos(out);
+                return Some(Match {
+                    length: self.haystack.unit_length(self.pos, out.length()),
+                    end: self.pos,
+                    value: out.value(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Iterator created by [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter_utf16()`] or
+/// [`CharwiseDoubleArrayAhoCorasick::leftmost_find_iter_lossy()`].
+pub struct DecodedLeftmostFindIterator<'a, D, V> {
+    pub(crate) pma: &'a CharwiseDoubleArrayAhoCorasick<V>,
+    pub(crate) haystack: D,
+    pub(crate) pos: usize,
+    pub(crate) output_pos: Option<NonZeroU32>,
+}
+
+impl<D, V> Iterator for DecodedLeftmostFindIterator<'_, D, V>
+where
+    D: CodeUnitDecoder,
+    V: Copy,
+{
+    type Item = Match<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(output_pos) = self.output_pos {
+            // output_pos.get() is always smaller than self.pma.outputs.len() because
+            // next_value_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            return Some(Match {
+                length: self.haystack.unit_length(self.pos, out.length()),
+                end: self.pos,
+                value: out.value(),
+            });
+        }
+
+        let mut state_id = ROOT_STATE_IDX;
+        let mut last_output_pos: Option<NonZeroU32> = None;
+
+        self.haystack.set_pos(self.pos);
+        while let Some((pos, c)) = self.haystack.next() {
+            // state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+            state_id = c.map_or(ROOT_STATE_IDX, |c| unsafe {
+                self.pma.next_state_id_leftmost_unchecked(state_id, c)
+            });
+            if state_id == ROOT_STATE_IDX {
+                if let Some(output_pos) = last_output_pos {
+                    // last_output_pos is always smaller than self.pma.outputs.len() because
+                    // State::output_pos() ensures to return such a value when it is Some.
+                    let out = unsafe {
+                        self.pma
+                            .outputs
+                            .get_unchecked(usize::from_u32(output_pos.get() - 1))
+                    };
+                    self.output_pos = self.pma.next_value_pos(out);
+                    return Some(Match {
+                        length: self.haystack.unit_length(self.pos, out.length()),
+                        end: self.pos,
+                        value: out.value(),
+                    });
+                }
+            // state_id is always smaller than self.pma.states.len() because
+            // self.pma.next_state_id_leftmost_unchecked() ensures to return such a value.
+            } else if let Some(output_pos) = unsafe {
+                self.pma
+                    .states
+                    .get_unchecked(usize::from_u32(state_id))
+                    .output_pos()
+            } {
+                last_output_pos.replace(output_pos);
+                self.pos = pos;
+            }
+        }
+
+        last_output_pos.map(|output_pos| {
+            // last_output_pos is always smaller than self.pma.outputs.len() because
+            // State::output_pos() ensures to return such a value when it is Some.
+            let out = unsafe {
+                self.pma
+                    .outputs
+                    .get_unchecked(usize::from_u32(output_pos.get() - 1))
+            };
+            self.output_pos = self.pma.next_value_pos(out);
+            Match {
+                length: self.haystack.unit_length(self.pos, out.length()),
+                end: self.pos,
+                value: out.value(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_char_with_end_offset_iterator() {
+        let test_string =
+            "\u{0000}\u{0001}\u{0002}\u{0004}\u{0008}\u{0010}\u{001f}\u{0020}\u{0040}\
+             \u{007f}\u{0080}\u{0100}\u{01ff}\u{0200}\u{0400}\u{07ff}\u{0800}\u{1000}\
+             \u{1fff}\u{2000}\u{4000}\u{8000}\u{ffff}\u{10000}\
+             \u{1ffff}\u{20000}\u{40000}\u{80000}\u{100000}\u{10ffff}";
+        let mut it =
+            unsafe { CharWithEndOffsetIterator::new(test_string.as_bytes().iter().copied()) };
+
+        // 1 byte
+        assert_eq!(Some((1, '\u{0000}')), it.next());
+        assert_eq!(Some((2, '\u{0001}')), it.next());
+        assert_eq!(Some((3, '\u{0002}')), it.next());
+        assert_eq!(Some((4, '\u{0004}')), it.next());
+        assert_eq!(Some((5, '\u{0008}')), it.next());
+        assert_eq!(Some((6, '\u{0010}')), it.next());
+        assert_eq!(Some((7, '\u{001f}')), it.next());
+        assert_eq!(Some((8, '\u{0020}')), it.next());
+        assert_eq!(Some((9, '\u{0040}')), it.next());
+        assert_eq!(Some((10, '\u{007f}')), it.next());
+
+        // 2 bytes
+        assert_eq!(Some((12, '\u{0080}')), it.next());
+        assert_eq!(Some((14, '\u{0100}')), it.next());
+        assert_eq!(Some((16, '\u{01ff}')), it.next());
+        assert_eq!(Some((18, '\u{0200}')), it.next());
+        assert_eq!(Some((20, '\u{0400}')), it.next());
+        assert_eq!(Some((22, '\u{07ff}')), it.next());
+
+        // 3 bytes
+        assert_eq!(Some((25, '\u{0800}')), it.next());
+        assert_eq!(Some((28, '\u{1000}')), it.next());
+        assert_eq!(Some((31, '\u{1fff}')), it.next());
+        assert_eq!(Some((34, '\u{2000}')), it.next());
+        assert_eq!(Some((37, '\u{4000}')), it.next());
+        assert_eq!(Some((40, '\u{8000}')), it.next());
+        assert_eq!(Some((43, '\u{ffff}')), it.next());
 
         // 4 bytes
         assert_eq!(Some((47, '\u{10000}')), it.next());
@@ -379,4 +1413,100 @@ mod tests {
         assert_eq!(None, it.next());
         assert_eq!(None, it.next());
     }
+
+    #[test]
+    fn test_utf16_char_with_end_offset_iterator() {
+        let test_string = "a\u{00e9}\u{3042}\u{1f600}\u{10ffff}";
+        let units: Vec<u16> = test_string.encode_utf16().collect();
+        let mut it = Utf16CharWithEndOffsetIterator::new(&units, InvalidSequence::Reset);
+
+        assert_eq!(Some((1, Some('a'))), it.next());
+        assert_eq!(Some((2, Some('\u{00e9}'))), it.next());
+        assert_eq!(Some((3, Some('\u{3042}'))), it.next());
+        assert_eq!(Some((5, Some('\u{1f600}'))), it.next());
+        assert_eq!(Some((7, Some('\u{10ffff}'))), it.next());
+        assert_eq!(None, it.next());
+
+        assert_eq!(1, it.unit_length(1, 1));
+        assert_eq!(2, it.unit_length(3, 5));
+        assert_eq!(4, it.unit_length(7, 8));
+        assert_eq!(7, it.unit_length(7, 14));
+    }
+
+    #[test]
+    fn test_utf16_unpaired_surrogates() {
+        // high surrogate at the end, low surrogate alone, and high surrogate followed by 'a'
+        let units = [0x0061, 0xdc00, 0xd800, 0x0061, 0xd800];
+
+        let mut it = Utf16CharWithEndOffsetIterator::new(&units, InvalidSequence::Reset);
+        assert_eq!(Some((1, Some('a'))), it.next());
+        assert_eq!(Some((2, None)), it.next());
+        assert_eq!(Some((3, None)), it.next());
+        assert_eq!(Some((4, Some('a'))), it.next());
+        assert_eq!(Some((5, None)), it.next());
+        assert_eq!(None, it.next());
+
+        let mut it = Utf16CharWithEndOffsetIterator::new(&units, InvalidSequence::Replace);
+        assert_eq!(Some((1, Some('a'))), it.next());
+        assert_eq!(Some((2, Some('\u{fffd}'))), it.next());
+        assert_eq!(Some((3, Some('\u{fffd}'))), it.next());
+        assert_eq!(Some((4, Some('a'))), it.next());
+        assert_eq!(Some((5, Some('\u{fffd}'))), it.next());
+        assert_eq!(None, it.next());
+
+        assert_eq!(3, it.unit_length(4, 7));
+    }
+
+    #[test]
+    fn test_lossy_utf8_char_with_end_offset_iterator() {
+        let test_string = "\u{0000}\u{007f}\u{0080}\u{07ff}\u{0800}\u{ffff}\u{10000}\u{10ffff}";
+        let mut it = LossyUtf8CharWithEndOffsetIterator::new(test_string, InvalidSequence::Reset);
+        let mut expected = vec![];
+        let mut end = 0;
+        for c in test_string.chars() {
+            end += c.len_utf8();
+            expected.push((end, Some(c)));
+        }
+        assert_eq!(expected, it.by_ref().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lossy_utf8_invalid_bytes() {
+        // overlong encoding, surrogate, out of range, truncated sequence, and lone continuation
+        let bytes = b"\xc0\xafa\xed\xa0\x80\xf4\x90\x80\x80\xe3\x81\x82\xe3\x81b\x80";
+
+        let it = LossyUtf8CharWithEndOffsetIterator::new(bytes, InvalidSequence::Reset);
+        let mut expected = vec![(1, None), (2, None), (3, Some('a'))];
+        expected.extend((4..=10).map(|end| (end, None)));
+        expected.extend([
+            (13, Some('\u{3042}')),
+            (14, None),
+            (15, None),
+            (16, Some('b')),
+            (17, None),
+        ]);
+        assert_eq!(expected, it.collect::<Vec<_>>());
+
+        let it = LossyUtf8CharWithEndOffsetIterator::new(bytes, InvalidSequence::Replace);
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(end, c)| (end, Some(c.unwrap_or(char::REPLACEMENT_CHARACTER))))
+            .collect();
+        assert_eq!(expected, it.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lossy_utf8_unit_length() {
+        let bytes = b"\xe3\x81\xe3\x81\x82\x82";
+
+        let it = LossyUtf8CharWithEndOffsetIterator::new(bytes, InvalidSequence::Replace);
+        // U+FFFD U+FFFD U+3042 U+FFFD
+        assert_eq!(1, it.unit_length(6, 3));
+        assert_eq!(4, it.unit_length(6, 6));
+        assert_eq!(5, it.unit_length(5, 9));
+        assert_eq!(6, it.unit_length(6, 12));
+
+        let it = LossyUtf8CharWithEndOffsetIterator::new(bytes, InvalidSequence::Reset);
+        assert_eq!(3, it.unit_length(5, 3));
+    }
 }
@@ -1,3 +1,4 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 use crate::serializer::{Serializable, SerializableVec};
@@ -5,40 +6,192 @@ use crate::utils::FromU32;
 
 pub const INVALID_CODE: u32 = u32::MAX;
 
+// The number of code points in a page of the two-level table.
+const PAGE_BITS: u32 = 8;
+const PAGE_LEN: usize = 1 << PAGE_BITS;
+const PAGE_MASK: u32 = (1 << PAGE_BITS) - 1;
+
+// The flag set to the serialized alphabet size if the two-level table is used.
+//
+// The dense table is serialized in the same layout as older versions, and the flag never appears
+// in their data since the alphabet size is at most the number of characters.
+const PAGED_FLAG: u32 = 1 << 31;
+
+/// Kind of a page of the two-level table.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum PageKind {
+    // No character is mapped.
+    Empty,
+    // All the characters are mapped to the code.
+    Filled(u32),
+    Mixed,
+}
+
+/// Mapper from characters to codes.
+///
+/// The mapping is stored in either of the following tables, chosen to minimize memory usage:
+///
+///  - Dense: `table[c]` directly stores the code of `c`, and `pages` is empty.
+///  - Two-level: `table[pages[c >> PAGE_BITS] + (c & PAGE_MASK)]` stores the code of `c`. The
+///    first page of `table` is filled with [`INVALID_CODE`] and shared by all pages containing no
+///    mapped characters, so sparse high code points such as emoji do not allocate a huge table.
+///    Likewise, a page filled with a code is shared by all pages whose characters are all mapped
+///    to the code, so large classes of characters do not allocate a huge table.
 #[derive(Default, Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(crate = "serde"))]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 pub struct CodeMapper {
     table: Vec<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pages: Vec<u32>,
     alphabet_size: u32,
 }
 
 impl CodeMapper {
-    pub fn new(freqs: &[u32]) -> Self {
-        let sorted = {
+    /// Creates a mapper assigning codes to characters in descending order of frequency.
+    ///
+    /// Only characters with non-zero `freqs[c]`, i.e., appearing in patterns, are mapped. They are
+    /// ranked by `text_freqs[c]` first and by `freqs[c]` second.
+    pub fn new(freqs: &[u32], text_freqs: &BTreeMap<char, u64>) -> Self {
+        let classes: Vec<_> = freqs
+            .iter()
+            .zip(0..)
+            .filter(|(&f, _)| f != 0)
+            .map(|(&f, c)| (c, c + 1, f))
+            .collect();
+        Self::from_classes(freqs.len(), &classes, text_freqs)
+    }
+
+    /// Creates a mapper assigning a code to each class of characters in descending order of
+    /// frequency.
+    ///
+    /// `classes` are disjoint ranges `start..end` of code points in ascending order with their
+    /// frequencies, and characters in no class are not mapped. The classes are ranked by the sum
+    /// of `text_freqs` over their characters first and by their frequencies second.
+    pub fn with_classes(classes: &[(u32, u32, u32)], text_freqs: &BTreeMap<char, u64>) -> Self {
+        let len = classes
+            .last()
+            .map_or(0, |&(_, end, _)| usize::from_u32(end));
+        Self::from_classes(len, classes, text_freqs)
+    }
+
+    fn from_classes(
+        len: usize,
+        classes: &[(u32, u32, u32)],
+        text_freqs: &BTreeMap<char, u64>,
+    ) -> Self {
+        let codes = {
             let mut sorted = vec![];
-            for (c, &f) in freqs.iter().enumerate().filter(|(_, &f)| f != 0) {
-                sorted.push((c, f));
+            for (i, &(start, end, f)) in classes.iter().enumerate() {
+                let tf = match (char::from_u32(start), char::from_u32(end - 1)) {
+                    (Some(first), Some(last)) => text_freqs
+                        .range(first..=last)
+                        .fold(0u64, |acc, (_, &tf)| acc.saturating_add(tf)),
+                    _ => 0,
+                };
+                sorted.push((i, (tf, f)));
             }
-            // Note: `c1.cmp(c2)` is necessary to uniquely determine the sort result.
-            sorted.sort_unstable_by(|(c1, f1), (c2, f2)| f2.cmp(f1).then_with(|| c1.cmp(c2)));
-            sorted
+            // Note: `i1.cmp(i2)` is necessary to uniquely determine the sort result.
+            sorted.sort_unstable_by(|(i1, f1), (i2, f2)| f2.cmp(f1).then_with(|| i1.cmp(i2)));
+            let mut codes = vec![0; classes.len()];
+            for (code, (i, _)) in sorted.into_iter().enumerate() {
+                codes[i] = u32::try_from(code).unwrap();
+            }
+            codes
         };
-        let mut table = vec![INVALID_CODE; freqs.len()];
-        for (i, &(c, _)) in sorted.iter().enumerate() {
-            table[c] = u32::try_from(i).unwrap();
+        let alphabet_size = u32::try_from(classes.len()).unwrap();
+
+        let num_pages = (len + PAGE_LEN - 1) / PAGE_LEN;
+        let mut kinds = vec![PageKind::Empty; num_pages];
+        for (&(start, end, _), &code) in classes.iter().zip(&codes) {
+            let (start, end) = (usize::from_u32(start), usize::from_u32(end));
+            let first_page = start >> PAGE_BITS;
+            let last_page = (end - 1) >> PAGE_BITS;
+            for (kind, page) in kinds[first_page..=last_page].iter_mut().zip(first_page..) {
+                let filled = start <= page << PAGE_BITS && (page + 1) << PAGE_BITS <= end;
+                *kind = match *kind {
+                    PageKind::Empty if filled => PageKind::Filled(code),
+                    _ => PageKind::Mixed,
+                };
+            }
+        }
+        let mut filled_codes: Vec<u32> = kinds
+            .iter()
+            .filter_map(|&kind| match kind {
+                PageKind::Filled(code) => Some(code),
+                _ => None,
+            })
+            .collect();
+        filled_codes.sort_unstable();
+        filled_codes.dedup();
+        let num_mixed_pages = kinds
+            .iter()
+            .filter(|&&kind| kind == PageKind::Mixed)
+            .count();
+        let paged_len = num_pages + (filled_codes.len() + num_mixed_pages + 1) * PAGE_LEN;
+
+        if len <= paged_len {
+            let mut table = vec![INVALID_CODE; len];
+            for (&(start, end, _), &code) in classes.iter().zip(&codes) {
+                table[usize::from_u32(start)..usize::from_u32(end)].fill(code);
+            }
+            return Self {
+                table,
+                pages: vec![],
+                alphabet_size,
+            };
+        }
+
+        let mut pages = vec![0; num_pages];
+        let mut table = vec![INVALID_CODE; PAGE_LEN];
+        let mut filled_pages = BTreeMap::new();
+        for (page, &kind) in pages.iter_mut().zip(&kinds) {
+            match kind {
+                PageKind::Empty => {}
+                PageKind::Filled(code) => {
+                    *page = *filled_pages.entry(code).or_insert_with(|| {
+                        let offset = u32::try_from(table.len()).unwrap();
+                        table.resize(table.len() + PAGE_LEN, code);
+                        offset
+                    });
+                }
+                PageKind::Mixed => {
+                    *page = u32::try_from(table.len()).unwrap();
+                    table.resize(table.len() + PAGE_LEN, INVALID_CODE);
+                }
+            }
+        }
+        for (&(start, end, _), &code) in classes.iter().zip(&codes) {
+            let (start, end) = (usize::from_u32(start), usize::from_u32(end));
+            for page in start >> PAGE_BITS..=(end - 1) >> PAGE_BITS {
+                if kinds[page] == PageKind::Mixed {
+                    let offset = usize::from_u32(pages[page]);
+                    let page_start = page << PAGE_BITS;
+                    let first = start.max(page_start) - page_start;
+                    let last = end.min(page_start + PAGE_LEN) - page_start;
+                    table[offset + first..offset + last].fill(code);
+                }
+            }
         }
         Self {
             table,
-            alphabet_size: u32::try_from(sorted.len()).unwrap(),
+            pages,
+            alphabet_size,
         }
     }
 
     #[inline(always)]
     pub fn get(&self, c: char) -> Option<u32> {
+        let c = u32::from(c);
+        let idx = if self.pages.is_empty() {
+            usize::from_u32(c)
+        } else {
+            let page = *self.pages.get(usize::from_u32(c >> PAGE_BITS))?;
+            usize::from_u32(page + (c & PAGE_MASK))
+        };
         self.table
-            .get(usize::from_u32(u32::from(c)))
+            .get(idx)
             .copied()
             .filter(|&code| code != INVALID_CODE)
     }
@@ -49,9 +202,8 @@ impl CodeMapper {
     }
 
     #[inline]
-    #[allow(dead_code)]
     pub fn heap_bytes(&self) -> usize {
-        self.table.len() * core::mem::size_of::<u32>()
+        (self.table.len() + self.pages.len()) * core::mem::size_of::<u32>()
     }
 }
 
@@ -59,17 +211,28 @@ impl SerializableVec for CodeMapper {
     #[inline(always)]
     fn serialize_to_vec(&self, dst: &mut Vec<u8>) {
         self.table.serialize_to_vec(dst);
-        self.alphabet_size.serialize_to_vec(dst);
+        if self.pages.is_empty() {
+            self.alphabet_size.serialize_to_vec(dst);
+        } else {
+            (self.alphabet_size | PAGED_FLAG).serialize_to_vec(dst);
+            self.pages.serialize_to_vec(dst);
+        }
     }
 
     #[inline(always)]
     fn deserialize_from_slice(src: &[u8]) -> (Self, &[u8]) {
         let (table, src) = Vec::<u32>::deserialize_from_slice(src);
         let (alphabet_size, src) = u32::deserialize_from_slice(src);
+        let (pages, src) = if alphabet_size & PAGED_FLAG == 0 {
+            (vec![], src)
+        } else {
+            Vec::<u32>::deserialize_from_slice(src)
+        };
         (
             Self {
                 table,
-                alphabet_size,
+                pages,
+                alphabet_size: alphabet_size & !PAGED_FLAG,
             },
             src,
         )
@@ -77,7 +240,12 @@ impl SerializableVec for CodeMapper {
 
     #[inline(always)]
     fn serialized_bytes(&self) -> usize {
-        self.table.serialized_bytes() + u32::serialized_bytes()
+        let pages_bytes = if self.pages.is_empty() {
+            0
+        } else {
+            self.pages.serialized_bytes()
+        };
+        self.table.serialized_bytes() + u32::serialized_bytes() + pages_bytes
     }
 }
 
@@ -88,7 +256,7 @@ mod tests {
     #[test]
     fn test_charwise_code_mapper() {
         let freqs = vec![3, 6, 0, 2, 3, 0, 3];
-        let mapper = CodeMapper::new(&freqs);
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
 
         assert_eq!(mapper.get(0 as char), Some(1));
         assert_eq!(mapper.get(1 as char), Some(0));
@@ -100,10 +268,96 @@ mod tests {
         assert_eq!(mapper.get(7 as char), None); // out-of-range
     }
 
+    #[test]
+    fn test_charwise_code_mapper_text_freqs() {
+        let freqs = vec![3, 6, 0, 2, 3, 0, 3];
+        let text_freqs = [
+            (3 as char, 10),
+            (4 as char, 10),
+            (6 as char, 20),
+            (5 as char, 30),
+        ]
+        .into_iter()
+        .collect();
+        let mapper = CodeMapper::new(&freqs, &text_freqs);
+
+        assert_eq!(mapper.get(0 as char), Some(4));
+        assert_eq!(mapper.get(1 as char), Some(3));
+        assert_eq!(mapper.get(2 as char), None);
+        assert_eq!(mapper.get(3 as char), Some(2));
+        assert_eq!(mapper.get(4 as char), Some(1));
+        assert_eq!(mapper.get(5 as char), None); // not in patterns
+        assert_eq!(mapper.get(6 as char), Some(0));
+        assert_eq!(mapper.alphabet_size(), 5);
+    }
+
+    #[test]
+    fn test_charwise_code_mapper_sparse() {
+        let mut freqs = vec![0; 0x1f601];
+        freqs[0x61] = 2;
+        freqs[0x3042] = 3;
+        freqs[0x1f600] = 1;
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
+
+        assert!(!mapper.pages.is_empty());
+        assert!(mapper.heap_bytes() < freqs.len());
+
+        assert_eq!(mapper.get('a'), Some(1));
+        assert_eq!(mapper.get('\u{3042}'), Some(0));
+        assert_eq!(mapper.get('\u{1f600}'), Some(2));
+        assert_eq!(mapper.get('b'), None);
+        assert_eq!(mapper.get('\u{3043}'), None);
+        assert_eq!(mapper.get('\u{1f5ff}'), None);
+        assert_eq!(mapper.get('\u{1f601}'), None); // out-of-range
+        assert_eq!(mapper.get('\u{10ffff}'), None); // out-of-range
+        assert_eq!(mapper.alphabet_size(), 3);
+    }
+
+    #[test]
+    fn test_charwise_code_mapper_dense() {
+        let freqs: Vec<u32> = (0..0x3000).map(|c| c % 2).collect();
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
+
+        assert!(mapper.pages.is_empty());
+        for c in 0..0x3000 {
+            let c = char::from_u32(c).unwrap();
+            assert_eq!(mapper.get(c).is_some(), u32::from(c) % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn test_serialize_sparse() {
+        let mut freqs = vec![0; 0x20000];
+        freqs[0x61] = 2;
+        freqs[0x1ffff] = 1;
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
+
+        let mut data = vec![];
+        mapper.serialize_to_vec(&mut data);
+        assert_eq!(data.len(), mapper.serialized_bytes());
+        let (other, rest) = CodeMapper::deserialize_from_slice(&data);
+        assert!(rest.is_empty());
+        assert_eq!(mapper, other);
+    }
+
+    #[test]
+    fn test_serialize_dense_layout() {
+        let freqs = vec![3, 6, 0, 2, 3, 0, 3];
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
+
+        // The dense table keeps the layout of older versions: the table and the alphabet size.
+        let mut expected = vec![];
+        mapper.table.serialize_to_vec(&mut expected);
+        mapper.alphabet_size.serialize_to_vec(&mut expected);
+        let mut data = vec![];
+        mapper.serialize_to_vec(&mut data);
+        assert_eq!(expected, data);
+    }
+
     #[test]
     fn test_serialize() {
         let freqs = vec![3, 6, 0, 2, 3, 0, 3];
-        let mapper = CodeMapper::new(&freqs);
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
 
         let mut data = vec![];
         mapper.serialize_to_vec(&mut data);
@@ -117,7 +371,7 @@ mod tests {
     #[test]
     fn test_serde() {
         let freqs = vec![3, 6, 0, 2, 3, 0, 3];
-        let mapper = CodeMapper::new(&freqs);
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
 
         let serialized = serde_json::to_string(&mapper).unwrap();
         let other: CodeMapper = serde_json::from_str(&serialized).unwrap();
@@ -128,7 +382,7 @@ mod tests {
     #[test]
     fn test_bitcode() {
         let freqs = vec![3, 6, 0, 2, 3, 0, 3];
-        let mapper = CodeMapper::new(&freqs);
+        let mapper = CodeMapper::new(&freqs, &BTreeMap::new());
 
         let encoded = bitcode::encode(&mapper);
         let other = bitcode::decode::<CodeMapper>(&encoded).unwrap();
@@ -0,0 +1,242 @@
+//! Iterators of spans covered by matches.
+
+use core::cmp::Reverse;
+use core::mem;
+use core::ops::Range;
+
+use alloc::collections::{BTreeMap, BinaryHeap, VecDeque};
+use alloc::vec::Vec;
+
+use crate::Match;
+
+/// Iterator of the merged spans covered by matches, created by `covered_spans()`.
+///
+/// The spans are non-overlapping, non-adjacent, and in ascending order. They are yielded as
+/// soon as no subsequent match can touch them, so the memory usage does not depend on the
+/// haystack length.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{CoveredSpanIterator, DoubleArrayAhoCorasick};
+///
+/// let patterns = vec!["bcd", "ab", "a", "e"];
+/// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+///
+/// // The same as pma.covered_spans("abcdxef").
+/// let it = CoveredSpanIterator::new(pma.find_overlapping_iter("abcdxef"), 3);
+///
+/// assert_eq!(vec![0..4, 5..6], it.collect::<Vec<_>>());
+/// ```
+pub struct CoveredSpanIterator<I> {
+    matches: I,
+    max_length: usize,
+    last_end: usize,
+    finished: bool,
+    spans: VecDeque<Range<usize>>,
+}
+
+impl<I> CoveredSpanIterator<I> {
+    /// Creates an iterator of the spans covered by the given matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - Iterator of matches in non-decreasing order of their ends, such as the one
+    ///   returned by `find_overlapping_iter()`.
+    /// * `max_length` - Upper bound of the match lengths, such as the length of the longest
+    ///   pattern in bytes.
+    #[must_use]
+    pub fn new(matches: I, max_length: usize) -> Self {
+        Self {
+            matches,
+            max_length,
+            last_end: 0,
+            finished: false,
+            spans: VecDeque::new(),
+        }
+    }
+}
+
+impl<I, V> Iterator for CoveredSpanIterator<I>
+where
+    I: Iterator<Item = Match<V>>,
+    V: Copy,
+{
+    type Item = Range<usize>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(span) = self.spans.front() {
+                // Subsequent matches start at or after self.last_end - self.max_length.
+                if self.finished || span.end + self.max_length < self.last_end {
+                    return self.spans.pop_front();
+                }
+            }
+            if self.finished {
+                return None;
+            }
+            if let Some(m) = self.matches.next() {
+                let mut span = m.start()..m.end();
+                // The pending spans end at or before m.end().
+                while let Some(back) = self.spans.back() {
+                    if back.end < span.start {
+                        break;
+                    }
+                    span.start = span.start.min(back.start);
+                    self.spans.pop_back();
+                }
+                self.spans.push_back(span);
+                self.last_end = m.end();
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+}
+
+/// Iterator of the spans covered by matches with the values of the matches covering them,
+/// created by `covered_spans_with_values()`.
+///
+/// The spans are non-overlapping and in ascending order. Each span is annotated with the
+/// sorted and deduplicated values covering it, and adjacent spans have different values. The
+/// spans are yielded as soon as no subsequent match can change them, so the memory usage does
+/// not depend on the haystack length.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{DoubleArrayAhoCorasick, ValuedCoveredSpanIterator};
+///
+/// let patterns = vec!["bcd", "ab", "a", "e"];
+/// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+///
+/// // The same as pma.covered_spans_with_values("abcdxef").
+/// let it = ValuedCoveredSpanIterator::new(pma.find_overlapping_iter("abcdxef"), 3);
+///
+/// assert_eq!(
+///     vec![
+///         (0..1, vec![1, 2]),
+///         (1..2, vec![0, 1]),
+///         (2..4, vec![0]),
+///         (5..6, vec![3]),
+///     ],
+///     it.collect::<Vec<_>>(),
+/// );
+/// ```
+pub struct ValuedCoveredSpanIterator<I, V> {
+    matches: I,
+    max_length: usize,
+    finished: bool,
+    starts: BinaryHeap<Reverse<(usize, V)>>,
+    ends: VecDeque<(usize, V)>,
+    counts: BTreeMap<V, usize>,
+    current: Vec<V>,
+    current_start: usize,
+    spans: VecDeque<(Range<usize>, Vec<V>)>,
+}
+
+impl<I, V> ValuedCoveredSpanIterator<I, V>
+where
+    V: Ord,
+{
+    /// Creates an iterator of the spans covered by the given matches with their values.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - Iterator of matches in non-decreasing order of their ends, such as the one
+    ///   returned by `find_overlapping_iter()`.
+    /// * `max_length` - Upper bound of the match lengths, such as the length of the longest
+    ///   pattern in bytes.
+    #[must_use]
+    pub fn new(matches: I, max_length: usize) -> Self {
+        Self {
+            matches,
+            max_length,
+            finished: false,
+            starts: BinaryHeap::new(),
+            ends: VecDeque::new(),
+            counts: BTreeMap::new(),
+            current: vec![],
+            current_start: 0,
+            spans: VecDeque::new(),
+        }
+    }
+}
+
+impl<I, V> ValuedCoveredSpanIterator<I, V>
+where
+    V: Copy + Ord,
+{
+    /// Sweeps the starts and ends of the pending matches before `frontier`.
+    fn sweep(&mut self, frontier: usize) {
+        loop {
+            let start = self.starts.peek().map(|Reverse((pos, _))| *pos);
+            let end = self.ends.front().map(|(pos, _)| *pos);
+            let pos = match (start, end) {
+                (Some(start), Some(end)) => start.min(end),
+                (Some(pos), None) | (None, Some(pos)) => pos,
+                (None, None) => return,
+            };
+            if pos >= frontier {
+                return;
+            }
+            while let Some(&Reverse((start, value))) = self.starts.peek() {
+                if start != pos {
+                    break;
+                }
+                self.starts.pop();
+                *self.counts.entry(value).or_insert(0) += 1;
+            }
+            while let Some(&(end, value)) = self.ends.front() {
+                if end != pos {
+                    break;
+                }
+                self.ends.pop_front();
+                if let Some(count) = self.counts.get_mut(&value) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.counts.remove(&value);
+                    }
+                }
+            }
+            let values: Vec<V> = self.counts.keys().copied().collect();
+            if values != self.current {
+                let values = mem::replace(&mut self.current, values);
+                if !values.is_empty() {
+                    self.spans.push_back((self.current_start..pos, values));
+                }
+                self.current_start = pos;
+            }
+        }
+    }
+}
+
+impl<I, V> Iterator for ValuedCoveredSpanIterator<I, V>
+where
+    I: Iterator<Item = Match<V>>,
+    V: Copy + Ord,
+{
+    type Item = (Range<usize>, Vec<V>);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(span) = self.spans.pop_front() {
+                return Some(span);
+            }
+            if self.finished {
+                return None;
+            }
+            if let Some(m) = self.matches.next() {
+                self.starts.push(Reverse((m.start(), m.value())));
+                self.ends.push_back((m.end(), m.value()));
+                // Subsequent matches start at or after m.end() - self.max_length.
+                self.sweep(m.end().saturating_sub(self.max_length));
+            } else {
+                self.finished = true;
+                self.sweep(usize::MAX);
+            }
+        }
+    }
+}
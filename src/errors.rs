@@ -4,9 +4,13 @@ use core::result;
 
 use alloc::fmt;
 use alloc::string::String;
+use alloc::vec::Vec;
 
 /// Errors in daachorse.
+///
+/// New variants may be added in minor versions.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DaachorseError {
     /// Contains [`InvalidArgumentError`].
     InvalidArgument(InvalidArgumentError),
@@ -19,6 +23,9 @@ pub enum DaachorseError {
 
     /// Contains [`InvalidConversionError`].
     InvalidConversion(InvalidConversionError),
+
+    /// Contains [`UnreachablePatternError`].
+    UnreachablePattern(UnreachablePatternError),
 }
 
 impl fmt::Display for DaachorseError {
@@ -28,10 +35,15 @@ impl fmt::Display for DaachorseError {
             Self::DuplicatePattern(e) => e.fmt(f),
             Self::AutomatonScale(e) => e.fmt(f),
             Self::InvalidConversion(e) => e.fmt(f),
+            Self::UnreachablePattern(e) => e.fmt(f),
         }
     }
 }
 
+// The inner error is not returned by `source()` since `Display` already prints it.
+#[cfg(feature = "std")]
+impl std::error::Error for DaachorseError {}
+
 impl DaachorseError {
     pub(crate) const fn invalid_argument(arg: &'static str, op: &'static str, value: u32) -> Self {
         Self::InvalidArgument(InvalidArgumentError { arg, op, value })
@@ -48,6 +60,29 @@ impl DaachorseError {
     pub(crate) const fn invalid_conversion(arg: &'static str, target: &'static str) -> Self {
         Self::InvalidConversion(InvalidConversionError { arg, target })
     }
+
+    /// Maps the indices of patterns in the error with `f`.
+    pub(crate) fn map_pattern_indices<F>(self, f: F) -> Self
+    where
+        F: Fn(usize) -> usize,
+    {
+        match self {
+            Self::UnreachablePattern(e) => Self::UnreachablePattern(UnreachablePatternError {
+                index: f(e.index),
+                pattern: e.pattern,
+                prefix: f(e.prefix),
+            }),
+            e => e,
+        }
+    }
+
+    pub(crate) const fn unreachable_pattern(index: usize, pattern: Vec<u8>, prefix: usize) -> Self {
+        Self::UnreachablePattern(UnreachablePatternError {
+            index,
+            pattern,
+            prefix,
+        })
+    }
 }
 
 /// Error used when the argument is invalid.
@@ -63,6 +98,26 @@ pub struct InvalidArgumentError {
     value: u32,
 }
 
+impl InvalidArgumentError {
+    /// Returns the name of the argument.
+    #[must_use]
+    pub const fn arg(&self) -> &'static str {
+        self.arg
+    }
+
+    /// Returns the condition operator, such as `">="`.
+    #[must_use]
+    pub const fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// Returns the condition value.
+    #[must_use]
+    pub const fn value(&self) -> u32 {
+        self.value
+    }
+}
+
 impl fmt::Display for InvalidArgumentError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -73,6 +128,9 @@ impl fmt::Display for InvalidArgumentError {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidArgumentError {}
+
 /// Error used when some patterns are duplicated.
 #[derive(Debug)]
 pub struct DuplicatePatternError {
@@ -80,12 +138,23 @@ pub struct DuplicatePatternError {
     pattern: String,
 }
 
+impl DuplicatePatternError {
+    /// Returns the duplicate pattern formatted with [`Debug`](core::fmt::Debug).
+    #[must_use]
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
 impl fmt::Display for DuplicatePatternError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "DuplicatePatternError: {}", self.pattern)
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for DuplicatePatternError {}
+
 /// Error used when the scale of the automaton exceeds the expected one.
 #[derive(Debug)]
 pub struct AutomatonScaleError {
@@ -96,6 +165,20 @@ pub struct AutomatonScaleError {
     max_value: u32,
 }
 
+impl AutomatonScaleError {
+    /// Returns the name of the argument.
+    #[must_use]
+    pub const fn arg(&self) -> &'static str {
+        self.arg
+    }
+
+    /// Returns the maximum value (inclusive).
+    #[must_use]
+    pub const fn max_value(&self) -> u32 {
+        self.max_value
+    }
+}
+
 impl fmt::Display for AutomatonScaleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -106,6 +189,9 @@ impl fmt::Display for AutomatonScaleError {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for AutomatonScaleError {}
+
 /// Error used when the conversion fails.
 #[derive(Debug)]
 pub struct InvalidConversionError {
@@ -116,6 +202,20 @@ pub struct InvalidConversionError {
     target: &'static str,
 }
 
+impl InvalidConversionError {
+    /// Returns the name of the argument.
+    #[must_use]
+    pub const fn arg(&self) -> &'static str {
+        self.arg
+    }
+
+    /// Returns the name of the target type.
+    #[must_use]
+    pub const fn target(&self) -> &'static str {
+        self.target
+    }
+}
+
 impl fmt::Display for InvalidConversionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -126,5 +226,158 @@ impl fmt::Display for InvalidConversionError {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidConversionError {}
+
+/// Error used when a pattern is never reported because a prefix of it is registered earlier.
+///
+/// This is returned only if it is enabled with
+/// [`reject_unreachable()`](crate::DoubleArrayAhoCorasickBuilder::reject_unreachable) of the
+/// builders.
+#[derive(Debug)]
+pub struct UnreachablePatternError {
+    /// Index of the pattern in the input.
+    index: usize,
+
+    /// Raw bytes of the pattern.
+    pattern: Vec<u8>,
+
+    /// Index of the prefix in the input.
+    prefix: usize,
+}
+
+impl UnreachablePatternError {
+    /// Returns the index of the pattern in the input.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the raw bytes of the pattern.
+    #[must_use]
+    pub fn pattern(&self) -> &[u8] {
+        &self.pattern
+    }
+
+    /// Returns the index of the pattern shadowing this pattern in the input.
+    #[must_use]
+    pub const fn prefix(&self) -> usize {
+        self.prefix
+    }
+}
+
+impl fmt::Display for UnreachablePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UnreachablePatternError: pattern {} {:?} is shadowed by pattern {}",
+            self.index, self.pattern, self.prefix
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnreachablePatternError {}
+
+/// Problem of an input pattern found in validation.
+///
+/// This is returned by [`validate()`](crate::DoubleArrayAhoCorasickBuilder::validate) of the
+/// builders.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PatternProblem {
+    /// Index of the pattern in the input.
+    index: usize,
+
+    /// Raw bytes of the pattern.
+    pattern: Vec<u8>,
+
+    /// Kind of the problem.
+    kind: PatternProblemKind,
+}
+
+impl PatternProblem {
+    pub(crate) fn new(index: usize, pattern: &[u8], kind: PatternProblemKind) -> Self {
+        Self {
+            index,
+            pattern: pattern.to_vec(),
+            kind,
+        }
+    }
+
+    /// Returns the index of the pattern in the input.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the raw bytes of the pattern.
+    #[must_use]
+    pub fn pattern(&self) -> &[u8] {
+        &self.pattern
+    }
+
+    /// Returns the kind of the problem.
+    #[must_use]
+    pub const fn kind(&self) -> PatternProblemKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for PatternProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern {} {:?}: ", self.index, self.pattern)?;
+        match self.kind {
+            PatternProblemKind::Empty => write!(f, "empty pattern"),
+            PatternProblemKind::Duplicate { first } => {
+                write!(f, "duplicate of pattern {first}")
+            }
+            PatternProblemKind::Unreachable { prefix } => {
+                write!(f, "unreachable due to pattern {prefix}")
+            }
+            PatternProblemKind::TooLong { max_len } => {
+                write!(f, "length must be <= {max_len}")
+            }
+            PatternProblemKind::TooMany { max_patterns } => {
+                write!(f, "number of patterns must be <= {max_patterns}")
+            }
+        }
+    }
+}
+
+/// Kind of [`PatternProblem`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum PatternProblemKind {
+    /// The pattern is empty.
+    Empty,
+
+    /// The pattern is the same as the pattern at index `first`. This is reported only under
+    /// [`DuplicatePolicy::Error`](crate::DuplicatePolicy::Error).
+    Duplicate {
+        /// Index of the first occurrence.
+        first: usize,
+    },
+
+    /// The pattern is never reported under
+    /// [`MatchKind::LeftmostFirst`](crate::MatchKind::LeftmostFirst) because the pattern at index
+    /// `prefix`, a prefix of it, is registered earlier.
+    Unreachable {
+        /// Index of the prefix.
+        prefix: usize,
+    },
+
+    /// The pattern is longer than `max_len` bytes.
+    TooLong {
+        /// The maximum length (inclusive).
+        max_len: u32,
+    },
+
+    /// The number of patterns stored in the automaton exceeds `max_patterns` at this pattern.
+    TooMany {
+        /// The maximum number of patterns (inclusive).
+        max_patterns: u32,
+    },
+}
+
 /// A specialized Result type for Daachorse.
 pub type Result<T, E = DaachorseError> = result::Result<T, E>;
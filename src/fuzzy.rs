@@ -0,0 +1,154 @@
+//! Approximate matching with bounded edit distance.
+
+use alloc::vec::Vec;
+
+/// Approximate match result.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FuzzyMatch<V> {
+    start: usize,
+    end: usize,
+    distance: usize,
+    value: V,
+}
+
+impl<V> FuzzyMatch<V>
+where
+    V: Copy,
+{
+    pub(crate) const fn new(start: usize, end: usize, distance: usize, value: V) -> Self {
+        Self {
+            start,
+            end,
+            distance,
+            value,
+        }
+    }
+
+    /// Starting position of the matched substring.
+    #[inline(always)]
+    #[must_use]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Ending position of the matched substring.
+    #[inline(always)]
+    #[must_use]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Levenshtein distance between the substring and the pattern.
+    #[inline(always)]
+    #[must_use]
+    pub const fn distance(&self) -> usize {
+        self.distance
+    }
+
+    /// Value associated with the pattern.
+    #[inline(always)]
+    #[must_use]
+    pub const fn value(&self) -> V {
+        self.value
+    }
+}
+
+/// Computes the row of the edit distances after appending a pattern character with label
+/// `label` to the prefix of `row`, and returns the minimum distance in the new row.
+///
+/// `row[j]` is the edit distance between the pattern prefix and the first `j` characters of
+/// the haystack suffix `labels`. The new row is truncated to `max_len + 1` elements since a
+/// substring longer than the pattern by more than the bound cannot match.
+pub(crate) fn next_row<L>(
+    row: &[usize],
+    labels: &[L],
+    label: L,
+    max_len: usize,
+    new_row: &mut Vec<usize>,
+) -> usize
+where
+    L: Copy + PartialEq,
+{
+    new_row.clear();
+    new_row.push(row[0] + 1);
+    let mut min = row[0] + 1;
+    for j in 1..=max_len.min(labels.len()) {
+        let mut d = row[j - 1] + usize::from(labels[j - 1] != label);
+        d = d.min(new_row[j - 1] + 1);
+        if let Some(&up) = row.get(j) {
+            d = d.min(up + 1);
+        }
+        new_row.push(d);
+        min = min.min(d);
+    }
+    min
+}
+
+/// Returns the longest length of a non-empty substring with the minimum distance in `row`
+/// and the distance if it is within `max_distance`.
+pub(crate) fn best_end(row: &[usize], max_distance: usize) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize)> = None;
+    for (j, &d) in row.iter().enumerate().skip(1) {
+        if d <= max_distance && best.map_or(true, |(_, best_d)| d <= best_d) {
+            best = Some((j, d));
+        }
+    }
+    best
+}
+
+/// Returns the labels that keep the distance within `max_distance` when every element of
+/// `row` is at least `max_distance`, i.e., only exact matches are allowed.
+pub(crate) fn exact_labels<L>(row: &[usize], labels: &[L], max_distance: usize) -> Vec<L>
+where
+    L: Copy + Ord,
+{
+    let mut exact: Vec<L> = row
+        .iter()
+        .zip(labels)
+        .filter(|(&d, _)| d == max_distance)
+        .map(|(_, &label)| label)
+        .collect();
+    exact.sort_unstable();
+    exact.dedup();
+    exact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    fn distances(pattern: &str, text: &str, max_distance: usize) -> Vec<usize> {
+        let labels: Vec<char> = text.chars().collect();
+        let mut row: Vec<usize> = (0..=max_distance.min(labels.len())).collect();
+        let mut new_row = vec![];
+        for (depth, c) in pattern.chars().enumerate() {
+            next_row(&row, &labels, c, depth + 1 + max_distance, &mut new_row);
+            core::mem::swap(&mut row, &mut new_row);
+        }
+        row
+    }
+
+    #[test]
+    fn test_next_row() {
+        assert_eq!(vec![3, 2, 1, 1, 1], distances("abc", "abdc", 1));
+        assert_eq!(vec![3, 2, 1, 0], distances("abc", "abc", 0));
+        assert_eq!(vec![2, 1], distances("ab", "b", 2));
+    }
+
+    #[test]
+    fn test_best_end() {
+        assert_eq!(Some((3, 1)), best_end(&[3, 2, 1, 1, 2], 1));
+        assert_eq!(Some((3, 0)), best_end(&[3, 2, 1, 0], 0));
+        assert_eq!(None, best_end(&[3, 2, 2], 1));
+    }
+
+    #[test]
+    fn test_exact_labels() {
+        assert_eq!(
+            vec!['a', 'c'],
+            exact_labels(&[1, 2, 1, 1], &['c', 'b', 'a'], 1)
+        );
+    }
+}
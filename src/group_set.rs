@@ -0,0 +1,148 @@
+//! A set of group IDs returned by `matched_groups()`.
+
+use alloc::vec::Vec;
+
+const WORD_BITS: usize = 64;
+
+/// A fixed-size set of group IDs, which are integers smaller than the number of groups.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::GroupSet;
+///
+/// let mut groups = GroupSet::new(3);
+/// assert!(groups.insert(2));
+/// assert!(!groups.insert(2));
+/// assert!(groups.insert(0));
+///
+/// assert_eq!(2, groups.len());
+/// assert!(groups.contains(0));
+/// assert!(!groups.contains(1));
+/// assert_eq!(vec![0, 2], groups.iter().collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GroupSet {
+    words: Vec<u64>,
+    num_groups: usize,
+    len: usize,
+}
+
+impl GroupSet {
+    /// Creates an empty set of groups `0..num_groups`.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_groups` - The number of groups.
+    #[must_use]
+    pub fn new(num_groups: usize) -> Self {
+        Self {
+            words: vec![0; (num_groups + WORD_BITS - 1) / WORD_BITS],
+            num_groups,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of groups that can be stored.
+    #[inline(always)]
+    #[must_use]
+    pub const fn num_groups(&self) -> usize {
+        self.num_groups
+    }
+
+    /// Returns the number of groups in the set.
+    #[inline(always)]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no groups.
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the set contains all the groups.
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.len == self.num_groups
+    }
+
+    /// Returns `true` if the set contains the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Group ID.
+    #[inline(always)]
+    #[must_use]
+    pub fn contains(&self, group: usize) -> bool {
+        group < self.num_groups && self.words[group / WORD_BITS] >> (group % WORD_BITS) & 1 != 0
+    }
+
+    /// Adds the group to the set, and returns `true` if it was not contained.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Group ID.
+    ///
+    /// # Panics
+    ///
+    /// `group` must be smaller than [`GroupSet::num_groups()`].
+    #[inline(always)]
+    pub fn insert(&mut self, group: usize) -> bool {
+        assert!(group < self.num_groups);
+        let word = &mut self.words[group / WORD_BITS];
+        let mask = 1 << (group % WORD_BITS);
+        if *word & mask != 0 {
+            return false;
+        }
+        *word |= mask;
+        self.len += 1;
+        true
+    }
+
+    /// Returns an iterator of the groups in the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            (0..WORD_BITS)
+                .filter(move |&j| word >> j & 1 != 0)
+                .map(move |j| i * WORD_BITS + j)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_set() {
+        let mut groups = GroupSet::new(130);
+        assert!(groups.is_empty());
+        for group in [129, 0, 64, 63, 64] {
+            groups.insert(group);
+        }
+        assert_eq!(4, groups.len());
+        assert_eq!(vec![0, 63, 64, 129], groups.iter().collect::<Vec<_>>());
+        assert!(!groups.contains(1));
+        assert!(!groups.contains(130));
+        assert!(!groups.is_full());
+    }
+
+    #[test]
+    fn test_empty_group_set() {
+        let groups = GroupSet::new(0);
+        assert!(groups.is_empty());
+        assert!(groups.is_full());
+        assert_eq!(0, groups.iter().count());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_range() {
+        GroupSet::new(3).insert(3);
+    }
+}
@@ -0,0 +1,232 @@
+//! Haystacks with search options.
+
+use core::ops::Range;
+
+/// A haystack with a search span and anchoring, which can be passed to `find_iter()`,
+/// `find_overlapping_iter()` and `leftmost_find_iter()` instead of a haystack.
+///
+/// Only matches lying within the span are reported, and their positions are offsets in the
+/// whole haystack, not in the span.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{DoubleArrayAhoCorasick, Input};
+///
+/// let patterns = vec!["bcd", "ab", "a"];
+/// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+///
+/// let mut it = pma.find_overlapping_iter(Input::new("abcdab").span(1..6));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((1, 4, 0), (m.start(), m.end(), m.value()));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((4, 5, 2), (m.start(), m.end(), m.value()));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((4, 6, 1), (m.start(), m.end(), m.value()));
+///
+/// assert_eq!(None, it.next());
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Input<P> {
+    haystack: P,
+    span: Option<Range<usize>>,
+    anchored: bool,
+}
+
+impl<P> Input<P> {
+    /// Creates an input searching the whole haystack without anchoring.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    #[must_use]
+    pub const fn new(haystack: P) -> Self {
+        Self {
+            haystack,
+            span: None,
+            anchored: false,
+        }
+    }
+
+    /// Restricts the search to the byte range `span` of the haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `span` - Range of byte offsets to search. Matches must start at or after `span.start`
+    ///   and end at or before `span.end`.
+    ///
+    /// # Panics
+    ///
+    /// The search will panic if `span` is out of the haystack or, in the charwise automaton,
+    /// either end is not on a UTF-8 character boundary.
+    #[must_use]
+    pub fn span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Specifies whether matches must start at the beginning of the span.
+    ///
+    /// In non-overlapping searches, each subsequent match must start where the previous one
+    /// ends, and the search stops at the first position where no match starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `yes` - `true` if matches must start at the beginning of the span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasick, Input};
+    ///
+    /// let patterns = vec!["bcd", "ab", "a"];
+    /// let pma = DoubleArrayAhoCorasick::new(patterns).unwrap();
+    ///
+    /// let mut it = pma.find_overlapping_iter(Input::new("xabcd").span(1..5).anchored(true));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 2, 2), (m.start(), m.end(), m.value()));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((1, 3, 1), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    #[must_use]
+    pub const fn anchored(mut self, yes: bool) -> Self {
+        self.anchored = yes;
+        self
+    }
+
+    /// Returns the haystack.
+    #[inline(always)]
+    #[must_use]
+    pub const fn haystack(&self) -> &P {
+        &self.haystack
+    }
+
+    /// Returns `true` if matches must start at the beginning of the span.
+    #[inline(always)]
+    #[must_use]
+    pub const fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+
+    /// Returns the haystack, the validated span, and the anchoring.
+    fn resolve(self, len: usize) -> (P, Range<usize>, bool) {
+        let span = self.span.unwrap_or(0..len);
+        assert!(
+            span.start <= span.end && span.end <= len,
+            "Error: span must be within the haystack."
+        );
+        (self.haystack, span, self.anchored)
+    }
+}
+
+/// Conversion into a haystack with search options, which is implemented for haystacks
+/// and [`Input`].
+///
+/// `T` is the type of the haystack seen by the automaton, that is, [`[u8]`](slice) for
+/// [`DoubleArrayAhoCorasick`](crate::DoubleArrayAhoCorasick) and [`str`] for
+/// [`CharwiseDoubleArrayAhoCorasick`](crate::CharwiseDoubleArrayAhoCorasick).
+pub trait IntoInput<T: ?Sized> {
+    /// Type of the haystack.
+    type Haystack: AsRef<T>;
+
+    /// Returns the haystack, the span to search, and whether matches must start at the
+    /// beginning of the span.
+    #[doc(hidden)]
+    fn into_parts(self) -> (Self::Haystack, Range<usize>, bool);
+}
+
+impl<P> IntoInput<[u8]> for P
+where
+    P: AsRef<[u8]>,
+{
+    type Haystack = P;
+
+    #[inline(always)]
+    fn into_parts(self) -> (P, Range<usize>, bool) {
+        let len = self.as_ref().len();
+        (self, 0..len, false)
+    }
+}
+
+impl<P> IntoInput<[u8]> for Input<P>
+where
+    P: AsRef<[u8]>,
+{
+    type Haystack = P;
+
+    #[inline(always)]
+    fn into_parts(self) -> (P, Range<usize>, bool) {
+        let len = self.haystack.as_ref().len();
+        self.resolve(len)
+    }
+}
+
+impl<P> IntoInput<str> for P
+where
+    P: AsRef<str>,
+{
+    type Haystack = P;
+
+    #[inline(always)]
+    fn into_parts(self) -> (P, Range<usize>, bool) {
+        let len = self.as_ref().len();
+        (self, 0..len, false)
+    }
+}
+
+impl<P> IntoInput<str> for Input<P>
+where
+    P: AsRef<str>,
+{
+    type Haystack = P;
+
+    #[inline(always)]
+    fn into_parts(self) -> (P, Range<usize>, bool) {
+        let haystack = self.haystack.as_ref();
+        let len = haystack.len();
+        if let Some(span) = &self.span {
+            assert!(
+                haystack.is_char_boundary(span.start) && haystack.is_char_boundary(span.end),
+                "Error: span must be on UTF-8 character boundaries."
+            );
+        }
+        self.resolve(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_parts() {
+        assert_eq!(("abc", 0..3, false), IntoInput::<[u8]>::into_parts("abc"));
+        assert_eq!(
+            ("abc", 1..2, true),
+            IntoInput::<[u8]>::into_parts(Input::new("abc").span(1..2).anchored(true))
+        );
+        assert_eq!(
+            ("あい", 3..6, false),
+            IntoInput::<str>::into_parts(Input::new("あい").span(3..6))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_span_out_of_range() {
+        IntoInput::<[u8]>::into_parts(Input::new("abc").span(2..4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_span_not_on_char_boundary() {
+        IntoInput::<str>::into_parts(Input::new("あい").span(1..6));
+    }
+}
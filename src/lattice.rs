@@ -0,0 +1,272 @@
+//! Lattices of matches indexed by their starting positions.
+
+use alloc::vec::Vec;
+
+use crate::Match;
+
+/// Lattice of matches indexed by their starting positions, created by `build_lattice()`.
+///
+/// The matches are stored in the compressed sparse row (CSR) form, so the edges outgoing from
+/// each position are obtained as a slice without sorting. This is useful for dictionary-based
+/// tokenizers that search for the best path with the Viterbi algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{DoubleArrayAhoCorasick, Lattice};
+///
+/// let patterns = vec!["bcd", "ab", "a"];
+/// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+///
+/// // The same as pma.build_lattice("abcd").
+/// let lattice = Lattice::new(4, pma.find_overlapping_iter("abcd"));
+///
+/// let edges: Vec<_> = lattice.edges(0).iter().map(|m| (m.end(), m.value())).collect();
+/// assert_eq!(vec![(1, 2), (2, 1)], edges);
+///
+/// let edges: Vec<_> = lattice.edges(1).iter().map(|m| (m.end(), m.value())).collect();
+/// assert_eq!(vec![(4, 0)], edges);
+///
+/// assert!(lattice.edges(2).is_empty());
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Lattice<V> {
+    offsets: Vec<usize>,
+    edges: Vec<Match<V>>,
+}
+
+impl<V> Lattice<V>
+where
+    V: Copy,
+{
+    /// Creates a lattice of the given matches.
+    ///
+    /// The edges outgoing from each position keep the order of `matches`. For the matches
+    /// returned by `find_overlapping_iter()`, they are in ascending order of their ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Length of the haystack in bytes.
+    /// * `matches` - Matches in the haystack.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if a match ends after `len`.
+    #[must_use]
+    pub fn new<I>(len: usize, matches: I) -> Self
+    where
+        I: IntoIterator<Item = Match<V>>,
+    {
+        Self::build(len, matches.into_iter().collect())
+    }
+
+    /// Creates a lattice of the given matches, filling each maximal span covered by no match
+    /// with an unknown-word edge.
+    ///
+    /// The unknown-word edges do not guarantee that a path exists from the beginning to the end
+    /// of the haystack, since a position covered by a match can still have no outgoing edges.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Length of the haystack in bytes.
+    /// * `matches` - Matches in the haystack.
+    /// * `unknown` - Value associated with the unknown-word edges.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if a match ends after `len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::{DoubleArrayAhoCorasick, Lattice};
+    ///
+    /// let patterns = vec!["bc", "b"];
+    /// let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+    ///
+    /// // The same as pma.build_lattice_with_unknown("aabcdd", u32::MAX).
+    /// let lattice = Lattice::with_unknown(6, pma.find_overlapping_iter("aabcdd"), u32::MAX);
+    ///
+    /// let edges: Vec<_> = (0..=6)
+    ///     .flat_map(|pos| lattice.edges(pos))
+    ///     .map(|m| (m.start(), m.end(), m.value()))
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec![(0, 2, u32::MAX), (2, 3, 1), (2, 4, 0), (4, 6, u32::MAX)],
+    ///     edges,
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_unknown<I>(len: usize, matches: I, unknown: V) -> Self
+    where
+        I: IntoIterator<Item = Match<V>>,
+    {
+        let mut edges: Vec<Match<V>> = matches.into_iter().collect();
+        let mut max_ends = vec![0; len];
+        for m in &edges {
+            assert!(m.end() <= len, "Error: match must be within the haystack.");
+            let max_end = &mut max_ends[m.start()];
+            *max_end = m.end().max(*max_end);
+        }
+        let mut covered_end = 0;
+        let mut unknown_start = None;
+        for (pos, max_end) in max_ends.into_iter().enumerate() {
+            covered_end = covered_end.max(max_end);
+            if pos < covered_end {
+                if let Some(start) = unknown_start.take() {
+                    edges.push(Match {
+                        length: pos - start,
+                        end: pos,
+                        value: unknown,
+                    });
+                }
+            } else if unknown_start.is_none() {
+                unknown_start = Some(pos);
+            }
+        }
+        if let Some(start) = unknown_start {
+            edges.push(Match {
+                length: len - start,
+                end: len,
+                value: unknown,
+            });
+        }
+        Self::build(len, edges)
+    }
+
+    /// Sorts the edges by their starting positions with the counting sort, keeping the order
+    /// of the edges starting at the same position.
+    fn build(len: usize, edges: Vec<Match<V>>) -> Self {
+        let mut offsets = vec![0; len + 3];
+        for m in &edges {
+            assert!(m.end() <= len, "Error: match must be within the haystack.");
+            offsets[m.start() + 2] += 1;
+        }
+        for i in 2..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        // offsets[pos + 1] now indicates the position to put the next edge starting at pos.
+        let mut order = vec![0; edges.len()];
+        for (i, m) in edges.iter().enumerate() {
+            let offset = &mut offsets[m.start() + 1];
+            order[*offset] = i;
+            *offset += 1;
+        }
+        offsets.pop();
+        Self {
+            offsets,
+            edges: order.into_iter().map(|i| edges[i]).collect(),
+        }
+    }
+
+    /// Returns the length of the haystack in bytes.
+    #[inline(always)]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 2
+    }
+
+    /// Returns `true` if the haystack is empty.
+    #[inline(always)]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of edges.
+    #[inline(always)]
+    #[must_use]
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Returns the edges outgoing from the given position, that is, the matches starting there.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - Position in the haystack in bytes.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `pos` is greater than [`Self::len()`].
+    #[inline(always)]
+    #[must_use]
+    pub fn edges(&self, pos: usize) -> &[Match<V>] {
+        &self.edges[self.offsets[pos]..self.offsets[pos + 1]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    fn matches(spans: &[(usize, usize, u32)]) -> Vec<Match<u32>> {
+        spans
+            .iter()
+            .map(|&(start, end, value)| Match {
+                length: end - start,
+                end,
+                value,
+            })
+            .collect()
+    }
+
+    fn spans(lattice: &Lattice<u32>) -> Vec<Vec<(usize, usize, u32)>> {
+        (0..=lattice.len())
+            .map(|pos| {
+                lattice
+                    .edges(pos)
+                    .iter()
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build() {
+        let lattice = Lattice::new(4, matches(&[(1, 2, 0), (0, 3, 1), (1, 4, 2), (1, 4, 3)]));
+        assert_eq!(4, lattice.num_edges());
+        assert_eq!(
+            vec![
+                vec![(0, 3, 1)],
+                vec![(1, 2, 0), (1, 4, 2), (1, 4, 3)],
+                vec![],
+                vec![],
+                vec![],
+            ],
+            spans(&lattice)
+        );
+    }
+
+    #[test]
+    fn test_with_unknown() {
+        let lattice = Lattice::with_unknown(7, matches(&[(2, 3, 0), (1, 4, 1)]), 9);
+        assert_eq!(
+            vec![
+                vec![(0, 1, 9)],
+                vec![(1, 4, 1)],
+                vec![(2, 3, 0)],
+                vec![],
+                vec![(4, 7, 9)],
+                vec![],
+                vec![],
+                vec![],
+            ],
+            spans(&lattice)
+        );
+        assert_eq!(
+            vec![vec![(0, 2, 9)], vec![], vec![]],
+            spans(&Lattice::with_unknown(2, vec![], 9))
+        );
+        assert!(Lattice::with_unknown(0, vec![], 9).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_range() {
+        let _ = Lattice::new(2, matches(&[(1, 3, 0)]));
+    }
+}
@@ -124,6 +124,32 @@
 //! assert_eq!(None, it.next());
 //! ```
 //!
+//! ## Example: Finding non-overlapped occurrences with shortest matching
+//!
+//! If you want to search for the shortest pattern among ones starting from the search position,
+//! use [`DoubleArrayAhoCorasick::leftmost_find_iter()`] with specifying
+//! [`MatchKind::LeftmostShortest`].
+//!
+//! ```
+//! use daachorse::{DoubleArrayAhoCorasickBuilder, MatchKind};
+//!
+//! let patterns = vec!["ab", "a", "abcd", "cd"];
+//! let pma = DoubleArrayAhoCorasickBuilder::new()
+//!     .match_kind(MatchKind::LeftmostShortest)
+//!     .build(&patterns)
+//!     .unwrap();
+//!
+//! let mut it = pma.leftmost_find_iter("abcd");
+//!
+//! let m = it.next().unwrap();
+//! assert_eq!((0, 1, 1), (m.start(), m.end(), m.value()));
+//!
+//! let m = it.next().unwrap();
+//! assert_eq!((2, 4, 3), (m.start(), m.end(), m.value()));
+//!
+//! assert_eq!(None, it.next());
+//! ```
+//!
 //! ## Example: Associating arbitrary values with patterns
 //!
 //! To build the automaton from pairs of a pattern and user-defined value, instead of assigning
@@ -188,14 +214,27 @@ compile_error!("`target_pointer_width` must be 32 or 64");
 #[macro_use]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
 mod build_helper;
 pub mod bytewise;
 pub mod charwise;
+mod coverage;
 pub mod errors;
+mod fuzzy;
+mod group_set;
+mod input;
 mod intpack;
+mod lattice;
+mod match_counts;
 mod nfa_builder;
+mod position;
+mod segment;
 mod serializer;
+pub mod store;
 mod utils;
+mod wildcard;
 
 use core::num::NonZeroU32;
 
@@ -204,7 +243,15 @@ use alloc::vec::Vec;
 use build_helper::BuildHelper;
 pub use bytewise::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder};
 pub use charwise::{CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder};
+pub use coverage::{CoveredSpanIterator, ValuedCoveredSpanIterator};
+pub use fuzzy::FuzzyMatch;
+pub use group_set::GroupSet;
+pub use input::{Input, IntoInput};
+pub use lattice::Lattice;
+pub use match_counts::MatchCounts;
+pub use position::{Position, PositionIterator, PositionedMatch};
 pub use serializer::Serializable;
+pub use wildcard::WildcardPattern;
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -368,6 +415,13 @@ pub enum MatchKind {
     /// earlier will be reported. For example, when matching patterns `ab|a|abcd` over `abcd`,
     /// `ab` will be reported.
     LeftmostFirst = 2,
+
+    /// The leftmost-shortest match semantics, which enables
+    /// [`leftmost_find_iter()`](DoubleArrayAhoCorasick::leftmost_find_iter()).
+    /// When multiple patterns are started from the same positions, the shortest pattern will be
+    /// reported. For example, when matching patterns `ab|a|abcd` over `abcd`, `a` will be
+    /// reported.
+    LeftmostShortest = 3,
 }
 
 impl MatchKind {
@@ -376,12 +430,24 @@ impl MatchKind {
     }
 
     fn is_leftmost(self) -> bool {
-        self == Self::LeftmostFirst || self == Self::LeftmostLongest
+        self == Self::LeftmostFirst
+            || self == Self::LeftmostLongest
+            || self == Self::LeftmostShortest
     }
 
     pub(crate) fn is_leftmost_first(self) -> bool {
         self == Self::LeftmostFirst
     }
+
+    pub(crate) fn is_leftmost_shortest(self) -> bool {
+        self == Self::LeftmostShortest
+    }
+
+    /// Whether patterns having a registered pattern as a prefix are never reported. Under
+    /// [`MatchKind::LeftmostShortest`], patterns are registered in ascending order of length.
+    pub(crate) fn shadows_extensions(self) -> bool {
+        self.is_leftmost_first() || self.is_leftmost_shortest()
+    }
 }
 
 impl From<u8> for MatchKind {
@@ -389,6 +455,7 @@ impl From<u8> for MatchKind {
         match src {
             1 => Self::LeftmostLongest,
             2 => Self::LeftmostFirst,
+            3 => Self::LeftmostShortest,
             _ => Self::Standard,
         }
     }
@@ -400,6 +467,7 @@ impl From<MatchKind> for u8 {
             MatchKind::Standard => 0,
             MatchKind::LeftmostLongest => 1,
             MatchKind::LeftmostFirst => 2,
+            MatchKind::LeftmostShortest => 3,
         }
     }
 }
@@ -421,6 +489,33 @@ impl Serializable for MatchKind {
     }
 }
 
+/// A policy for patterns registered more than once,
+/// specified in [`DoubleArrayAhoCorasickBuilder::duplicate_policy`].
+///
+/// To combine the values of a repeated pattern with an arbitrary function, use
+/// [`DoubleArrayAhoCorasickBuilder::build_with_values_merged`] instead.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Rejects a repeated pattern with [`DuplicatePatternError`](errors::DuplicatePatternError).
+    Error,
+
+    /// Keeps the value registered first and ignores the others.
+    KeepFirst,
+
+    /// Keeps the value registered last and ignores the others.
+    KeepLast,
+
+    /// Keeps all values attached to a repeated pattern. The values are stored contiguously in
+    /// registration order, and every iterator reports one match for each value in that order.
+    KeepAll,
+}
+
+impl Default for DuplicatePolicy {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
 /// Empty value type.
 ///
 /// This is a unit struct with no field. This type can be used to reduce memory consumption when
@@ -0,0 +1,68 @@
+//! Collections accumulating the numbers of matches.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// A collection accumulating the number of matches for each value, which is filled by
+/// `count_matches()` and `count_overlapping_matches()`.
+///
+/// This is implemented for
+///   - [`Vec<u64>`] indexed by values, such as pattern IDs, which must be allocated for all
+///     the values in advance since it is never extended. Values not smaller than its length are
+///     ignored, so a large value does not cause a huge allocation.
+///   - [`BTreeMap<V, u64>`] keyed by values, and
+///   - `HashMap<V, u64>` keyed by values, only with the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::MatchCounts;
+///
+/// let mut counts: Vec<u64> = vec![0; 3];
+/// counts.increment(2u32);
+/// counts.increment(2u32);
+/// counts.increment(u32::MAX); // ignored
+/// assert_eq!(vec![0, 0, 2], counts);
+/// ```
+pub trait MatchCounts<V> {
+    /// Increments the number of matches for the value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value of the matched pattern.
+    fn increment(&mut self, value: V);
+}
+
+impl<V> MatchCounts<V> for Vec<u64>
+where
+    V: TryInto<usize>,
+{
+    #[inline(always)]
+    fn increment(&mut self, value: V) {
+        if let Some(count) = value.try_into().ok().and_then(|i: usize| self.get_mut(i)) {
+            *count += 1;
+        }
+    }
+}
+
+impl<V> MatchCounts<V> for BTreeMap<V, u64>
+where
+    V: Ord,
+{
+    #[inline(always)]
+    fn increment(&mut self, value: V) {
+        *self.entry(value).or_insert(0) += 1;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V, S> MatchCounts<V> for std::collections::HashMap<V, u64, S>
+where
+    V: Eq + core::hash::Hash,
+    S: core::hash::BuildHasher,
+{
+    #[inline(always)]
+    fn increment(&mut self, value: V) {
+        *self.entry(value).or_insert(0) += 1;
+    }
+}
@@ -1,11 +1,12 @@
 use core::cell::RefCell;
 use core::num::NonZeroU32;
 
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
-use crate::errors::{DaachorseError, Result};
+use crate::errors::{DaachorseError, PatternProblem, PatternProblemKind, Result};
 use crate::utils::FromU32;
-use crate::{MatchKind, Output};
+use crate::{DuplicatePolicy, MatchKind, Output};
 
 // The root state id of SparseNFA.
 pub const ROOT_STATE_ID: u32 = 0;
@@ -14,22 +15,32 @@ pub const DEAD_STATE_ID: u32 = 1;
 
 pub trait EdgeLabel: Copy + Ord + core::fmt::Debug {
     fn num_bytes(&self) -> usize;
+
+    fn push_bytes(&self, dst: &mut Vec<u8>);
 }
 
 impl EdgeLabel for u8 {
     fn num_bytes(&self) -> usize {
         1
     }
+
+    fn push_bytes(&self, dst: &mut Vec<u8>) {
+        dst.push(*self);
+    }
 }
 
 impl EdgeLabel for char {
     fn num_bytes(&self) -> usize {
         self.len_utf8()
     }
+
+    fn push_bytes(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.encode_utf8(&mut [0; 4]).as_bytes());
+    }
 }
 
 /// Mapping edge lables to child ids using `BTreeMap`.
-type EdgeMap<L> = alloc::collections::BTreeMap<L, u32>;
+type EdgeMap<L> = BTreeMap<L, u32>;
 
 /// State of [`NfaBuilder`].
 #[derive(Clone)]
@@ -37,6 +48,9 @@ pub struct NfaBuilderState<L, V> {
     pub(crate) edges: EdgeMap<L>,
     pub(crate) fail: u32,
     pub(crate) output: Option<(V, NonZeroU32)>,
+    // The last one of the values added to `output` under `DuplicatePolicy::KeepAll`,
+    // represented as an 1-based position in `NfaBuilder::extra_values`.
+    pub(crate) extra_value: Option<NonZeroU32>,
     pub(crate) output_pos: Option<NonZeroU32>,
 }
 
@@ -46,6 +60,7 @@ impl<L, V> Default for NfaBuilderState<L, V> {
             edges: EdgeMap::<L>::default(),
             fail: ROOT_STATE_ID,
             output: None,
+            extra_value: None,
             output_pos: None,
         }
     }
@@ -55,8 +70,23 @@ impl<L, V> Default for NfaBuilderState<L, V> {
 pub struct NfaBuilder<L, V> {
     pub(crate) states: Vec<RefCell<NfaBuilderState<L, V>>>,
     pub(crate) outputs: Vec<Output<V>>, // in which common parts are merged.
+    // Values of repeated patterns, each of which has the position of the previous value.
+    pub(crate) extra_values: Vec<(V, Option<NonZeroU32>)>,
     pub(crate) len: usize,
     pub(crate) match_kind: MatchKind,
+    pub(crate) duplicate_policy: DuplicatePolicy,
+    // Whether to return an error for patterns ignored under MatchKind::LeftmostFirst or
+    // MatchKind::LeftmostShortest.
+    pub(crate) reject_unreachable: bool,
+    // Patterns ignored under MatchKind::LeftmostFirst or MatchKind::LeftmostShortest, which are
+    // collected only if it is Some. In validation, the other problems are also collected.
+    pub(crate) unreachable: Option<Vec<PatternProblem>>,
+    // Whether to collect all the problems of input patterns into `unreachable` instead of
+    // returning errors.
+    validating: bool,
+    // Mapping states to the indices of the input patterns, which is used only if
+    // `reject_unreachable` is true or `unreachable` is Some.
+    pattern_indices: BTreeMap<u32, usize>,
 }
 
 impl<L, V> NfaBuilder<L, V>
@@ -64,57 +94,330 @@ where
     L: EdgeLabel,
     V: Copy,
 {
-    pub(crate) fn new(match_kind: MatchKind) -> Self {
+    pub(crate) fn new(match_kind: MatchKind, duplicate_policy: DuplicatePolicy) -> Self {
         Self {
             states: vec![
                 RefCell::new(NfaBuilderState::<L, V>::default()), // root
                 RefCell::new(NfaBuilderState::<L, V>::default()), // dead
             ],
             outputs: vec![],
+            extra_values: vec![],
             len: 0,
             match_kind,
+            duplicate_policy,
+            reject_unreachable: false,
+            unreachable: None,
+            validating: false,
+            pattern_indices: BTreeMap::new(),
         }
     }
 
     #[inline(always)]
-    pub(crate) fn add(&mut self, pattern: &[L], value: V) -> Result<()> {
-        let pattern_len = pattern
-            .iter()
-            .fold(0, |acc, c| acc + c.num_bytes())
+    pub(crate) fn add<F>(
+        &mut self,
+        index: usize,
+        pattern: &[L],
+        value: V,
+        merge: Option<&mut F>,
+    ) -> Result<()>
+    where
+        F: FnMut(V, V) -> V,
+    {
+        let pattern_len = pattern.iter().fold(0, |acc, c| acc + c.num_bytes());
+        if self.validating && (pattern_len == 0 || u32::try_from(pattern_len).is_err()) {
+            let kind = if pattern_len == 0 {
+                PatternProblemKind::Empty
+            } else {
+                PatternProblemKind::TooLong { max_len: u32::MAX }
+            };
+            self.push_problem(index, pattern, kind);
+            return Ok(());
+        }
+        let pattern_len = pattern_len
             .try_into()
             .map_err(|_| DaachorseError::invalid_argument("pattern.len()", "<=", u32::MAX))?;
         let pattern_len = NonZeroU32::new(pattern_len)
             .ok_or_else(|| DaachorseError::invalid_argument("pattern.len()", ">=", 1))?;
 
+        if merge.is_some() || self.duplicate_policy != DuplicatePolicy::Error || self.validating {
+            // This is checked before the following loop so that duplicates are handled even if a
+            // prefix of the pattern is registered later under MatchKind::LeftmostFirst.
+            if let Some(state_id) = self.state_id(pattern) {
+                if self.add_duplicate(state_id, index, pattern, value, merge)? {
+                    return Ok(());
+                }
+            }
+        }
+
         let mut state_id = ROOT_STATE_ID;
         for &c in pattern {
-            if self.match_kind.is_leftmost_first() {
-                // If state_id has an output, the descendants will never searched.
-                let output = &self.states[usize::from_u32(state_id)].borrow().output;
-                if output.is_some() {
-                    return Ok(());
+            if self.shadows(state_id) {
+                return self.add_unreachable(index, pattern, state_id);
+            }
+
+            state_id = match self.child_id(state_id, c) {
+                Some(next_state_id) => next_state_id,
+                None => self.push_child(state_id, c)?,
+            };
+        }
+
+        self.set_output(state_id, index, pattern, value, pattern_len)
+    }
+
+    /// Adds the patterns formed by choosing one of the alternatives for each element, all of
+    /// which are associated with `value`.
+    ///
+    /// The states are created in depth-first order, so the concrete patterns are never
+    /// enumerated as a whole. If `num_labels` is Some, only the patterns of that number of
+    /// labels are added. An error is returned if the number of states exceeds `max_states`.
+    pub(crate) fn add_alternatives<A>(
+        &mut self,
+        index: usize,
+        elements: &[Vec<A>],
+        value: V,
+        num_labels: Option<usize>,
+        max_states: u32,
+    ) -> Result<()>
+    where
+        A: AsRef<[L]>,
+    {
+        if elements.is_empty() {
+            return Err(DaachorseError::invalid_argument("pattern.len()", ">=", 1));
+        }
+        // The minimum and maximum numbers of labels of the suffixes from each element.
+        let mut rest_lens = vec![(0, 0); elements.len() + 1];
+        for (i, alternatives) in elements.iter().enumerate().rev() {
+            let lens = alternatives
+                .iter()
+                .map(|alternative| alternative.as_ref().len());
+            let (min, max) = (lens.clone().min().unwrap_or(0), lens.max().unwrap_or(0));
+            rest_lens[i] = (rest_lens[i + 1].0 + min, rest_lens[i + 1].1 + max);
+        }
+
+        let mut pattern = vec![];
+        // Each element has a state, the position of its next alternative, the length of
+        // `pattern` at the state, and the state shadowing it if any.
+        let mut stack = vec![(ROOT_STATE_ID, 0, 0, None)];
+        while let Some(&(mut state_id, next, len, mut shadow)) = stack.last() {
+            let depth = stack.len() - 1;
+            let alternative = match elements[depth].get(next) {
+                Some(alternative) => alternative.as_ref(),
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            stack[depth].1 += 1;
+            let new_len = len + alternative.len();
+            if let Some(num_labels) = num_labels {
+                let (min, max) = rest_lens[depth + 1];
+                if num_labels < new_len + min || new_len + max < num_labels {
+                    continue;
                 }
             }
+            pattern.truncate(len);
+            pattern.extend_from_slice(alternative);
 
-            if let Some(next_state_id) = self.child_id(state_id, c) {
-                state_id = next_state_id;
-            } else if let Ok(next_state_id) = u32::try_from(self.states.len()) {
-                self.states[usize::from_u32(state_id)]
-                    .borrow_mut()
-                    .edges
-                    .insert(c, next_state_id);
-                self.states
-                    .push(RefCell::new(NfaBuilderState::<L, V>::default()));
-                state_id = next_state_id;
-            } else {
-                return Err(DaachorseError::automaton_scale("state_id", u32::MAX));
+            let mut reachable = true;
+            for &c in alternative {
+                if shadow.is_none() && self.shadows(state_id) {
+                    shadow = Some(state_id);
+                    // Without duplicates, all the patterns from here are unreachable.
+                    if self.duplicate_policy == DuplicatePolicy::Error {
+                        reachable = false;
+                        break;
+                    }
+                }
+                state_id = match self.child_id(state_id, c) {
+                    Some(next_state_id) => next_state_id,
+                    None if shadow.is_some() => {
+                        reachable = false;
+                        break;
+                    }
+                    None => {
+                        if self.states.len() - 2 >= usize::from_u32(max_states) {
+                            return Err(DaachorseError::automaton_scale("num_states", max_states));
+                        }
+                        self.push_child(state_id, c)?
+                    }
+                };
+            }
+            if !reachable {
+                for alternatives in &elements[depth + 1..] {
+                    pattern.extend_from_slice(alternatives[0].as_ref());
+                }
+                // shadow is always Some if the state is unreachable.
+                self.add_unreachable(index, &pattern, shadow.unwrap())?;
+                continue;
+            }
+            if depth + 1 < elements.len() {
+                stack.push((state_id, 0, pattern.len(), shadow));
+                continue;
+            }
+
+            let pattern_len = pattern.iter().fold(0, |acc, c| acc + c.num_bytes());
+            let pattern_len = pattern_len
+                .try_into()
+                .ok()
+                .and_then(NonZeroU32::new)
+                .ok_or_else(|| DaachorseError::invalid_argument("pattern.len()", "<=", u32::MAX))?;
+            if self.duplicate_policy != DuplicatePolicy::Error
+                && self.add_duplicate(
+                    state_id,
+                    index,
+                    &pattern,
+                    value,
+                    None::<&mut fn(V, V) -> V>,
+                )?
+            {
+                continue;
+            }
+            if let Some(shadow) = shadow {
+                self.add_unreachable(index, &pattern, shadow)?;
+                continue;
+            }
+            self.set_output(state_id, index, &pattern, value, pattern_len)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the patterns of alternatives with [`Self::add_alternatives()`], where the index of
+    /// each pattern is its position in `patvals`.
+    pub(crate) fn add_all_alternatives<A>(
+        &mut self,
+        patvals: &[(Vec<Vec<A>>, V)],
+        max_states: u32,
+    ) -> Result<()>
+    where
+        A: AsRef<[L]>,
+    {
+        if !self.match_kind.is_leftmost_shortest() {
+            for (index, (elements, value)) in patvals.iter().enumerate() {
+                self.add_alternatives(index, elements, *value, None, max_states)?;
+            }
+            return Ok(());
+        }
+        // Registers shorter patterns earlier so that they shadow the longer ones.
+        let ranges: Vec<(usize, usize)> = patvals
+            .iter()
+            .map(|(elements, _)| {
+                elements.iter().fold((0, 0), |(min, max), alternatives| {
+                    let lens = alternatives
+                        .iter()
+                        .map(|alternative| alternative.as_ref().len());
+                    (
+                        min + lens.clone().min().unwrap_or(0),
+                        max + lens.max().unwrap_or(0),
+                    )
+                })
+            })
+            .collect();
+        let mut lens: Vec<usize> = ranges.iter().flat_map(|&(min, max)| min..=max).collect();
+        lens.sort_unstable();
+        lens.dedup();
+        for len in lens {
+            for (index, ((elements, value), &(min, max))) in patvals.iter().zip(&ranges).enumerate()
+            {
+                if min <= len && len <= max {
+                    self.add_alternatives(index, elements, *value, Some(len), max_states)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles the pattern reaching `state_id` if it is a duplicate, and returns whether it is.
+    fn add_duplicate<F>(
+        &mut self,
+        state_id: u32,
+        index: usize,
+        pattern: &[L],
+        value: V,
+        merge: Option<&mut F>,
+    ) -> Result<bool>
+    where
+        F: FnMut(V, V) -> V,
+    {
+        let mut state = self.states[usize::from_u32(state_id)].borrow_mut();
+        let s = &mut *state;
+        let output = match s.output.as_mut() {
+            Some(output) => output,
+            None => return Ok(false),
+        };
+        if self.validating && self.duplicate_policy == DuplicatePolicy::Error {
+            drop(state);
+            let first = self.pattern_indices[&state_id];
+            self.push_problem(index, pattern, PatternProblemKind::Duplicate { first });
+        } else if let Some(merge) = merge {
+            output.0 = merge(output.0, value);
+        } else if self.duplicate_policy == DuplicatePolicy::KeepLast {
+            output.0 = value;
+        } else if self.duplicate_policy == DuplicatePolicy::KeepAll {
+            self.extra_values.push((value, s.extra_value));
+            s.extra_value =
+                NonZeroU32::new(u32::try_from(self.extra_values.len()).map_err(|_| {
+                    DaachorseError::automaton_scale("extra_values.len()", u32::MAX)
+                })?);
+            self.len += 1;
+        }
+        Ok(true)
+    }
+
+    /// Returns whether the descendants of the state are never searched.
+    #[inline(always)]
+    fn shadows(&self, state_id: u32) -> bool {
+        self.match_kind.shadows_extensions()
+            && self.states[usize::from_u32(state_id)]
+                .borrow()
+                .output
+                .is_some()
+    }
+
+    /// Handles the pattern shadowed by the pattern of the state `shadow`.
+    fn add_unreachable(&mut self, index: usize, pattern: &[L], shadow: u32) -> Result<()> {
+        if self.tracks_unreachable() {
+            let prefix = self.pattern_indices[&shadow];
+            if self.reject_unreachable {
+                return Err(DaachorseError::unreachable_pattern(
+                    index,
+                    pattern_bytes(pattern),
+                    prefix,
+                ));
             }
+            self.push_problem(index, pattern, PatternProblemKind::Unreachable { prefix });
         }
+        Ok(())
+    }
 
+    /// Creates a child of the state with the label and returns its id.
+    fn push_child(&mut self, state_id: u32, c: L) -> Result<u32> {
+        let child_id = u32::try_from(self.states.len())
+            .map_err(|_| DaachorseError::automaton_scale("state_id", u32::MAX))?;
+        self.states[usize::from_u32(state_id)]
+            .borrow_mut()
+            .edges
+            .insert(c, child_id);
+        self.states
+            .push(RefCell::new(NfaBuilderState::<L, V>::default()));
+        Ok(child_id)
+    }
+
+    /// Sets the output of the pattern to the state.
+    fn set_output(
+        &mut self,
+        state_id: u32,
+        index: usize,
+        pattern: &[L],
+        value: V,
+        pattern_len: NonZeroU32,
+    ) -> Result<()> {
         let output = &mut self.states[usize::from_u32(state_id)].borrow_mut().output;
         if output.replace((value, pattern_len)).is_some() {
             return Err(DaachorseError::duplicate_pattern(format!("{pattern:?}")));
         }
+        if self.tracks_unreachable() {
+            self.pattern_indices.insert(state_id, index);
+        }
 
         self.len += 1;
         Ok(())
@@ -212,19 +515,56 @@ where
         // But, there is no problem since Daachorse does not allow an empty pattern.
         debug_assert_ne!(q[0], ROOT_STATE_ID);
 
+        let mut values = vec![];
         for &state_id in q {
             let s = &mut self.states[usize::from_u32(state_id)].borrow_mut();
             if let Some(output) = s.output {
                 s.output_pos = NonZeroU32::new(u32::try_from(self.outputs.len() + 1).unwrap());
                 let parent = self.states[usize::from_u32(s.fail)].borrow().output_pos;
+
+                // Values of a repeated pattern are stored contiguously in registration order,
+                // each of which has the next one as the parent.
+                values.clear();
+                let mut extra_value = s.extra_value;
+                while let Some(pos) = extra_value {
+                    let (value, prev) = self.extra_values[usize::from_u32(pos.get() - 1)];
+                    values.push(value);
+                    extra_value = prev;
+                }
+                let mut value = output.0;
+                for &next_value in values.iter().rev() {
+                    let next_pos = NonZeroU32::new(u32::try_from(self.outputs.len() + 2).unwrap());
+                    self.outputs
+                        .push(Output::new(value, output.1.get(), next_pos));
+                    value = next_value;
+                }
                 self.outputs
-                    .push(Output::new(output.0, output.1.get(), parent));
+                    .push(Output::new(value, output.1.get(), parent));
             } else {
                 s.output_pos = self.states[usize::from_u32(s.fail)].borrow().output_pos;
             }
         }
     }
 
+    #[inline(always)]
+    fn tracks_unreachable(&self) -> bool {
+        self.reject_unreachable || self.unreachable.is_some()
+    }
+
+    fn push_problem(&mut self, index: usize, pattern: &[L], kind: PatternProblemKind) {
+        if let Some(problems) = self.unreachable.as_mut() {
+            problems.push(PatternProblem::new(index, &pattern_bytes(pattern), kind));
+        }
+    }
+
+    fn state_id(&self, pattern: &[L]) -> Option<u32> {
+        let mut state_id = ROOT_STATE_ID;
+        for &c in pattern {
+            state_id = self.child_id(state_id, c)?;
+        }
+        Some(state_id)
+    }
+
     #[inline(always)]
     fn child_id(&self, state_id: u32, c: L) -> Option<u32> {
         self.states[usize::from_u32(state_id)]
@@ -234,3 +574,93 @@ where
             .copied()
     }
 }
+
+impl<L> NfaBuilder<L, ()>
+where
+    L: EdgeLabel,
+{
+    /// Creates a builder collecting the problems of input patterns instead of returning errors.
+    pub(crate) fn new_validator(match_kind: MatchKind, duplicate_policy: DuplicatePolicy) -> Self {
+        let mut nfa = Self::new(match_kind, duplicate_policy);
+        nfa.unreachable = Some(vec![]);
+        nfa.validating = true;
+        nfa
+    }
+
+    /// Collects the problems of the next pattern, where at most `max_patterns` patterns can be
+    /// stored in the automaton.
+    pub(crate) fn validate(&mut self, index: usize, pattern: &[L], max_patterns: u32) {
+        let len = self.len;
+        // An error is returned only if the scale of the automaton is exceeded, which is not
+        // checked in validation.
+        let _ = self.add(index, pattern, (), None::<&mut fn((), ())>);
+        if self.len > len && len == usize::from_u32(max_patterns) {
+            self.push_problem(index, pattern, PatternProblemKind::TooMany { max_patterns });
+        }
+    }
+
+    /// Returns the problems in the order of the input patterns.
+    pub(crate) fn finish_validation(self) -> Vec<PatternProblem> {
+        let mut problems = self.unreachable.unwrap_or_default();
+        // Patterns are added in ascending order of length under MatchKind::LeftmostShortest.
+        problems.sort_by_key(PatternProblem::index);
+        problems
+    }
+}
+
+fn pattern_bytes<L>(pattern: &[L]) -> Vec<u8>
+where
+    L: EdgeLabel,
+{
+    let mut bytes = vec![];
+    pattern.iter().for_each(|c| c.push_bytes(&mut bytes));
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(
+        match_kind: MatchKind,
+        duplicate_policy: DuplicatePolicy,
+        max_patterns: u32,
+        patterns: &[&[u8]],
+    ) -> Vec<PatternProblem> {
+        let mut nfa = NfaBuilder::<u8, ()>::new_validator(match_kind, duplicate_policy);
+        for (i, pattern) in patterns.iter().enumerate() {
+            nfa.validate(i, pattern, max_patterns);
+        }
+        nfa.finish_validation()
+    }
+
+    #[test]
+    fn test_validate_too_many() {
+        let patterns = [&b"a"[..], b"b", b"a", b"c", b"d"];
+        assert_eq!(
+            vec![PatternProblem::new(
+                2,
+                b"a",
+                PatternProblemKind::TooMany { max_patterns: 2 }
+            )],
+            validate(MatchKind::Standard, DuplicatePolicy::KeepAll, 2, &patterns)
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicate_of_unreachable() {
+        let patterns = [&b"a"[..], b"ab", b"ab"];
+        assert_eq!(
+            vec![
+                PatternProblem::new(1, b"ab", PatternProblemKind::Unreachable { prefix: 0 }),
+                PatternProblem::new(2, b"ab", PatternProblemKind::Unreachable { prefix: 0 }),
+            ],
+            validate(
+                MatchKind::LeftmostFirst,
+                DuplicatePolicy::Error,
+                10,
+                &patterns
+            )
+        );
+    }
+}
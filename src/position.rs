@@ -0,0 +1,255 @@
+//! Positions of matches in bytes, characters, and lines and columns.
+
+use crate::Match;
+
+/// A position in a string.
+///
+/// Lines and columns are counted from zero. A line is terminated by `'\n'`, and columns are
+/// counted in characters.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub struct Position {
+    byte: usize,
+    char: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    /// Returns the offset in bytes.
+    #[inline(always)]
+    #[must_use]
+    pub const fn byte(&self) -> usize {
+        self.byte
+    }
+
+    /// Returns the offset in characters.
+    #[inline(always)]
+    #[must_use]
+    pub const fn char(&self) -> usize {
+        self.char
+    }
+
+    /// Returns the line number.
+    #[inline(always)]
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column number in characters.
+    #[inline(always)]
+    #[must_use]
+    pub const fn column(&self) -> usize {
+        self.column
+    }
+}
+
+/// Match result with its positions in bytes, characters, and lines and columns.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PositionedMatch<V> {
+    start: Position,
+    end: Position,
+    value: V,
+}
+
+impl<V> PositionedMatch<V>
+where
+    V: Copy,
+{
+    /// Returns the starting position of the match.
+    #[inline(always)]
+    #[must_use]
+    pub const fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Returns the ending position of the match.
+    #[inline(always)]
+    #[must_use]
+    pub const fn end(&self) -> Position {
+        self.end
+    }
+
+    /// Returns the value associated with the pattern.
+    #[inline(always)]
+    #[must_use]
+    pub const fn value(&self) -> V {
+        self.value
+    }
+}
+
+/// Iterator attaching positions to matches in a string.
+///
+/// The positions are counted in a second pass over the haystack, which is interleaved with the
+/// search: two cursors, one for the match ends and one for the match starts, only move forward
+/// through the haystack. Hence, if the starts of the matches are also in non-decreasing order,
+/// as with non-overlapping matches, the whole iteration takes time linear in the haystack
+/// length. A match starting before the start of a previous match is counted back from its end
+/// instead, and only if it also starts on an earlier line than the previous match, the column
+/// of its start is counted from the beginning of its line.
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{DoubleArrayAhoCorasick, PositionIterator};
+///
+/// let haystack = "ab\ncd\nab";
+/// let pma = DoubleArrayAhoCorasick::new(["ab", "b\nc"]).unwrap();
+///
+/// let mut it = PositionIterator::new(haystack, pma.find_overlapping_iter(haystack));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((0, 0, 0), (m.start().line(), m.start().column(), m.value()));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((0, 1, 1), (m.start().line(), m.start().column(), m.value()));
+/// assert_eq!((1, 1), (m.end().line(), m.end().column()));
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((2, 0, 0), (m.start().line(), m.start().column(), m.value()));
+///
+/// assert_eq!(None, it.next());
+/// ```
+pub struct PositionIterator<'h, I> {
+    haystack: &'h str,
+    matches: I,
+    start: Position,
+    end: Position,
+}
+
+impl<'h, I> PositionIterator<'h, I> {
+    /// Creates an iterator attaching positions to the matches in the haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String searched for.
+    /// * `matches` - Iterator of matches in `haystack`.
+    ///
+    /// # Panics
+    ///
+    /// The iterator will panic if the matches are not in non-decreasing order of their ends,
+    /// or they are not on character boundaries of `haystack`. The iterators returned by
+    /// `find_iter()`, `find_overlapping_iter()` and `leftmost_find_iter()` for `haystack`
+    /// satisfy these conditions.
+    #[must_use]
+    pub const fn new(haystack: &'h str, matches: I) -> Self {
+        let origin = Position {
+            byte: 0,
+            char: 0,
+            line: 0,
+            column: 0,
+        };
+        Self {
+            haystack,
+            matches,
+            start: origin,
+            end: origin,
+        }
+    }
+
+    /// Moves the cursor forward to the given byte offset.
+    #[inline(always)]
+    fn advance(haystack: &str, cursor: &mut Position, byte: usize) {
+        for c in haystack[cursor.byte..byte].chars() {
+            cursor.char += 1;
+            if c == '\n' {
+                cursor.line += 1;
+                cursor.column = 0;
+            } else {
+                cursor.column += 1;
+            }
+        }
+        cursor.byte = byte;
+    }
+}
+
+impl<'h, I, V> Iterator for PositionIterator<'h, I>
+where
+    I: Iterator<Item = Match<V>>,
+    V: Copy,
+{
+    type Item = PositionedMatch<V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.matches.next()?;
+
+        Self::advance(self.haystack, &mut self.end, m.end());
+        let end = self.end;
+
+        let start = if m.start() >= self.start.byte {
+            Self::advance(self.haystack, &mut self.start, m.start());
+            self.start
+        } else {
+            // Overlapping matches can start before the previous one. Count back from the end.
+            let mut num_chars = 0;
+            let mut num_lines = 0;
+            for c in self.haystack[m.start()..m.end()].chars() {
+                num_chars += 1;
+                if c == '\n' {
+                    num_lines += 1;
+                }
+            }
+            let char = end.char - num_chars;
+            let line = end.line - num_lines;
+            let column = if num_lines == 0 {
+                end.column - num_chars
+            } else if line == self.start.line {
+                self.start.column - (self.start.char - char)
+            } else {
+                let prefix = &self.haystack[..m.start()];
+                prefix[prefix.rfind('\n').map_or(0, |i| i + 1)..]
+                    .chars()
+                    .count()
+            };
+            Position {
+                byte: m.start(),
+                char,
+                line,
+                column,
+            }
+        };
+
+        Some(PositionedMatch {
+            start,
+            end,
+            value: m.value(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_positions() {
+        let haystack = "あa\nいう\n\nえ";
+        let matches = [(0, 3, 0), (4, 11, 1), (11, 12, 2), (12, 16, 3)]
+            .into_iter()
+            .map(|(start, end, value)| Match {
+                length: end - start,
+                end,
+                value,
+            });
+        let positions: Vec<_> = PositionIterator::new(haystack, matches)
+            .map(|m| {
+                (
+                    (m.start().char(), m.start().line(), m.start().column()),
+                    (m.end().char(), m.end().line(), m.end().column()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ((0, 0, 0), (1, 0, 1)),
+                ((2, 0, 2), (5, 1, 2)),
+                ((5, 1, 2), (6, 2, 0)),
+                ((6, 2, 0), (8, 3, 1)),
+            ],
+            positions
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Iterator over segmented haystacks.
+
+/// Iterator of the bytes in a sequence of segments.
+#[doc(hidden)]
+pub struct SegmentIterator<I, S> {
+    segments: I,
+    segment: Option<S>,
+    pos: usize,
+}
+
+impl<I, S> SegmentIterator<I, S>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<[u8]>,
+{
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn new(segments: I) -> Self {
+        Self {
+            segments,
+            segment: None,
+            pos: 0,
+        }
+    }
+}
+
+impl<I, S> Iterator for SegmentIterator<I, S>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<[u8]>,
+{
+    type Item = u8;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(&c) = self
+                .segment
+                .as_ref()
+                .and_then(|segment| segment.as_ref().get(self.pos))
+            {
+                self.pos += 1;
+                return Some(c);
+            }
+            self.segment = Some(self.segments.next()?);
+            self.pos = 0;
+        }
+    }
+}
+
+/// Segment of a string haystack viewed as bytes.
+#[doc(hidden)]
+pub struct StrSegment<S>(S);
+
+impl<S> AsRef<[u8]> for StrSegment<S>
+where
+    S: AsRef<str>,
+{
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref().as_bytes()
+    }
+}
+
+/// Iterator of string segments viewed as bytes.
+#[doc(hidden)]
+pub struct StrSegments<I>(pub(crate) I);
+
+impl<I> Iterator for StrSegments<I>
+where
+    I: Iterator,
+{
+    type Item = StrSegment<I::Item>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(StrSegment)
+    }
+}
+
+/// Iterator of the bytes in a sequence of string segments.
+#[doc(hidden)]
+pub type StrSegmentIterator<I, S> = SegmentIterator<StrSegments<I>, StrSegment<S>>;
@@ -213,3 +213,26 @@ pub mod nzu32_option_serde {
         Ok(NonZeroU32::new(u))
     }
 }
+
+#[cfg(feature = "serde")]
+pub mod byte_table_serde {
+    use alloc::vec::Vec;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(table: &[u8; 256], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        table[..].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 256], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let table = Vec::<u8>::deserialize(deserializer)?;
+        <[u8; 256]>::try_from(table.as_slice())
+            .map_err(|_| D::Error::invalid_length(table.len(), &"256 bytes"))
+    }
+}
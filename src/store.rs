@@ -0,0 +1,418 @@
+//! Automata associating patterns with values that are not [`Copy`].
+//!
+//! [`DoubleArrayAhoCorasick`] and [`CharwiseDoubleArrayAhoCorasick`] store associated values inline
+//! and return them by copy, so the values must implement [`Copy`]. [`ValueStore`] instead wraps an
+//! automaton whose values are indices into a separate table of values, such as [`String`]s or
+//! [`Vec`]s, and returns references to them in match results.
+//!
+//! Since patterns refer to values through indices, a value can be shared by many patterns by
+//! passing the same index to `new()`.
+//!
+//! With the `serde` or `bitcode` feature, [`ValueStore`] can be serialized together with its
+//! values, whose sizes may vary.
+//!
+//! [`String`]: alloc::string::String
+
+use core::mem;
+
+use alloc::vec::Vec;
+
+use crate::bytewise::iter::{
+    FindIterator, FindOverlappingIterator, FindOverlappingNoSuffixIterator, LeftmostFindIterator,
+    U8SliceIterator,
+};
+use crate::charwise::iter::{
+    FindIterator as CharwiseFindIterator,
+    FindOverlappingIterator as CharwiseFindOverlappingIterator,
+    FindOverlappingNoSuffixIterator as CharwiseFindOverlappingNoSuffixIterator,
+    LeftmostFindIterator as CharwiseLeftmostFindIterator, StrIterator,
+};
+use crate::errors::{DaachorseError, Result};
+use crate::utils::FromU32;
+use crate::{CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick, IntoInput, Match};
+
+/// An automaton whose matches refer to values in a separate table.
+///
+/// The automaton `A` is either [`DoubleArrayAhoCorasick<u32>`] or
+/// [`CharwiseDoubleArrayAhoCorasick<u32>`], and the value associated with each pattern is the
+/// index of the actual value in the table. The search methods return [`Match<&V>`].
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::store::DoubleArrayAhoCorasickStore;
+///
+/// let patvals = vec![("bcd", "BCD".to_string()), ("ab", "AB".to_string())];
+/// let pma = DoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+///
+/// let mut it = pma.find_iter("abcd");
+///
+/// let m = it.next().unwrap();
+/// assert_eq!((0, 2, "AB"), (m.start(), m.end(), m.value().as_str()));
+///
+/// assert_eq!(None, it.next());
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde"))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "A: serde::Serialize, V: serde::Serialize",
+        deserialize = "A: serde::Deserialize<'de>, V: serde::Deserialize<'de>",
+    ))
+)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+pub struct ValueStore<A, V> {
+    pma: A,
+    values: Vec<V>,
+}
+
+impl<A, V> ValueStore<A, V> {
+    /// Returns the inner automaton, whose values are indices into [`ValueStore::values()`].
+    #[must_use]
+    pub const fn automaton(&self) -> &A {
+        &self.pma
+    }
+
+    /// Returns the table of values.
+    #[must_use]
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Converts an iterator of matches from the inner automaton into an iterator of matches with
+    /// references to values.
+    ///
+    /// This is useful for search methods of the inner automaton not wrapped by [`ValueStore`].
+    ///
+    /// # Arguments
+    ///
+    /// * `iter` - Iterator of matches returned by [`ValueStore::automaton()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use daachorse::store::DoubleArrayAhoCorasickStore;
+    ///
+    /// let patvals = vec![("bcd", vec![1, 2]), ("ab", vec![3])];
+    /// let pma = DoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+    ///
+    /// let haystack = b"abcd".iter().copied();
+    /// let mut it = pma.resolve(pma.automaton().find_iter_from_iter(haystack));
+    ///
+    /// let m = it.next().unwrap();
+    /// assert_eq!((0, 2, &vec![3]), (m.start(), m.end(), m.value()));
+    ///
+    /// assert_eq!(None, it.next());
+    /// ```
+    pub fn resolve<I>(&self, iter: I) -> ValueIterator<'_, I, V>
+    where
+        I: Iterator<Item = Match<u32>>,
+    {
+        ValueIterator {
+            inner: iter,
+            values: &self.values,
+        }
+    }
+
+    fn check_indices<I>(indices: I, num_values: usize) -> Result<()>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        if indices
+            .into_iter()
+            .any(|i| usize::from_u32(i) >= num_values)
+        {
+            return Err(DaachorseError::invalid_argument(
+                "value index",
+                "<",
+                u32::try_from(num_values).unwrap_or(u32::MAX),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Moves values into `values` and returns pairs of a pattern and the index of its value.
+    fn split_values<I, P>(patvals: I, values: &mut Vec<V>) -> Result<Vec<(P, u32)>>
+    where
+        I: IntoIterator<Item = (P, V)>,
+    {
+        let mut patids = vec![];
+        for (pattern, value) in patvals {
+            let idx = u32::try_from(values.len())
+                .map_err(|_| DaachorseError::automaton_scale("values.len()", u32::MAX))?;
+            patids.push((pattern, idx));
+            values.push(value);
+        }
+        Ok(patids)
+    }
+}
+
+macro_rules! define_common_methods {
+    ($automaton:ident, $haystack:ty) => {
+        /// Creates a new [`ValueStore`] from an automaton and a table of values.
+        ///
+        /// The value associated with each pattern in `pma` must be an index into `values`. Many
+        /// patterns can share the same index.
+        ///
+        /// # Arguments
+        ///
+        /// * `pma` - Automaton whose values are indices into `values`.
+        /// * `values` - Table of values.
+        ///
+        /// # Errors
+        ///
+        /// [`DaachorseError`] is returned when `pma` contains an index not smaller than
+        /// `values.len()`.
+        pub fn new(pma: $automaton<u32>, values: Vec<V>) -> Result<Self> {
+            Self::check_indices(pma.output_values(), values.len())?;
+            Ok(Self { pma, values })
+        }
+
+        /// Creates a new [`ValueStore`] from input pattern-value pairs with
+        /// [`MatchKind::Standard`](crate::MatchKind::Standard).
+        ///
+        /// Each value is stored separately even if it is equal to another one. To share a value
+        /// among patterns, use `new()` instead.
+        ///
+        /// # Arguments
+        ///
+        /// * `patvals` - List of pattern-value pairs.
+        ///
+        /// # Errors
+        ///
+        /// [`DaachorseError`] is returned when
+        ///   - `patvals` is empty,
+        ///   - `patvals` contains patterns of length zero,
+        ///   - `patvals` contains duplicate patterns,
+        ///   - the scale of `patvals` exceeds the expected one, or
+        ///   - the scale of the resulting automaton exceeds the expected one.
+        pub fn with_values<I, P>(patvals: I) -> Result<Self>
+        where
+            I: IntoIterator<Item = (P, V)>,
+            P: AsRef<$haystack>,
+        {
+            let mut values = vec![];
+            let patids = Self::split_values(patvals, &mut values)?;
+            let pma = $automaton::with_values(patids)?;
+            Ok(Self { pma, values })
+        }
+
+        /// Returns the total amount of heap used by this automaton in bytes.
+        ///
+        /// The heap owned by each value, such as the buffer of a [`String`](alloc::string::String),
+        /// is not included.
+        #[must_use]
+        pub fn heap_bytes(&self) -> usize {
+            self.pma.heap_bytes() + self.values.len() * mem::size_of::<V>()
+        }
+    };
+}
+
+/// [`ValueStore`] with the byte-wise automaton.
+pub type DoubleArrayAhoCorasickStore<V> = ValueStore<DoubleArrayAhoCorasick<u32>, V>;
+
+/// [`ValueStore`] with the character-wise automaton.
+pub type CharwiseDoubleArrayAhoCorasickStore<V> =
+    ValueStore<CharwiseDoubleArrayAhoCorasick<u32>, V>;
+
+impl<V> ValueStore<DoubleArrayAhoCorasick<u32>, V> {
+    define_common_methods!(DoubleArrayAhoCorasick, [u8]);
+
+    /// Returns an iterator of non-overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte string to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, FindIterator<'_, U8SliceIterator<H::Haystack>, u32>, V>
+    where
+        H: IntoInput<[u8]>,
+    {
+        self.resolve(self.pma.find_iter(haystack))
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte string to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_overlapping_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, FindOverlappingIterator<'_, U8SliceIterator<H::Haystack>, u32>, V>
+    where
+        H: IntoInput<[u8]>,
+    {
+        self.resolve(self.pma.find_overlapping_iter(haystack))
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte string to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_overlapping_no_suffix_iter<P>(
+        &self,
+        haystack: P,
+    ) -> ValueIterator<'_, FindOverlappingNoSuffixIterator<'_, U8SliceIterator<P>, u32>, V>
+    where
+        P: AsRef<[u8]>,
+    {
+        self.resolve(self.pma.find_overlapping_no_suffix_iter(haystack))
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - Byte string to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`](crate::MatchKind::LeftmostLongest),
+    /// [`MatchKind::LeftmostFirst`](crate::MatchKind::LeftmostFirst), or
+    /// [`MatchKind::LeftmostShortest`](crate::MatchKind::LeftmostShortest) in the construction,
+    /// the iterator is not supported and the function will panic.
+    pub fn leftmost_find_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, LeftmostFindIterator<'_, H::Haystack, u32>, V>
+    where
+        H: IntoInput<[u8]>,
+    {
+        self.resolve(self.pma.leftmost_find_iter(haystack))
+    }
+}
+
+impl<V> ValueStore<CharwiseDoubleArrayAhoCorasick<u32>, V> {
+    define_common_methods!(CharwiseDoubleArrayAhoCorasick, str);
+
+    /// Returns an iterator of non-overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, CharwiseFindIterator<'_, StrIterator<H::Haystack>, u32>, V>
+    where
+        H: IntoInput<str>,
+    {
+        self.resolve(self.pma.find_iter(haystack))
+    }
+
+    /// Returns an iterator of overlapping matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_overlapping_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, CharwiseFindOverlappingIterator<'_, StrIterator<H::Haystack>, u32>, V>
+    where
+        H: IntoInput<str>,
+    {
+        self.resolve(self.pma.find_overlapping_iter(haystack))
+    }
+
+    /// Returns an iterator of overlapping matches without suffixes in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::Standard`](crate::MatchKind::Standard) in the
+    /// construction, the iterator is not supported and the function will panic.
+    pub fn find_overlapping_no_suffix_iter<P>(
+        &self,
+        haystack: P,
+    ) -> ValueIterator<'_, CharwiseFindOverlappingNoSuffixIterator<'_, StrIterator<P>, u32>, V>
+    where
+        P: AsRef<str>,
+    {
+        self.resolve(self.pma.find_overlapping_no_suffix_iter(haystack))
+    }
+
+    /// Returns an iterator of leftmost matches in the given haystack.
+    ///
+    /// # Arguments
+    ///
+    /// * `haystack` - String to search for, or [`Input`](crate::Input) specifying the span
+    ///   and anchoring.
+    ///
+    /// # Panics
+    ///
+    /// If you do not specify [`MatchKind::LeftmostLongest`](crate::MatchKind::LeftmostLongest),
+    /// [`MatchKind::LeftmostFirst`](crate::MatchKind::LeftmostFirst), or
+    /// [`MatchKind::LeftmostShortest`](crate::MatchKind::LeftmostShortest) in the construction,
+    /// the iterator is not supported and the function will panic.
+    pub fn leftmost_find_iter<H>(
+        &self,
+        haystack: H,
+    ) -> ValueIterator<'_, CharwiseLeftmostFindIterator<'_, H::Haystack, u32>, V>
+    where
+        H: IntoInput<str>,
+    {
+        self.resolve(self.pma.leftmost_find_iter(haystack))
+    }
+}
+
+/// Iterator created by [`ValueStore::resolve()`] and the search methods of [`ValueStore`].
+pub struct ValueIterator<'a, I, V> {
+    inner: I,
+    values: &'a [V],
+}
+
+impl<'a, I, V> Iterator for ValueIterator<'a, I, V>
+where
+    I: Iterator<Item = Match<u32>>,
+{
+    type Item = Match<&'a V>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|m| Match {
+            length: m.length,
+            end: m.end,
+            value: &self.values[usize::from_u32(m.value)],
+        })
+    }
+}
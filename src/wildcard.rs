@@ -0,0 +1,365 @@
+//! Patterns with wildcards and character classes.
+
+use core::ops::{Range, RangeInclusive};
+
+use alloc::vec::Vec;
+
+use crate::errors::{DaachorseError, Result};
+
+// The end of the code points.
+const CHAR_END: u32 = 0x11_0000;
+
+// The surrogate code points, which are not characters.
+const SURROGATES: Range<u32> = 0xD800..0xE000;
+
+// The boundaries of the code points by the lengths in UTF-8 and of the surrogates.
+const FIXED_BOUNDARIES: [u32; 5] = [0x80, 0x800, 0xD800, 0xE000, 0x1_0000];
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Element {
+    Literal(char),
+    Any,
+    // Sorted, disjoint, and non-adjacent ranges.
+    Class(Vec<(char, char)>),
+}
+
+/// Pattern containing single-character wildcards and character classes, which is registered
+/// with `build_with_wildcards()` of the builders.
+///
+/// In construction, each element adds edges for all its alternatives to the states reached by
+/// the preceding elements, so the number of the states can grow with the product of the numbers
+/// of the alternatives. The total number of the states is limited by `max_wildcard_states()` of
+/// the builders. In [`CharwiseDoubleArrayAhoCorasick`], characters never distinguished by the
+/// patterns share an edge, so wildcards and large classes add only a few edges.
+///
+/// [`CharwiseDoubleArrayAhoCorasick`]: crate::CharwiseDoubleArrayAhoCorasick
+///
+/// # Examples
+///
+/// ```
+/// use daachorse::{DoubleArrayAhoCorasickBuilder, WildcardPattern};
+///
+/// let patvals = vec![
+///     (WildcardPattern::new().literal("colo").any().literal("r"), 0),
+///     (
+///         WildcardPattern::new()
+///             .class(['0'..='9'])
+///             .class(['0'..='9'])
+///             .literal("-item"),
+///         1,
+///     ),
+///     (WildcardPattern::new().literal("Ver.").class(['0'..='9']), 2),
+/// ];
+/// let pma = DoubleArrayAhoCorasickBuilder::new()
+///     .build_with_wildcards(patvals)
+///     .unwrap();
+///
+/// let matches: Vec<_> = pma
+///     .find_iter("color, colour, 42-item, Ver.3")
+///     .map(|m| (m.start(), m.end(), m.value()))
+///     .collect();
+/// assert_eq!(vec![(7, 13, 0), (15, 22, 1), (24, 29, 2)], matches);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct WildcardPattern {
+    elements: Vec<Element>,
+}
+
+impl WildcardPattern {
+    /// Creates an empty pattern.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { elements: vec![] }
+    }
+
+    /// Appends the characters of the given string.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String matched literally.
+    #[must_use]
+    pub fn literal(mut self, s: &str) -> Self {
+        self.elements.extend(s.chars().map(Element::Literal));
+        self
+    }
+
+    /// Appends a wildcard matching any single byte in [`DoubleArrayAhoCorasick`] or any
+    /// single character in [`CharwiseDoubleArrayAhoCorasick`].
+    ///
+    /// In [`DoubleArrayAhoCorasick`], the wildcard adds 256 edges to each state it follows.
+    ///
+    /// [`DoubleArrayAhoCorasick`]: crate::DoubleArrayAhoCorasick
+    /// [`CharwiseDoubleArrayAhoCorasick`]: crate::CharwiseDoubleArrayAhoCorasick
+    #[must_use]
+    pub fn any(mut self) -> Self {
+        self.elements.push(Element::Any);
+        self
+    }
+
+    /// Appends a character class matching any single character in the given ranges.
+    ///
+    /// # Arguments
+    ///
+    /// * `ranges` - Ranges of characters. Empty ranges are ignored.
+    #[must_use]
+    pub fn class<I>(mut self, ranges: I) -> Self
+    where
+        I: IntoIterator<Item = RangeInclusive<char>>,
+    {
+        let mut sorted: Vec<(char, char)> = ranges
+            .into_iter()
+            .filter(|r| r.start() <= r.end())
+            .map(|r| (*r.start(), *r.end()))
+            .collect();
+        sorted.sort_unstable();
+        let mut merged: Vec<(char, char)> = vec![];
+        for (start, end) in sorted {
+            if let Some(last) = merged.last_mut() {
+                if u32::from(start) <= u32::from(last.1) + 1 {
+                    last.1 = last.1.max(end);
+                    continue;
+                }
+            }
+            merged.push((start, end));
+        }
+        self.elements.push(Element::Class(merged));
+        self
+    }
+
+    /// Appends a character class matching any single character in the given string.
+    ///
+    /// # Arguments
+    ///
+    /// * `chars` - Characters in the class.
+    #[must_use]
+    pub fn one_of(self, chars: &str) -> Self {
+        self.class(chars.chars().map(|c| c..=c))
+    }
+
+    /// Returns the alternatives of each element in bytes, which are single bytes for a
+    /// wildcard and UTF-8 encoded characters otherwise.
+    pub(crate) fn byte_alternatives(&self) -> Result<Vec<Vec<ByteAlternative>>> {
+        self.elements
+            .iter()
+            .map(|element| match element {
+                Element::Literal(c) => Ok(vec![ByteAlternative::from_char(*c)]),
+                Element::Any => Ok((0..=u8::MAX).map(ByteAlternative::from_byte).collect()),
+                Element::Class(ranges) => {
+                    check_class(ranges)?;
+                    Ok(ranges
+                        .iter()
+                        .flat_map(|&(start, end)| (start..=end).map(ByteAlternative::from_char))
+                        .collect())
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the alternatives of each element in characters, which are the first characters
+    /// of the classes returned by [`char_classes()`] for the patterns including this one.
+    pub(crate) fn char_alternatives(&self, classes: &[(u32, u32)]) -> Result<Vec<Vec<[char; 1]>>> {
+        // Returns the first characters of the classes in start..end.
+        let class_chars = |start: u32, end: u32| {
+            let first = classes.partition_point(|&(_, e)| e <= start);
+            let last = classes.partition_point(|&(s, _)| s < end);
+            // The first code point of a class is always a character since surrogates are
+            // excluded from the classes.
+            classes[first..last]
+                .iter()
+                .map(|&(s, _)| [char::from_u32(s).unwrap()])
+        };
+        self.elements
+            .iter()
+            .map(|element| match element {
+                Element::Literal(c) => Ok(vec![[*c]]),
+                Element::Any => Ok(class_chars(0, CHAR_END).collect()),
+                Element::Class(ranges) => {
+                    check_class(ranges)?;
+                    Ok(ranges
+                        .iter()
+                        .flat_map(|&(start, end)| class_chars(start.into(), u32::from(end) + 1))
+                        .collect())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Single byte or UTF-8 encoded character, which is an alternative of an element in bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ByteAlternative {
+    bytes: [u8; 4],
+    len: u8,
+}
+
+impl ByteAlternative {
+    const fn from_byte(b: u8) -> Self {
+        Self {
+            bytes: [b, 0, 0, 0],
+            len: 1,
+        }
+    }
+
+    fn from_char(c: char) -> Self {
+        let mut bytes = [0; 4];
+        let len = u8::try_from(c.encode_utf8(&mut bytes).len()).unwrap();
+        Self { bytes, len }
+    }
+}
+
+impl AsRef<[u8]> for ByteAlternative {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+/// Returns the classes of the characters in the given patterns, which are disjoint ranges
+/// `start..end` of code points in ascending order.
+///
+/// The characters in a class are not distinguished by any element of the patterns and have the
+/// same length in UTF-8. Characters matched by no element belong to no class.
+pub(crate) fn char_classes<'a, I>(patterns: I) -> Vec<(u32, u32)>
+where
+    I: IntoIterator<Item = &'a WildcardPattern>,
+{
+    let mut ranges = vec![];
+    for pattern in patterns {
+        for element in &pattern.elements {
+            match element {
+                Element::Literal(c) => ranges.push((u32::from(*c), u32::from(*c) + 1)),
+                Element::Any => ranges.push((0, CHAR_END)),
+                Element::Class(class) => ranges.extend(
+                    class
+                        .iter()
+                        .map(|&(start, end)| (u32::from(start), u32::from(end) + 1)),
+                ),
+            }
+        }
+    }
+    let mut boundaries: Vec<u32> = ranges
+        .iter()
+        .flat_map(|&(start, end)| [start, end])
+        .chain(FIXED_BOUNDARIES)
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    // The union of the ranges.
+    ranges.sort_unstable();
+    let mut covered: Vec<(u32, u32)> = vec![];
+    for (start, end) in ranges {
+        if let Some(last) = covered.last_mut() {
+            if start <= last.1 {
+                last.1 = last.1.max(end);
+                continue;
+            }
+        }
+        covered.push((start, end));
+    }
+
+    boundaries
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .filter(|&(start, _)| {
+            let i = covered.partition_point(|&(_, end)| end <= start);
+            !SURROGATES.contains(&start) && covered.get(i).map_or(false, |&(s, _)| s <= start)
+        })
+        .collect()
+}
+
+/// Returns an error if the character class is empty.
+fn check_class(ranges: &[(char, char)]) -> Result<()> {
+    if ranges.is_empty() {
+        return Err(DaachorseError::invalid_argument("class.len()", ">=", 1));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_merge() {
+        let pattern = WildcardPattern::new().class(['c'..='e', 'a'..='b', 'd'..='f', 'z'..='x']);
+        assert_eq!(vec![Element::Class(vec![('a', 'f')])], pattern.elements);
+    }
+
+    #[test]
+    fn test_byte_alternatives() {
+        let pattern = WildcardPattern::new().one_of("aé").literal("b").any();
+        let alternatives = pattern.byte_alternatives().unwrap();
+        assert_eq!(3, alternatives.len());
+        assert_eq!(
+            vec![b"a".as_slice(), "é".as_bytes()],
+            alternatives[0]
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![ByteAlternative::from_byte(b'b')], alternatives[1]);
+        assert_eq!(256, alternatives[2].len());
+    }
+
+    #[test]
+    fn test_char_classes() {
+        let patterns = [
+            WildcardPattern::new().class(['a'..='z']).literal("x"),
+            WildcardPattern::new().class(['\u{D7FF}'..='\u{E000}']),
+        ];
+        assert_eq!(
+            vec![
+                (0x61, 0x78),
+                (0x78, 0x79),
+                (0x79, 0x7B),
+                (0xD7FF, 0xD800),
+                (0xE000, 0xE001)
+            ],
+            char_classes(&patterns)
+        );
+
+        let patterns = [WildcardPattern::new().literal("あ").any()];
+        assert_eq!(
+            vec![
+                (0, 0x80),
+                (0x80, 0x800),
+                (0x800, 0x3042),
+                (0x3042, 0x3043),
+                (0x3043, 0xD800),
+                (0xE000, 0x1_0000),
+                (0x1_0000, CHAR_END)
+            ],
+            char_classes(&patterns)
+        );
+    }
+
+    #[test]
+    fn test_char_alternatives() {
+        let patterns = [
+            WildcardPattern::new().class(['a'..='z']).literal("x"),
+            WildcardPattern::new().any(),
+        ];
+        let classes = char_classes(&patterns);
+        assert_eq!(
+            vec![vec![['a'], ['x'], ['y']], vec![['x']]],
+            patterns[0].char_alternatives(&classes).unwrap()
+        );
+        assert_eq!(
+            classes.len(),
+            patterns[1].char_alternatives(&classes).unwrap()[0].len()
+        );
+    }
+
+    #[test]
+    fn test_empty_class() {
+        let pattern = WildcardPattern::new().literal("a").class([]);
+        assert!(pattern.byte_alternatives().is_err());
+        assert!(pattern
+            .char_alternatives(&char_classes([&pattern]))
+            .is_err());
+        assert!(WildcardPattern::new()
+            .one_of("")
+            .byte_alternatives()
+            .is_err());
+    }
+}
@@ -1,10 +1,11 @@
+use daachorse::charwise::InvalidSequence;
 use daachorse::{
     CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder, Match, MatchKind,
 };
 
-/// The following test suites are copied from
-/// [aho-corasick crate](https://github.com/BurntSushi/aho-corasick/blob/master/src/tests.rs),
-/// although duplicate and empty patterns are removed.
+// The following test suites are copied from
+// [aho-corasick crate](https://github.com/BurntSushi/aho-corasick/blob/master/src/tests.rs),
+// although duplicate and empty patterns are removed.
 
 /// A description of a single test against an Aho-Corasick automaton.
 ///
@@ -45,20 +46,22 @@ type TestCollection = &'static [&'static [SearchTest]];
 // but each collection should have some tests that no other collection has.
 
 /// Tests for Aho-Corasick's standard non-overlapping match semantics.
-const AC_STANDARD_NON_OVERLAPPING: TestCollection = &[BASICS, NON_OVERLAPPING, STANDARD];
+const AC_STANDARD_NON_OVERLAPPING: TestCollection = &[BASICS, UNICODE, NON_OVERLAPPING, STANDARD];
 
 /// Tests for Aho-Corasick's standard overlapping match semantics.
-const AC_STANDARD_OVERLAPPING: TestCollection = &[BASICS, OVERLAPPING];
+const AC_STANDARD_OVERLAPPING: TestCollection = &[BASICS, UNICODE, OVERLAPPING];
 
 /// Tests for Aho-Corasick's leftmost-longest match semantics.
-const AC_LEFTMOST_LONGEST: TestCollection = &[BASICS, NON_OVERLAPPING, LEFTMOST, LEFTMOST_LONGEST];
+const AC_LEFTMOST_LONGEST: TestCollection =
+    &[BASICS, UNICODE, NON_OVERLAPPING, LEFTMOST, LEFTMOST_LONGEST];
 
 /// Tests for Aho-Corasick's leftmost-first match semantics.
-const AC_LEFTMOST_FIRST: TestCollection = &[BASICS, NON_OVERLAPPING, LEFTMOST, LEFTMOST_FIRST];
+const AC_LEFTMOST_FIRST: TestCollection =
+    &[BASICS, UNICODE, NON_OVERLAPPING, LEFTMOST, LEFTMOST_FIRST];
 
 /// A collection of tests for the Aho-Corasick algorithm that should always be true.
 /// That is, all iterators should produce the same answer.
-const BASICS: &'static [SearchTest] = &[
+const BASICS: &[SearchTest] = &[
     t!(basic001, &["a"], "", &[]),
     t!(basic010, &["a"], "a", &[(0, 0, 1)]),
     t!(basic020, &["a"], "aa", &[(0, 0, 1), (0, 1, 2)]),
@@ -146,13 +149,32 @@ const BASICS: &'static [SearchTest] = &[
     ),
 ];
 
+/// Tests on haystacks containing multibyte characters, which should always be true.
+///
+/// These are not in the aho-corasick crate. They check that the positions are counted in the
+/// code units of each encoding, including surrogate pairs in UTF-16.
+const UNICODE: &[SearchTest] = &[
+    t!(unicode010, &["あ"], "あいあ", &[(0, 0, 3), (0, 6, 9)]),
+    t!(unicode020, &["🍣"], "a🍣b🍣", &[(0, 1, 5), (0, 6, 10)]),
+    t!(
+        unicode030,
+        &["é", "🍣"],
+        "é🍣aé",
+        &[(0, 0, 2), (1, 2, 6), (0, 7, 9)]
+    ),
+    t!(unicode040, &["🍣🍣"], "🍣🍣x🍣🍣", &[(0, 0, 8), (0, 9, 17)]),
+    t!(unicode050, &["a𝄞"], "𝄞a𝄞", &[(0, 4, 9)]),
+    t!(unicode060, &["𝄞", "い"], "あ𝄞い", &[(0, 3, 7), (1, 7, 10)]),
+    t!(unicode070, &["\u{FFFD}"], "a\u{FFFD}", &[(0, 1, 4)]),
+];
+
 /// Tests for non-overlapping standard match semantics.
 ///
 /// These tests generally shouldn't pass for leftmost-{first,longest}, although
 /// some do in order to write clearer tests. For example, standard000 will
 /// pass with leftmost-first semantics, but standard010 will not. We write
 /// both to emphasize how the match semantics work.
-const STANDARD: &'static [SearchTest] = &[
+const STANDARD: &[SearchTest] = &[
     t!(standard000, &["ab", "abcd"], "abcd", &[(0, 0, 2)]),
     t!(standard010, &["abcd", "ab"], "abcd", &[(1, 0, 2)]),
     t!(standard020, &["abcd", "ab", "abc"], "abcd", &[(1, 0, 2)]),
@@ -169,7 +191,7 @@ const STANDARD: &'static [SearchTest] = &[
 /// both leftmost-first and leftmost-longest match kinds. Stated differently,
 /// among ambiguous matches, the longest match and the match that appeared
 /// first when constructing the automaton should always be the same.
-const LEFTMOST: &'static [SearchTest] = &[
+const LEFTMOST: &[SearchTest] = &[
     t!(leftmost030, &["a", "ab"], "aa", &[(0, 0, 1), (0, 1, 2)]),
     t!(leftmost031, &["ab", "a"], "aa", &[(1, 0, 1), (1, 1, 2)]),
     t!(
@@ -235,7 +257,7 @@ const LEFTMOST: &'static [SearchTest] = &[
 /// Tests for non-overlapping leftmost-first match semantics. These tests
 /// should generally be specific to leftmost-first, which means they should
 /// generally fail under leftmost-longest semantics.
-const LEFTMOST_FIRST: &'static [SearchTest] = &[
+const LEFTMOST_FIRST: &[SearchTest] = &[
     t!(leftfirst000, &["ab", "abcd"], "abcd", &[(0, 0, 2)]),
     t!(leftfirst020, &["abcd", "ab"], "abcd", &[(0, 0, 4)]),
     t!(
@@ -280,7 +302,7 @@ const LEFTMOST_FIRST: &'static [SearchTest] = &[
 /// Tests for non-overlapping leftmost-longest match semantics. These tests
 /// should generally be specific to leftmost-longest, which means they should
 /// generally fail under leftmost-first semantics.
-const LEFTMOST_LONGEST: &'static [SearchTest] = &[
+const LEFTMOST_LONGEST: &[SearchTest] = &[
     t!(leftlong000, &["ab", "abcd"], "abcd", &[(1, 0, 4)]),
     t!(
         leftlong010,
@@ -330,7 +352,7 @@ const LEFTMOST_LONGEST: &'static [SearchTest] = &[
 ///
 /// Generally these tests shouldn't pass when using overlapping semantics.
 /// These should pass for both standard and leftmost match semantics.
-const NON_OVERLAPPING: &'static [SearchTest] = &[
+const NON_OVERLAPPING: &[SearchTest] = &[
     t!(nover010, &["abcd", "bcd", "cd"], "abcd", &[(0, 0, 4),]),
     t!(nover020, &["bcd", "cd", "abcd"], "abcd", &[(2, 0, 4),]),
     t!(nover030, &["abc", "bc"], "zazabcz", &[(0, 3, 6),]),
@@ -346,7 +368,7 @@ const NON_OVERLAPPING: &'static [SearchTest] = &[
 ///
 /// This only supports standard match semantics, since leftmost-{first,longest}
 /// do not support overlapping matches.
-const OVERLAPPING: &'static [SearchTest] = &[
+const OVERLAPPING: &[SearchTest] = &[
     t!(
         over000,
         &["abcd", "bcd", "cd", "b"],
@@ -416,6 +438,7 @@ fn search_tests_have_unique_names() {
         }
     };
     assert("BASICS", BASICS);
+    assert("UNICODE", UNICODE);
     assert("STANDARD", STANDARD);
     assert("LEFTMOST", LEFTMOST);
     assert("LEFTMOST_FIRST", LEFTMOST_FIRST);
@@ -461,6 +484,84 @@ macro_rules! testconfig {
             });
         }
     };
+    (non_overlapping_utf16, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_utf16_search_tests($collection, |test, haystack| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.find_iter_utf16(haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
+    (overlapping_utf16, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_utf16_search_tests($collection, |test, haystack| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.find_overlapping_iter_utf16(haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
+    (non_overlapping_lossy, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_search_tests($collection, |test| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.find_iter_lossy(test.haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
+    (overlapping_lossy, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_search_tests($collection, |test| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.find_overlapping_iter_lossy(test.haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
+    (leftmost_lossy, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_search_tests($collection, |test| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.leftmost_find_iter_lossy(test.haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
+    (leftmost_utf16, $name:ident, $builder:ident, $collection:expr, $kind:ident, $with:expr) => {
+        #[test]
+        fn $name() {
+            run_utf16_search_tests($collection, |test, haystack| {
+                let pma = $builder::new()
+                    .match_kind(MatchKind::$kind)
+                    .build(test.patterns)
+                    .unwrap();
+                pma.leftmost_find_iter_utf16(haystack, InvalidSequence::Reset)
+                    .collect()
+            });
+        }
+    };
 }
 
 // Bytewise Daachorse tests
@@ -537,18 +638,119 @@ testconfig!(
     |_| ()
 );
 
+// Charwise Daachorse tests on UTF-16 haystacks.
+// The expected positions are converted from UTF-8 bytes into UTF-16 code units.
+testconfig!(
+    non_overlapping_utf16,
+    search_standard_non_overlapping_charwise_utf16,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_STANDARD_NON_OVERLAPPING,
+    Standard,
+    |_| ()
+);
+
+testconfig!(
+    overlapping_utf16,
+    search_standard_overlapping_charwise_utf16,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_STANDARD_OVERLAPPING,
+    Standard,
+    |_| ()
+);
+
+testconfig!(
+    leftmost_utf16,
+    search_leftmost_longest_charwise_utf16,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_LEFTMOST_LONGEST,
+    LeftmostLongest,
+    |_| ()
+);
+
+testconfig!(
+    leftmost_utf16,
+    search_leftmost_first_charwise_utf16,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_LEFTMOST_FIRST,
+    LeftmostFirst,
+    |_| ()
+);
+
+// Charwise Daachorse tests on byte sequences.
+testconfig!(
+    non_overlapping_lossy,
+    search_standard_non_overlapping_charwise_lossy,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_STANDARD_NON_OVERLAPPING,
+    Standard,
+    |_| ()
+);
+
+testconfig!(
+    overlapping_lossy,
+    search_standard_overlapping_charwise_lossy,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_STANDARD_OVERLAPPING,
+    Standard,
+    |_| ()
+);
+
+testconfig!(
+    leftmost_lossy,
+    search_leftmost_longest_charwise_lossy,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_LEFTMOST_LONGEST,
+    LeftmostLongest,
+    |_| ()
+);
+
+testconfig!(
+    leftmost_lossy,
+    search_leftmost_first_charwise_lossy,
+    CharwiseDoubleArrayAhoCorasickBuilder,
+    AC_LEFTMOST_FIRST,
+    LeftmostFirst,
+    |_| ()
+);
+
+fn match_triples(matches: Vec<Match<usize>>) -> Vec<(usize, usize, usize)> {
+    matches
+        .into_iter()
+        .map(|m| (m.value(), m.start(), m.end()))
+        .collect()
+}
+
 fn run_search_tests<F: FnMut(&SearchTest) -> Vec<Match<usize>>>(which: TestCollection, mut f: F) {
-    let get_match_triples = |matches: Vec<Match<usize>>| -> Vec<(usize, usize, usize)> {
-        matches
-            .into_iter()
-            .map(|m| (m.value(), m.start(), m.end()))
-            .collect()
-    };
     for &tests in which {
         for test in tests {
             assert_eq!(
                 test.matches,
-                get_match_triples(f(&test)).as_slice(),
+                match_triples(f(test)).as_slice(),
+                "test: {}, patterns: {:?}, haystack: {:?}",
+                test.name,
+                test.patterns,
+                test.haystack
+            );
+        }
+    }
+}
+
+fn run_utf16_search_tests<F>(which: TestCollection, mut f: F)
+where
+    F: FnMut(&SearchTest, &[u16]) -> Vec<Match<usize>>,
+{
+    for &tests in which {
+        for test in tests {
+            let haystack: Vec<u16> = test.haystack.encode_utf16().collect();
+            let units = |i: usize| test.haystack[..i].encode_utf16().count();
+            let expected: Vec<_> = test
+                .matches
+                .iter()
+                .map(|&(value, start, end)| (value, units(start), units(end)))
+                .collect();
+            assert_eq!(
+                expected,
+                match_triples(f(test, &haystack)),
                 "test: {}, patterns: {:?}, haystack: {:?}",
                 test.name,
                 test.patterns,
@@ -557,3 +759,179 @@ fn run_search_tests<F: FnMut(&SearchTest) -> Vec<Match<usize>>>(which: TestColle
         }
     }
 }
+
+/// A test against a haystack containing invalid code units, which are not in the aho-corasick
+/// crate. The matches should be the same for all the search methods.
+struct InvalidSequenceTest<T: 'static> {
+    name: &'static str,
+    patterns: &'static [&'static str],
+    haystack: &'static [T],
+    invalid: InvalidSequence,
+    /// Each match is a triple of (value, start, end) in code units.
+    matches: &'static [(usize, usize, usize)],
+}
+
+/// Short-hand constructor for InvalidSequenceTest.
+macro_rules! inv {
+    ($name:ident, $patterns:expr, $haystack:expr, $invalid:ident, $matches:expr) => {
+        InvalidSequenceTest {
+            name: stringify!($name),
+            patterns: $patterns,
+            haystack: $haystack,
+            invalid: InvalidSequence::$invalid,
+            matches: $matches,
+        }
+    };
+}
+
+/// Tests on UTF-16 haystacks with unpaired surrogates.
+const UTF16_INVALID: &[InvalidSequenceTest<u16>] = &[
+    // A high surrogate not followed by a low one.
+    inv!(utf16_010, &["ab"], &[0x61, 0xD83C, 0x62], Reset, &[]),
+    inv!(
+        utf16_020,
+        &["a\u{FFFD}b"],
+        &[0x61, 0xD83C, 0x62],
+        Replace,
+        &[(0, 0, 3)]
+    ),
+    // A reversed surrogate pair is two unpaired surrogates.
+    inv!(
+        utf16_030,
+        &["\u{FFFD}"],
+        &[0xDF63, 0xD83C],
+        Replace,
+        &[(0, 0, 1), (0, 1, 2)]
+    ),
+    inv!(
+        utf16_040,
+        &["🍣", "a"],
+        &[0xD83C, 0xDF63, 0x61, 0xDF63],
+        Reset,
+        &[(0, 0, 2), (1, 2, 3)]
+    ),
+    inv!(
+        utf16_050,
+        &["あ🍣"],
+        &[0xDF63, 0x3042, 0xD83C, 0xDF63],
+        Reset,
+        &[(0, 1, 4)]
+    ),
+    inv!(
+        utf16_060,
+        &["a"],
+        &[0xD83C, 0x61, 0xD83C],
+        Reset,
+        &[(0, 1, 2)]
+    ),
+];
+
+/// Tests on byte sequences that are not valid UTF-8.
+const LOSSY_INVALID: &[InvalidSequenceTest<u8>] = &[
+    inv!(lossy010, &["ab"], b"a\xffb", Reset, &[]),
+    // The replacement character is three bytes in patterns but matches one invalid byte.
+    inv!(lossy020, &["a\u{FFFD}b"], b"a\xffb", Replace, &[(0, 0, 3)]),
+    // Each byte of a truncated sequence is one invalid unit.
+    inv!(
+        lossy030,
+        &["\u{FFFD}", "あ"],
+        b"\xe3\x81\xe3\x81\x82",
+        Replace,
+        &[(0, 0, 1), (0, 1, 2), (1, 2, 5)]
+    ),
+    inv!(
+        lossy040,
+        &["あ"],
+        b"\xe3\x81\xe3\x81\x82",
+        Reset,
+        &[(0, 2, 5)]
+    ),
+    inv!(
+        lossy050,
+        &["🍣"],
+        b"\x80\xf0\x9f\x8d\xa3\x80",
+        Reset,
+        &[(0, 1, 5)]
+    ),
+    // An overlong encoding and an encoded surrogate are invalid.
+    inv!(
+        lossy060,
+        &["a", "\u{FFFD}"],
+        b"\xc0a\xed\xa0\x80a",
+        Replace,
+        &[
+            (1, 0, 1),
+            (0, 1, 2),
+            (1, 2, 3),
+            (1, 3, 4),
+            (1, 4, 5),
+            (0, 5, 6)
+        ]
+    ),
+];
+
+#[test]
+fn search_invalid_sequence_charwise_utf16() {
+    for test in UTF16_INVALID {
+        for kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+            let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .build(test.patterns)
+                .unwrap();
+            let mut results = vec![];
+            if kind == MatchKind::Standard {
+                results.push(pma.find_iter_utf16(test.haystack, test.invalid).collect());
+                results.push(
+                    pma.find_overlapping_iter_utf16(test.haystack, test.invalid)
+                        .collect(),
+                );
+            } else {
+                results.push(
+                    pma.leftmost_find_iter_utf16(test.haystack, test.invalid)
+                        .collect(),
+                );
+            }
+            for matches in results {
+                assert_eq!(
+                    test.matches,
+                    match_triples(matches).as_slice(),
+                    "test: {}",
+                    test.name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn search_invalid_sequence_charwise_lossy() {
+    for test in LOSSY_INVALID {
+        for kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+            let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .build(test.patterns)
+                .unwrap();
+            let mut results = vec![];
+            if kind == MatchKind::Standard {
+                results.push(pma.find_iter_lossy(test.haystack, test.invalid).collect());
+                results.push(
+                    pma.find_overlapping_iter_lossy(test.haystack, test.invalid)
+                        .collect(),
+                );
+            } else {
+                results.push(
+                    pma.leftmost_find_iter_lossy(test.haystack, test.invalid)
+                        .collect(),
+                );
+            }
+            for matches in results {
+                assert_eq!(
+                    test.matches,
+                    match_triples(matches).as_slice(),
+                    "test: {}",
+                    test.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Match, MatchKind};
+
+fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+    it.map(|m| (m.start(), m.end(), m.value())).collect()
+}
+
+#[test]
+fn test_byte_classes_all_bytes() {
+    let patterns: Vec<_> = (u8::MIN..=u8::MAX).map(|c| vec![c, c]).collect();
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .byte_classes(true)
+        .build(&patterns)
+        .unwrap();
+    let haystack: Vec<_> = (u8::MIN..=u8::MAX).flat_map(|c| [c, c, c]).collect();
+    let expected: Vec<_> = (0..256)
+        .map(|c| (c * 3, c * 3 + 2, u32::try_from(c).unwrap()))
+        .collect();
+    assert_eq!(expected, triples(pma.find_iter(&haystack)));
+}
+
+#[test]
+fn test_byte_classes_serialize() {
+    let patterns = vec!["bcd", "ab", "a"];
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .byte_classes(true)
+        .build(&patterns)
+        .unwrap();
+    let mut bytes = pma.serialize();
+    bytes.push(42);
+    let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    assert_eq!(&[42], rest);
+    assert_eq!(pma, other);
+    assert_eq!(
+        vec![(0, 1, 2), (1, 4, 0)],
+        triples(other.find_iter("abcdx"))
+    );
+}
+
+#[test]
+fn test_byte_classes_serialize_255_bytes() {
+    // The classes of 255 bytes are a permutation of all the bytes, not the identity.
+    let mut patterns: Vec<Vec<u8>> = (0..0xff).map(|c| vec![c]).collect();
+    patterns.push(b"ba".to_vec());
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .byte_classes(true)
+        .build(&patterns)
+        .unwrap();
+    let bytes = pma.serialize();
+    let (other, rest) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    assert!(rest.is_empty());
+    assert_eq!(pma, other);
+    assert_eq!(
+        vec![(0, 1, 0x62), (0, 2, 0xff), (1, 2, 0x61), (2, 3, 0xfe)],
+        triples(other.find_overlapping_iter([b'b', b'a', 0xfe, 0xff]))
+    );
+}
+
+#[test]
+fn test_byte_classes_leftmost() {
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .byte_classes(true)
+        .build(["ab", "abcd", "xy"])
+        .unwrap();
+    // Bytes not in the patterns share a class that has no transition.
+    assert_eq!(
+        vec![(0, 2, 0), (5, 7, 2)],
+        triples(pma.leftmost_find_iter(b"abcd\xffxy"))
+    );
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use daachorse::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+#[test]
+fn test_count_out_of_range() {
+    let patvals = vec![("a", 1), ("b", u32::MAX)];
+    let pma = DoubleArrayAhoCorasick::with_values(patvals).unwrap();
+    let mut counts = vec![0; 2];
+    pma.count_overlapping_matches("abab", &mut counts);
+    assert_eq!(vec![0, 2], counts);
+
+    let mut counts = vec![];
+    pma.count_matches("abab", &mut counts);
+    assert!(counts.is_empty());
+}
+
+#[test]
+fn test_count_slice() {
+    // The matches are counted as reported by the iterator of each match kind.
+    let patterns = vec!["ab", "a", "abcd", "bc"];
+    let expected = [
+        (MatchKind::Standard, vec![0, 1, 0, 1], vec![1, 1, 1, 1]),
+        (
+            MatchKind::LeftmostLongest,
+            vec![0, 0, 1, 0],
+            vec![0, 0, 1, 0],
+        ),
+        (MatchKind::LeftmostFirst, vec![1, 0, 0, 0], vec![1, 0, 0, 0]),
+    ];
+    for (kind, non_overlapping, overlapping) in expected {
+        let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .build(&patterns)
+            .unwrap();
+        let mut counts = vec![0u64; 4];
+        pma.count_matches("abcd", &mut counts);
+        assert_eq!(non_overlapping, counts, "{kind:?}");
+        if kind == MatchKind::Standard {
+            let mut counts = vec![0u64; 4];
+            pma.count_overlapping_matches("abcd", &mut counts);
+            assert_eq!(overlapping, counts);
+        }
+    }
+}
+
+#[test]
+fn test_count_by_value() {
+    let patvals = vec![("ab", "x"), ("b", "y"), ("ab", "y")];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    let mut counts = BTreeMap::new();
+    pma.count_overlapping_matches("abab", &mut counts);
+    assert_eq!(
+        vec![("x", 2), ("y", 4)],
+        counts.into_iter().collect::<Vec<_>>()
+    );
+
+    let mut counts = BTreeMap::new();
+    pma.count_matches("abb", &mut counts);
+    assert_eq!(
+        vec![("x", 1), ("y", 2)],
+        counts.into_iter().collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_count_hash_map() {
+    use std::collections::HashMap;
+
+    use daachorse::CharwiseDoubleArrayAhoCorasick;
+
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["世界", "界"]).unwrap();
+    let mut counts = HashMap::new();
+    pma.count_overlapping_matches("世界の世界", &mut counts);
+    assert_eq!(Some(&2), counts.get(&0));
+    assert_eq!(Some(&2), counts.get(&1));
+}
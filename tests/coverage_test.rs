@@ -0,0 +1,48 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CoveredSpanIterator, DoubleArrayAhoCorasick,
+    ValuedCoveredSpanIterator,
+};
+
+#[test]
+fn test_covered_spans_streaming() {
+    // Spans are yielded before the whole haystack is searched.
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["ab", "b"]).unwrap();
+    let haystack = std::iter::repeat(&b"abxx"[..]);
+    let mut it = CoveredSpanIterator::new(pma.find_overlapping_iter_from_segments(haystack), 2);
+    assert_eq!(Some(0..2), it.next());
+    assert_eq!(Some(4..6), it.next());
+    let mut it = ValuedCoveredSpanIterator::new(
+        pma.find_overlapping_iter_from_segments(std::iter::repeat(&b"abxx"[..])),
+        2,
+    );
+    assert_eq!(Some((0..1, vec![0])), it.next());
+    assert_eq!(Some((1..2, vec![0, 1])), it.next());
+    assert_eq!(Some((4..5, vec![0])), it.next());
+}
+
+#[test]
+fn test_covered_spans_adjacent() {
+    // Adjacent spans are merged, but spans with a gap are not.
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["ab", "cd", "f"]).unwrap();
+    let spans: Vec<_> = pma.covered_spans("abcdxf").collect();
+    assert_eq!(vec![0..4, 5..6], spans);
+    let spans: Vec<_> = pma.covered_spans_with_values("abcdxf").collect();
+    assert_eq!(
+        vec![(0..2, vec![0]), (2..4, vec![1]), (5..6, vec![2])],
+        spans
+    );
+}
+
+#[test]
+fn test_covered_spans_nested() {
+    // A pattern inside another one splits the valued span but not the merged one.
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["全世界", "世"]).unwrap();
+    let spans: Vec<_> = pma.covered_spans("全世界中").collect();
+    assert_eq!(vec![0..9], spans);
+    let spans: Vec<_> = pma.covered_spans_with_values("全世界中").collect();
+    assert_eq!(
+        vec![(0..3, vec![0]), (3..6, vec![0, 1]), (6..9, vec![0])],
+        spans
+    );
+    assert_eq!(None, pma.covered_spans("中").next());
+}
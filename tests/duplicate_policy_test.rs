@@ -0,0 +1,208 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, Match,
+    MatchKind,
+};
+
+fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+    it.map(|m| (m.start(), m.end(), m.value())).collect()
+}
+
+#[test]
+fn test_keep_all_bytewise() {
+    let patvals = vec![
+        ("Paris", 0),
+        ("Paris", 1),
+        ("aris", 2),
+        ("is", 3),
+        ("Paris", 4),
+        ("is", 5),
+    ];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    let haystack = "Paris is";
+
+    assert_eq!(
+        vec![(0, 5, 0), (0, 5, 1), (0, 5, 4), (6, 8, 3), (6, 8, 5)],
+        triples(pma.find_iter(haystack))
+    );
+    assert_eq!(
+        vec![
+            (0, 5, 0),
+            (0, 5, 1),
+            (0, 5, 4),
+            (1, 5, 2),
+            (3, 5, 3),
+            (3, 5, 5),
+            (6, 8, 3),
+            (6, 8, 5),
+        ],
+        triples(pma.find_overlapping_iter(haystack))
+    );
+    assert_eq!(
+        vec![(0, 5, 0), (0, 5, 1), (0, 5, 4), (6, 8, 3), (6, 8, 5)],
+        triples(pma.find_overlapping_no_suffix_iter(haystack))
+    );
+    assert_eq!(
+        vec![(0, 5, 0), (0, 5, 1), (0, 5, 4), (6, 8, 3), (6, 8, 5)],
+        triples(pma.find_iter_from_iter(haystack.bytes()))
+    );
+}
+
+#[test]
+fn test_keep_all_leftmost() {
+    for kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+        let patvals = vec![("ab", 0), ("a", 1), ("ab", 2), ("b", 3), ("b", 4)];
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .duplicate_policy(DuplicatePolicy::KeepAll)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        assert_eq!(
+            vec![(0, 2, 0), (0, 2, 2), (2, 3, 3), (2, 3, 4)],
+            triples(pma.leftmost_find_iter("abb"))
+        );
+
+        let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .duplicate_policy(DuplicatePolicy::KeepAll)
+            .build_with_values(patvals)
+            .unwrap();
+        assert_eq!(
+            vec![(0, 2, 0), (0, 2, 2), (2, 3, 3), (2, 3, 4)],
+            triples(pma.leftmost_find_iter("abb"))
+        );
+    }
+}
+
+#[test]
+fn test_keep_all_charwise() {
+    let patvals = vec![("パリ", 0), ("リ", 1), ("パリ", 2)];
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    let haystack = "パリのリ";
+
+    assert_eq!(
+        vec![(0, 6, 0), (0, 6, 2), (9, 12, 1)],
+        triples(pma.find_iter(haystack))
+    );
+    assert_eq!(
+        vec![(0, 6, 0), (0, 6, 2), (3, 6, 1), (9, 12, 1)],
+        triples(pma.find_overlapping_iter(haystack))
+    );
+    assert_eq!(
+        vec![(0, 6, 0), (0, 6, 2), (9, 12, 1)],
+        triples(pma.find_overlapping_no_suffix_iter(haystack))
+    );
+
+    let utf16: Vec<u16> = haystack.encode_utf16().collect();
+    assert_eq!(
+        vec![(0, 2, 0), (0, 2, 2), (3, 4, 1)],
+        triples(pma.find_iter_utf16(&utf16, daachorse::charwise::InvalidSequence::Reset))
+    );
+}
+
+#[test]
+fn test_duplicate_error() {
+    let patvals = vec![("Paris", 0), ("Paris", 1)];
+    assert!(DoubleArrayAhoCorasickBuilder::new()
+        .build_with_values(patvals.clone())
+        .is_err());
+    assert!(CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::Error)
+        .build_with_values(patvals)
+        .is_err());
+}
+
+#[test]
+fn test_keep_first_and_last() {
+    let patvals = vec![("Paris", 0), ("is", 1), ("Paris", 2), ("Paris", 3)];
+    for (policy, value) in [
+        (DuplicatePolicy::KeepFirst, 0),
+        (DuplicatePolicy::KeepLast, 3),
+    ] {
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .duplicate_policy(policy)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        assert_eq!(
+            vec![(0, 5, value), (3, 5, 1)],
+            triples(pma.find_overlapping_iter("Paris"))
+        );
+
+        let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .duplicate_policy(policy)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        assert_eq!(
+            vec![(0, 5, value), (3, 5, 1)],
+            triples(pma.find_overlapping_iter("Paris"))
+        );
+    }
+}
+
+#[test]
+fn test_merged() {
+    let patvals = vec![
+        ("Paris", 1),
+        ("is", 10),
+        ("Paris", 2),
+        ("is", 20),
+        ("Paris", 3),
+    ];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::Error)
+        .build_with_values_merged(patvals.clone(), |a, b| a + b)
+        .unwrap();
+    assert_eq!(
+        vec![(0, 5, 6), (3, 5, 30)],
+        triples(pma.find_overlapping_iter("Paris"))
+    );
+
+    let mut calls = 0;
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .build_with_values_merged(patvals, |a, b| {
+            calls += 1;
+            a.max(b)
+        })
+        .unwrap();
+    assert_eq!(3, calls);
+    assert_eq!(
+        vec![(0, 5, 3), (3, 5, 20)],
+        triples(pma.find_overlapping_iter("Paris"))
+    );
+}
+
+#[test]
+fn test_duplicate_after_prefix_leftmost_first() {
+    // "ab" is registered again after its prefix "a", which shadows patterns registered later.
+    let patvals = vec![("ab", 1), ("a", 2), ("ab", 4), ("abc", 8)];
+    for policy in [DuplicatePolicy::KeepFirst, DuplicatePolicy::KeepLast] {
+        let pma = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .duplicate_policy(policy)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        let expected = if policy == DuplicatePolicy::KeepFirst {
+            1
+        } else {
+            4
+        };
+        assert_eq!(
+            vec![(0, 2, expected), (3, 4, 2)],
+            triples(pma.leftmost_find_iter("abca"))
+        );
+    }
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build_with_values_merged(patvals, |a, b| a | b)
+        .unwrap();
+    assert_eq!(
+        vec![(0, 2, 5), (3, 4, 2)],
+        triples(pma.leftmost_find_iter("abca"))
+    );
+}
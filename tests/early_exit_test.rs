@@ -0,0 +1,30 @@
+use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Match, MatchKind};
+
+fn triple(m: Option<Match<u32>>) -> Option<(usize, usize, u32)> {
+    m.map(|m| (m.start(), m.end(), m.value()))
+}
+
+#[test]
+fn test_leftmost_first_match() {
+    let patterns = vec!["ab", "a", "abcd", "bc"];
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(&patterns)
+        .unwrap();
+    assert!(pma.is_match("xbc"));
+    assert_eq!(Some((0, 2, 0)), triple(pma.find_first("abcd")));
+    assert_eq!(Some((1, 3, 3)), triple(pma.find_first("xbcd")));
+    assert_eq!(None, triple(pma.find_first("xyz")));
+    assert!(!pma.is_match(""));
+}
+
+#[test]
+fn test_find_earliest() {
+    // The match ending first is reported, even if a longer one starts earlier.
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["abcd", "bc", "c"]).unwrap();
+    assert_eq!(Some((1, 3, 1)), triple(pma.find_earliest("abcd")));
+    assert_eq!(Some((1, 3, 1)), triple(pma.find_first("abcd")));
+    assert!(pma.is_match("xxc"));
+    assert!(!pma.is_match("abd"));
+    assert_eq!(None, triple(pma.find_earliest("")));
+}
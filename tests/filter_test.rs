@@ -0,0 +1,90 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy, Match, MatchKind,
+};
+
+fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+    it.map(|m| (m.start(), m.end(), m.value())).collect()
+}
+
+#[test]
+fn test_filtered_leftmost_longest_fallback() {
+    // The shorter pattern is reported when the longer one is rejected.
+    let patterns = vec!["a", "ab", "bcd"];
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(&patterns)
+        .unwrap();
+    assert_eq!(
+        vec![(0, 2, 1)],
+        triples(pma.leftmost_find_iter_filtered("abcd", |_| true))
+    );
+    assert_eq!(
+        vec![(0, 1, 0), (1, 4, 2)],
+        triples(pma.leftmost_find_iter_filtered("abcd", |v| v != 1))
+    );
+    assert_eq!(
+        vec![(0, 2, 1)],
+        triples(pma.leftmost_find_iter_filtered("abcd", |v| v != 0))
+    );
+    assert_eq!(
+        Vec::<(usize, usize, u32)>::new(),
+        triples(pma.leftmost_find_iter_filtered("abcd", |_| false))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_filtered_leftmost_first() {
+    // "ab" is discarded in the construction, so it cannot be reported when "a" is rejected.
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(["a", "ab"])
+        .unwrap();
+    let _ = pma.leftmost_find_iter_filtered("ab", |v| v != 0);
+}
+
+#[test]
+#[should_panic]
+fn test_filtered_leftmost_shortest() {
+    let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build(["a", "ab"])
+        .unwrap();
+    let _ = pma.leftmost_find_iter_filtered("ab", |v| v != 0);
+}
+
+#[test]
+fn test_filtered_keep_all() {
+    let patvals = vec![("ab", 0), ("b", 1), ("ab", 2), ("ab", 3)];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals.clone())
+        .unwrap();
+    assert_eq!(
+        vec![(0, 2, 0), (0, 2, 3)],
+        triples(pma.find_iter_filtered("ab", |v| v != 1 && v != 2))
+    );
+    assert_eq!(
+        vec![(0, 2, 2), (1, 2, 1)],
+        triples(pma.find_overlapping_iter_filtered("ab", |v| v == 1 || v == 2))
+    );
+    assert_eq!(
+        vec![(1, 2, 1)],
+        triples(pma.find_iter_filtered("ab", |v| v == 1))
+    );
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals)
+        .unwrap();
+    assert_eq!(
+        vec![(0, 2, 2), (0, 2, 3)],
+        triples(pma.leftmost_find_iter_filtered("ab", |v| v >= 2))
+    );
+    assert_eq!(
+        vec![(1, 2, 1)],
+        triples(pma.leftmost_find_iter_filtered("ab", |v| v == 1))
+    );
+}
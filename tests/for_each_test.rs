@@ -0,0 +1,54 @@
+use core::ops::ControlFlow;
+
+use daachorse::{
+    DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+#[test]
+fn test_for_each_match_break_among_duplicates() {
+    let patvals = vec![("ab", 0), ("ab", 1), ("ab", 2), ("b", 3)];
+    for kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+        let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .duplicate_policy(DuplicatePolicy::KeepAll)
+            .build_with_values(patvals.clone())
+            .unwrap();
+        let mut values = vec![];
+        let result = pma.for_each_match("abab", |m| {
+            values.push(m.value());
+            if m.value() == 1 {
+                ControlFlow::Break(m.end())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(ControlFlow::Break(2), result);
+        assert_eq!(vec![0, 1], values);
+    }
+}
+
+#[test]
+fn test_for_each_match_continue() {
+    // The overlapping matches are visited under MatchKind::Standard, and the leftmost ones under
+    // the other match kinds.
+    let patterns = vec!["ab", "a", "abcd", "bc"];
+    let expected = [
+        (MatchKind::Standard, vec![(0, 1), (0, 2), (1, 3), (0, 4)]),
+        (MatchKind::LeftmostLongest, vec![(0, 4)]),
+        (MatchKind::LeftmostFirst, vec![(0, 2)]),
+        (MatchKind::LeftmostShortest, vec![(0, 1), (1, 3)]),
+    ];
+    for (kind, spans) in expected {
+        let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .build(&patterns)
+            .unwrap();
+        let mut visited = vec![];
+        let result: ControlFlow<()> = pma.for_each_match("abcd", |m| {
+            visited.push((m.start(), m.end()));
+            ControlFlow::Continue(())
+        });
+        assert_eq!(ControlFlow::Continue(()), result);
+        assert_eq!(spans, visited, "{kind:?}");
+    }
+}
@@ -0,0 +1,63 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder,
+    FuzzyMatch, MatchKind,
+};
+
+fn sorted(matches: Vec<FuzzyMatch<u32>>) -> Vec<(usize, usize, usize, u32)> {
+    let tuples: Vec<_> = matches
+        .iter()
+        .map(|m| (m.start(), m.end(), m.distance(), m.value()))
+        .collect();
+    // Matches are ordered by their starting and ending positions.
+    assert!(tuples
+        .windows(2)
+        .all(|w| (w[0].0, w[0].1) <= (w[1].0, w[1].1)));
+    let mut tuples = tuples;
+    tuples.sort_unstable();
+    tuples
+}
+
+#[test]
+fn test_find_fuzzy_exact() {
+    // With zero distance, the matches are those of find_overlapping_iter().
+    let patterns = vec!["abc", "bc", "c", "cab"];
+    let pma = DoubleArrayAhoCorasick::<u32>::new(&patterns).unwrap();
+    let mut expected: Vec<_> = pma
+        .find_overlapping_iter("abcabc")
+        .map(|m| (m.start(), m.end(), 0, m.value()))
+        .collect();
+    expected.sort_unstable();
+    assert_eq!(expected, sorted(pma.find_fuzzy("abcabc", 0)));
+}
+
+#[test]
+fn test_find_fuzzy_unknown_chars() {
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["全世界"]).unwrap();
+    assert_eq!(vec![(0, 7, 1, 0)], sorted(pma.find_fuzzy("全x界", 1)));
+    assert!(pma.find_fuzzy("xyz", 2).is_empty());
+}
+
+#[test]
+fn test_find_fuzzy_max_distance() {
+    // The bound on the substring lengths must not overflow.
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["ab", "c"]).unwrap();
+    assert_eq!(
+        vec![(0, 2, 1, 0), (0, 2, 1, 1), (1, 2, 0, 1), (1, 2, 2, 0)],
+        sorted(pma.find_fuzzy("ac", usize::MAX))
+    );
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["あい", "う"]).unwrap();
+    assert_eq!(
+        vec![(0, 6, 1, 0), (0, 6, 1, 1), (3, 6, 0, 1), (3, 6, 2, 0)],
+        sorted(pma.find_fuzzy("あう", usize::MAX))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_find_fuzzy_leftmost() {
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(["a"])
+        .unwrap();
+    let _ = pma.find_fuzzy("a", 1);
+}
@@ -0,0 +1,47 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, MatchKind,
+};
+
+#[test]
+fn test_matched_groups_all_found() {
+    let pma = DoubleArrayAhoCorasick::<usize>::new(["a", "b"]).unwrap();
+    let groups = pma.matched_groups("ccbcccaccc", 2, |v| v);
+    assert!(groups.is_full());
+
+    let groups = pma.matched_groups("ccbccc", 2, |v| v);
+    assert_eq!(vec![1], groups.iter().collect::<Vec<_>>());
+
+    let groups = pma.matched_groups("ab", 0, |v| v);
+    assert!(groups.is_empty());
+}
+
+#[test]
+fn test_matched_groups_leftmost() {
+    // "ab" is never reported under MatchKind::LeftmostFirst because "a" shadows it.
+    let patterns = vec!["a", "ab", "bc"];
+    let pattern_groups = [0, 1, 2];
+    for match_kind in [
+        MatchKind::LeftmostFirst,
+        MatchKind::LeftmostLongest,
+        MatchKind::LeftmostShortest,
+    ] {
+        let pma: DoubleArrayAhoCorasick<usize> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .build(&patterns)
+            .unwrap();
+        let charwise: CharwiseDoubleArrayAhoCorasick<usize> =
+            CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(match_kind)
+                .build(&patterns)
+                .unwrap();
+        let expected: Vec<_> = pma
+            .leftmost_find_iter("abc")
+            .map(|m| pattern_groups[m.value()])
+            .collect();
+        let groups = pma.matched_groups("abc", 3, |v| pattern_groups[v]);
+        assert_eq!(expected, groups.iter().collect::<Vec<_>>());
+        let groups = charwise.matched_groups("abc", 3, |v| pattern_groups[v]);
+        assert_eq!(expected, groups.iter().collect::<Vec<_>>());
+    }
+}
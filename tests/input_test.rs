@@ -0,0 +1,35 @@
+use daachorse::{CharwiseDoubleArrayAhoCorasick, DoubleArrayAhoCorasick, Input};
+
+#[test]
+fn test_input_context() {
+    let patterns = vec!["ab", "b", "bc"];
+    let pma = DoubleArrayAhoCorasick::<u32>::new(patterns).unwrap();
+
+    // "ab" crossing the start of the span is not reported.
+    let matches: Vec<_> = pma
+        .find_overlapping_iter(Input::new("abc").span(1..3))
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(1, 2, 1), (1, 3, 2)], matches);
+
+    // The iterator ends at the first position where no pattern starts.
+    let matches: Vec<_> = pma
+        .find_iter(Input::new("abbxb").anchored(true))
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 2, 0), (2, 3, 1)], matches);
+}
+
+#[test]
+#[should_panic]
+fn test_input_out_of_range() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["a"]).unwrap();
+    pma.find_iter(Input::new("abc").span(1..4));
+}
+
+#[test]
+#[should_panic]
+fn test_input_not_on_char_boundary() {
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["あ"]).unwrap();
+    pma.find_iter(Input::new("あい").span(1..6));
+}
@@ -1,3 +1,4 @@
+use daachorse::errors::DaachorseError;
 use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
 
 #[test]
@@ -86,3 +87,37 @@ fn test_duplicate_patterns_with_matchkind_standard() {
         .build(["abc", "123", "abc"]);
     assert!(pma.is_err());
 }
+
+#[test]
+fn test_error_accessors() {
+    match DoubleArrayAhoCorasick::<usize>::new([""]) {
+        Err(DaachorseError::InvalidArgument(e)) => {
+            assert_eq!(("pattern.len()", ">=", 1), (e.arg(), e.op(), e.value()));
+        }
+        _ => panic!("InvalidArgumentError is expected"),
+    }
+    match DoubleArrayAhoCorasick::<usize>::new(["abc", "abc"]) {
+        Err(DaachorseError::DuplicatePattern(e)) => {
+            assert_eq!("[97, 98, 99]", e.pattern());
+        }
+        _ => panic!("DuplicatePatternError is expected"),
+    }
+    match DoubleArrayAhoCorasick::<u8>::new(vec!["a"; 257]) {
+        Err(DaachorseError::InvalidConversion(e)) => {
+            assert_eq!(("index", "V"), (e.arg(), e.target()));
+        }
+        _ => panic!("InvalidConversionError is expected"),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_std_error() {
+    fn build() -> Result<DoubleArrayAhoCorasick<usize>, Box<dyn std::error::Error>> {
+        Ok(DoubleArrayAhoCorasick::new(["abc", "abc"])?)
+    }
+    let e = build().unwrap_err();
+    // The message of the inner error is printed only once in the error chain.
+    assert!(e.source().is_none());
+    assert_eq!("DuplicatePatternError: [97, 98, 99]", e.to_string());
+}
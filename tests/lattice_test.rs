@@ -0,0 +1,20 @@
+use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Lattice};
+
+#[test]
+fn test_build_lattice_edge_order() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["abc", "a", "ab", "b"]).unwrap();
+    let lattice = pma.build_lattice("abc");
+    let edges: Vec<_> = lattice.edges(0).iter().map(|m| m.end()).collect();
+    assert_eq!(vec![1, 2, 3], edges);
+    assert_eq!(4, lattice.num_edges());
+}
+
+#[test]
+#[should_panic]
+fn test_build_lattice_leftmost() {
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(daachorse::MatchKind::LeftmostLongest)
+        .build(["a"])
+        .unwrap();
+    let _: Lattice<u32> = pma.build_lattice("a");
+}
@@ -0,0 +1,108 @@
+use daachorse::errors::{DaachorseError, PatternProblemKind};
+
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+};
+
+#[test]
+fn test_leftmost_shortest() {
+    let patterns = vec!["abcd", "ab", "bcd", "b", "d"];
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build(&patterns)
+        .unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("abcdbcd")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 2, 1), (3, 4, 4), (4, 5, 3), (6, 7, 4)], matches);
+
+    let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build(&patterns)
+        .unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("abcdbcd")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 2, 1), (3, 4, 4), (4, 5, 3), (6, 7, 4)], matches);
+}
+
+#[test]
+fn test_leftmost_shortest_duplicates() {
+    let patvals = vec![("abc", 0), ("ab", 1), ("abc", 2), ("ab", 3)];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .duplicate_policy(DuplicatePolicy::KeepAll)
+        .build_with_values(patvals.clone())
+        .unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("abc")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 2, 1), (0, 2, 3)], matches);
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .duplicate_policy(DuplicatePolicy::KeepLast)
+        .build_with_values(patvals)
+        .unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("abc")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 2, 3)], matches);
+}
+
+#[test]
+fn test_leftmost_shortest_unreachable() {
+    let patterns = vec!["abc", "b", "ab", "a"];
+    let problems = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .validate(&patterns);
+    let problems: Vec<_> = problems.iter().map(|p| (p.index(), p.kind())).collect();
+    assert_eq!(
+        vec![
+            (0, PatternProblemKind::Unreachable { prefix: 3 }),
+            (2, PatternProblemKind::Unreachable { prefix: 3 }),
+        ],
+        problems
+    );
+
+    let patvals = patterns.iter().zip(0u32..);
+    let (_, report) = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build_with_values_and_report(patvals.clone())
+        .unwrap();
+    let report: Vec<_> = report.iter().map(|p| (p.index(), p.kind())).collect();
+    assert_eq!(problems, report);
+
+    let result = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .reject_unreachable(true)
+        .build_with_values(patvals);
+    match result {
+        Err(DaachorseError::UnreachablePattern(e)) => {
+            assert_eq!((2, 3), (e.index(), e.prefix()));
+        }
+        _ => panic!("UnreachablePatternError is expected"),
+    }
+}
+
+#[test]
+fn test_leftmost_shortest_serialize() {
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build(["ab", "a"])
+        .unwrap();
+    let bytes = pma.serialize();
+    let (other, _) = unsafe { DoubleArrayAhoCorasick::<u32>::deserialize_unchecked(&bytes) };
+    assert_eq!(
+        vec![(0, 1, 1)],
+        other
+            .leftmost_find_iter("ab")
+            .map(|m| (m.start(), m.end(), m.value()))
+            .collect::<Vec<_>>()
+    );
+}
@@ -1,3 +1,4 @@
+use daachorse::charwise::InvalidSequence;
 use daachorse::{CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, MatchKind};
 
 #[test]
@@ -69,3 +70,63 @@ fn test_leftmost_find_iter_with_standard() {
         .unwrap();
     pma.leftmost_find_iter("");
 }
+
+#[test]
+#[should_panic]
+fn test_find_iter_utf16_with_leftmost_longest() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(["pattern"])
+        .unwrap();
+    pma.find_iter_utf16([], InvalidSequence::Reset);
+}
+
+#[test]
+#[should_panic]
+fn test_find_overlapping_iter_utf16_with_leftmost_first() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(["pattern"])
+        .unwrap();
+    pma.find_overlapping_iter_utf16([], InvalidSequence::Reset);
+}
+
+#[test]
+#[should_panic]
+fn test_leftmost_find_iter_utf16_with_standard() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    pma.leftmost_find_iter_utf16([], InvalidSequence::Reset);
+}
+
+#[test]
+#[should_panic]
+fn test_find_iter_lossy_with_leftmost_first() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(["pattern"])
+        .unwrap();
+    pma.find_iter_lossy(b"", InvalidSequence::Reset);
+}
+
+#[test]
+#[should_panic]
+fn test_find_overlapping_no_suffix_iter_lossy_with_leftmost_longest() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(["pattern"])
+        .unwrap();
+    pma.find_overlapping_no_suffix_iter_lossy(b"", InvalidSequence::Reset);
+}
+
+#[test]
+#[should_panic]
+fn test_leftmost_find_iter_lossy_with_standard() {
+    let pma: CharwiseDoubleArrayAhoCorasick<usize> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build(["pattern"])
+        .unwrap();
+    pma.leftmost_find_iter_lossy(b"", InvalidSequence::Reset);
+}
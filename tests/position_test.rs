@@ -0,0 +1,85 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    MatchKind, PositionIterator, PositionedMatch,
+};
+
+type Positions = Vec<(
+    (usize, usize, usize, usize),
+    (usize, usize, usize, usize),
+    u32,
+)>;
+
+/// Returns (byte, char, line, column) of the start and end of each match.
+fn positions(matches: impl Iterator<Item = PositionedMatch<u32>>) -> Positions {
+    matches
+        .map(|m| {
+            let (s, e) = (m.start(), m.end());
+            (
+                (s.byte(), s.char(), s.line(), s.column()),
+                (e.byte(), e.char(), e.line(), e.column()),
+                m.value(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_positions_multibyte_lines() {
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["b", "い"]).unwrap();
+    assert_eq!(
+        vec![
+            ((1, 1, 0, 1), (2, 2, 0, 2), 0),
+            ((6, 4, 1, 1), (9, 5, 1, 2), 1),
+            ((9, 5, 1, 2), (10, 6, 1, 3), 0),
+            ((12, 8, 3, 0), (13, 9, 3, 1), 0),
+        ],
+        positions(pma.find_iter_with_positions("ab\nあいb\n\nb"))
+    );
+}
+
+#[test]
+fn test_positions_start_before_previous() {
+    // "cdef" starts before "de" on the same line, and "b\ncdef" on the previous line.
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["de", "cdef", "b\ncdef"]).unwrap();
+    assert_eq!(
+        vec![
+            ((4, 4, 1, 1), (6, 6, 1, 3), 0),
+            ((1, 1, 0, 1), (7, 7, 1, 4), 2),
+            ((3, 3, 1, 0), (7, 7, 1, 4), 1),
+        ],
+        positions(pma.find_overlapping_iter_with_positions("ab\ncdef"))
+    );
+}
+
+#[test]
+fn test_positions_leftmost() {
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build::<_, _, u32>(["世界", "世界\n全"])
+        .unwrap();
+    assert_eq!(
+        vec![
+            ((0, 0, 0, 0), (10, 4, 1, 1), 1),
+            ((10, 4, 1, 1), (16, 6, 1, 3), 0),
+        ],
+        positions(pma.leftmost_find_iter_with_positions("世界\n全世界"))
+    );
+}
+
+#[test]
+fn test_positions_bytewise() {
+    let haystack = "é\n\né";
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["\n", "é"]).unwrap();
+    assert_eq!(
+        vec![
+            ((0, 0, 0, 0), (2, 1, 0, 1), 1),
+            ((2, 1, 0, 1), (3, 2, 1, 0), 0),
+            ((3, 2, 1, 0), (4, 3, 2, 0), 0),
+            ((4, 3, 2, 0), (6, 4, 2, 1), 1),
+        ],
+        positions(PositionIterator::new(
+            haystack,
+            pma.find_overlapping_iter(haystack)
+        ))
+    );
+}
@@ -0,0 +1,32 @@
+use daachorse::errors::DaachorseError;
+
+use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind};
+
+#[test]
+fn test_same_priorities() {
+    // Equivalent to LeftmostLongest if all the priorities are the same.
+    let patvalpris = vec![("ab", 0, 7), ("a", 1, 7), ("abcd", 2, 7)];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::Standard)
+        .build_with_priorities(patvalpris)
+        .unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("abcdab")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 4, 2), (4, 6, 0)], matches);
+}
+
+#[test]
+fn test_priorities_reject_unreachable() {
+    let patvalpris = vec![("abc", 0, 1), ("a", 1, 1), ("ab", 2, 2)];
+    let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+        .reject_unreachable(true)
+        .build_with_priorities(patvalpris);
+    match result {
+        Err(DaachorseError::UnreachablePattern(e)) => {
+            assert_eq!((0, 2), (e.index(), e.prefix()));
+        }
+        _ => panic!("UnreachablePatternError is expected"),
+    }
+}
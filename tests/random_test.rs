@@ -0,0 +1,1793 @@
+//! Randomized tests comparing the automata with naive implementations.
+//!
+//! Each module checks a feature against a naive search over random patterns and haystacks. Tests
+//! of specific cases are in the test file of each feature.
+
+/// Xorshift pseudo-random number generator for reproducible random tests.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Generates a non-empty byte string of at most `max_len` bytes drawn from `alphabet`.
+    fn gen_bytes(&mut self, alphabet: &[u8], max_len: u64) -> Vec<u8> {
+        let len = 1 + self.next() % max_len;
+        (0..len)
+            .map(|_| alphabet[(self.next() % alphabet.len() as u64) as usize])
+            .collect()
+    }
+
+    /// Generates a non-empty string of at most `max_len` characters drawn from `alphabet`.
+    fn gen_string(&mut self, alphabet: &[char], max_len: u64) -> String {
+        let len = 1 + self.next() % max_len;
+        (0..len)
+            .map(|_| alphabet[(self.next() % alphabet.len() as u64) as usize])
+            .collect()
+    }
+
+    /// Returns a random character boundary of the haystack, including both ends.
+    fn gen_boundary(&mut self, haystack: &str) -> usize {
+        let boundaries: Vec<_> = (0..=haystack.len())
+            .filter(|&i| haystack.is_char_boundary(i))
+            .collect();
+        boundaries[usize::try_from(self.next()).unwrap() % boundaries.len()]
+    }
+
+    /// Splits the bytes at random positions, possibly in the middle of characters and into
+    /// empty segments.
+    fn split<'a>(&mut self, bytes: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut segments = vec![];
+        let mut start = 0;
+        while start < bytes.len() {
+            let len = usize::try_from(self.next() % 4)
+                .unwrap()
+                .min(bytes.len() - start);
+            segments.push(&bytes[start..start + len]);
+            start += len;
+        }
+        segments
+    }
+}
+
+mod byte_classes {
+    use daachorse::{DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Match, MatchKind};
+
+    use super::XorShift;
+
+    fn gen_patterns(rng: &mut XorShift, alphabet: &[u8]) -> Vec<Vec<u8>> {
+        let mut patterns = vec![];
+        while patterns.len() < 100 {
+            let pattern = rng.gen_bytes(alphabet, 8);
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+        patterns
+    }
+
+    fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+        it.map(|m| (m.start(), m.end(), m.value())).collect()
+    }
+
+    #[test]
+    fn test_byte_classes_standard() {
+        let mut rng = XorShift(0x1234_5678);
+        for alphabet in [&b"ab"[..], b"abcdefg", b"\x00\x01\x7f\x80\xfe\xff"] {
+            let patterns = gen_patterns(&mut rng, alphabet);
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasick::new(&patterns).unwrap();
+            let pma_classes: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .byte_classes(true)
+                .build(&patterns)
+                .unwrap();
+            assert!(pma_classes.heap_bytes() < pma.heap_bytes());
+
+            for _ in 0..100 {
+                // Includes bytes not appearing in patterns.
+                let haystack = rng.gen_bytes(b"abcdefgxyz\x00\x01\x7f\x80\xfe\xff", 100);
+                assert_eq!(
+                    triples(pma.find_iter(&haystack)),
+                    triples(pma_classes.find_iter(&haystack)),
+                );
+                assert_eq!(
+                    triples(pma.find_overlapping_iter(&haystack)),
+                    triples(pma_classes.find_overlapping_iter(&haystack)),
+                );
+                assert_eq!(
+                    triples(pma.find_overlapping_no_suffix_iter(&haystack)),
+                    triples(pma_classes.find_overlapping_no_suffix_iter(&haystack)),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_byte_classes_leftmost() {
+        let mut rng = XorShift(0x9abc_def0);
+        for kind in [MatchKind::LeftmostLongest, MatchKind::LeftmostFirst] {
+            let patterns = gen_patterns(&mut rng, b"abcd");
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .build(&patterns)
+                .unwrap();
+            let pma_classes: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .byte_classes(true)
+                .build(&patterns)
+                .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_bytes(b"abcdez", 100);
+                assert_eq!(
+                    triples(pma.leftmost_find_iter(&haystack)),
+                    triples(pma_classes.leftmost_find_iter(&haystack)),
+                );
+            }
+        }
+    }
+}
+
+mod count {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind,
+    };
+
+    use super::XorShift;
+
+    fn count(values: impl Iterator<Item = u32>, counts: &mut [u64]) {
+        for v in values {
+            counts[usize::try_from(v).unwrap()] += 1;
+        }
+    }
+
+    #[test]
+    fn test_count_random() {
+        let mut rng = XorShift(0x7777_1111);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 30 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+            let haystacks: Vec<_> = (0..20).map(|_| rng.gen_string(alphabet, 50)).collect();
+
+            for kind in [
+                MatchKind::Standard,
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostFirst,
+                MatchKind::LeftmostShortest,
+            ] {
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .build(&patterns)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build(&patterns)
+                        .unwrap();
+
+                let mut expected = vec![0; patterns.len()];
+                let mut counts = vec![0; patterns.len()];
+                let mut charwise_counts = vec![0; patterns.len()];
+                for haystack in &haystacks {
+                    if kind == MatchKind::Standard {
+                        count(pma.find_iter(haystack).map(|m| m.value()), &mut expected);
+                    } else {
+                        count(
+                            pma.leftmost_find_iter(haystack).map(|m| m.value()),
+                            &mut expected,
+                        );
+                    }
+                    pma.count_matches(haystack, &mut counts);
+                    charwise.count_matches(haystack, &mut charwise_counts);
+                }
+                assert_eq!(expected, counts);
+                assert_eq!(expected, charwise_counts);
+
+                if kind == MatchKind::Standard {
+                    let mut expected = vec![0; patterns.len()];
+                    let mut counts = vec![0; patterns.len()];
+                    let mut charwise_counts = vec![0; patterns.len()];
+                    for haystack in &haystacks {
+                        count(
+                            pma.find_overlapping_iter(haystack).map(|m| m.value()),
+                            &mut expected,
+                        );
+                        pma.count_overlapping_matches(haystack, &mut counts);
+                        charwise.count_overlapping_matches(haystack, &mut charwise_counts);
+                    }
+                    assert_eq!(expected, counts);
+                    assert_eq!(expected, charwise_counts);
+                }
+            }
+        }
+    }
+}
+
+mod coverage {
+    use std::ops::Range;
+
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CoveredSpanIterator, DoubleArrayAhoCorasick,
+        DoubleArrayAhoCorasickBuilder, DuplicatePolicy, ValuedCoveredSpanIterator,
+    };
+
+    use super::XorShift;
+
+    /// Returns the spans with the same non-empty sets of values, computed byte by byte.
+    fn naive_spans(patvals: &[(String, u32)], haystack: &str) -> Vec<(Range<usize>, Vec<u32>)> {
+        let mut sets = vec![vec![]; haystack.len()];
+        for (pattern, value) in patvals {
+            for start in 0..haystack.len() {
+                if haystack.as_bytes()[start..].starts_with(pattern.as_bytes()) {
+                    for set in &mut sets[start..start + pattern.len()] {
+                        set.push(*value);
+                    }
+                }
+            }
+        }
+        let mut spans: Vec<(Range<usize>, Vec<u32>)> = vec![];
+        for (pos, mut set) in sets.into_iter().enumerate() {
+            set.sort_unstable();
+            set.dedup();
+            if set.is_empty() {
+                continue;
+            }
+            match spans.last_mut() {
+                Some((range, last)) if range.end == pos && *last == set => range.end += 1,
+                _ => spans.push((pos..pos + 1, set)),
+            }
+        }
+        spans
+    }
+
+    fn merge(spans: &[(Range<usize>, Vec<u32>)]) -> Vec<Range<usize>> {
+        let mut merged: Vec<Range<usize>> = vec![];
+        for (range, _) in spans {
+            match merged.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => merged.push(range.clone()),
+            }
+        }
+        merged
+    }
+
+    #[test]
+    fn test_covered_spans_random() {
+        let mut rng = XorShift(0x5772_1566);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patvals: Vec<(String, u32)> = vec![];
+            while patvals.len() < 10 {
+                let pattern = rng.gen_string(alphabet, 5);
+                if patvals.iter().all(|(p, _)| *p != pattern) {
+                    // Values are shared by patterns and patterns may have multiple values.
+                    for _ in 0..=rng.next() % 2 {
+                        patvals.push((pattern.clone(), u32::try_from(rng.next() % 6).unwrap()));
+                    }
+                }
+            }
+            let max_length = patvals.iter().map(|(p, _)| p.len()).max().unwrap();
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_values(patvals.clone())
+                .unwrap();
+            let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                daachorse::CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_values(patvals.clone())
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 60);
+                let expected = naive_spans(&patvals, &haystack);
+                let expected_merged = merge(&expected);
+
+                assert_eq!(
+                    expected,
+                    pma.covered_spans_with_values(&haystack).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    expected,
+                    charwise
+                        .covered_spans_with_values(&haystack)
+                        .collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    expected_merged,
+                    pma.covered_spans(&haystack).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    expected_merged,
+                    charwise.covered_spans(&haystack).collect::<Vec<_>>()
+                );
+
+                // Streams over segments with the explicit bound of the match lengths.
+                let bytes = haystack.as_bytes();
+                let segments = bytes.chunks(3);
+                assert_eq!(
+                    expected,
+                    ValuedCoveredSpanIterator::new(
+                        pma.find_overlapping_iter_from_segments(segments.clone()),
+                        max_length
+                    )
+                    .collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    expected_merged,
+                    CoveredSpanIterator::new(
+                        pma.find_overlapping_iter_from_segments(segments),
+                        max_length
+                    )
+                    .collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+}
+
+mod duplicate_policy {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+        DoubleArrayAhoCorasickBuilder, DuplicatePolicy, Match, MatchKind,
+    };
+
+    use super::XorShift;
+
+    fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+        it.map(|m| (m.start(), m.end(), m.value())).collect()
+    }
+
+    // Expands each match of the automaton built from unique patterns into the matches of all
+    // values.
+    fn expand(
+        it: impl Iterator<Item = Match<u32>>,
+        values: &[Vec<u32>],
+    ) -> Vec<(usize, usize, u32)> {
+        let mut expanded = vec![];
+        for m in it {
+            for &v in &values[usize::try_from(m.value()).unwrap()] {
+                expanded.push((m.start(), m.end(), v));
+            }
+        }
+        expanded
+    }
+
+    #[test]
+    fn test_keep_all_random() {
+        let mut rng = XorShift(0x0123_4567);
+        for kind in [
+            MatchKind::Standard,
+            MatchKind::LeftmostLongest,
+            MatchKind::LeftmostFirst,
+        ] {
+            // Registers each pattern once, and then some of them repeatedly in a random order.
+            let mut unique: Vec<Vec<u8>> = vec![];
+            while unique.len() < 50 {
+                let pattern = rng.gen_bytes(b"abc", 5);
+                if !unique.contains(&pattern) {
+                    unique.push(pattern);
+                }
+            }
+            let mut values = vec![vec![]; unique.len()];
+            let mut patvals = vec![];
+            for v in 0..100 {
+                let i = if v < unique.len() {
+                    v
+                } else {
+                    usize::try_from(rng.next() % 50).unwrap()
+                };
+                let v = u32::try_from(v).unwrap();
+                values[i].push(v);
+                patvals.push((unique[i].clone(), v));
+            }
+
+            let reference: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .build(&unique)
+                .unwrap();
+            let pma = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_values(patvals.clone())
+                .unwrap();
+            let patvals: Vec<_> = patvals
+                .into_iter()
+                .map(|(p, v)| (String::from_utf8(p).unwrap(), v))
+                .collect();
+            let charwise = CharwiseDoubleArrayAhoCorasickBuilder::new()
+                .match_kind(kind)
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_values(patvals)
+                .unwrap();
+
+            for _ in 0..50 {
+                let haystack = String::from_utf8(rng.gen_bytes(b"abcd", 50)).unwrap();
+                if kind == MatchKind::Standard {
+                    let expected = expand(reference.find_iter(&haystack), &values);
+                    assert_eq!(expected, triples(pma.find_iter(&haystack)));
+                    assert_eq!(expected, triples(charwise.find_iter(&haystack)));
+
+                    let expected = expand(reference.find_overlapping_iter(&haystack), &values);
+                    assert_eq!(expected, triples(pma.find_overlapping_iter(&haystack)));
+                    assert_eq!(expected, triples(charwise.find_overlapping_iter(&haystack)));
+
+                    let expected = expand(
+                        reference.find_overlapping_no_suffix_iter(&haystack),
+                        &values,
+                    );
+                    assert_eq!(
+                        expected,
+                        triples(pma.find_overlapping_no_suffix_iter(&haystack))
+                    );
+                    assert_eq!(
+                        expected,
+                        triples(charwise.find_overlapping_no_suffix_iter(&haystack))
+                    );
+                } else {
+                    let expected = expand(reference.leftmost_find_iter(&haystack), &values);
+                    assert_eq!(expected, triples(pma.leftmost_find_iter(&haystack)));
+                    assert_eq!(expected, triples(charwise.leftmost_find_iter(&haystack)));
+                }
+            }
+        }
+    }
+}
+
+mod early_exit {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Match, MatchKind,
+    };
+
+    use super::XorShift;
+
+    fn triple(m: Option<Match<u32>>) -> Option<(usize, usize, u32)> {
+        m.map(|m| (m.start(), m.end(), m.value()))
+    }
+
+    #[test]
+    fn test_early_exit_random() {
+        let mut rng = XorShift(0x0bad_cafe);
+        for alphabet in [&['a', 'b', 'c', 'd'][..], &['あ', 'い', 'b', 'c']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 20 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+            let standard: DoubleArrayAhoCorasick<u32> =
+                DoubleArrayAhoCorasick::new(&patterns).unwrap();
+
+            for kind in [
+                MatchKind::Standard,
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostFirst,
+                MatchKind::LeftmostShortest,
+            ] {
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .build(&patterns)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build(&patterns)
+                        .unwrap();
+
+                for _ in 0..100 {
+                    let haystack = rng.gen_string(alphabet, 12);
+                    let earliest = triple(standard.find_iter(&haystack).next());
+                    let first = if kind == MatchKind::Standard {
+                        earliest
+                    } else {
+                        triple(pma.leftmost_find_iter(&haystack).next())
+                    };
+
+                    assert_eq!(earliest.is_some(), pma.is_match(&haystack));
+                    assert_eq!(earliest.is_some(), charwise.is_match(&haystack));
+                    assert_eq!(earliest, triple(pma.find_earliest(&haystack)));
+                    assert_eq!(earliest, triple(charwise.find_earliest(&haystack)));
+                    assert_eq!(first, triple(pma.find_first(&haystack)));
+                    assert_eq!(first, triple(charwise.find_first(&haystack)));
+                }
+            }
+        }
+    }
+}
+
+mod filter {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Match, MatchKind,
+    };
+
+    use super::XorShift;
+
+    fn triples(it: impl Iterator<Item = Match<u32>>) -> Vec<(usize, usize, u32)> {
+        it.map(|m| (m.start(), m.end(), m.value())).collect()
+    }
+
+    #[test]
+    fn test_filtered_random() {
+        let mut rng = XorShift(0x2468_ace0);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 50 {
+                let pattern = rng.gen_string(alphabet, 5);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+            let enabled: Vec<bool> = (0..patterns.len()).map(|_| rng.next() % 3 != 0).collect();
+            let filter = |v: u32| enabled[usize::try_from(v).unwrap()];
+            let subset: Vec<_> = patterns
+                .iter()
+                .zip(0u32..)
+                .filter(|&(_, v)| filter(v))
+                .collect();
+
+            for kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+                let expected_pma: DoubleArrayAhoCorasick<u32> =
+                    DoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build_with_values(subset.clone())
+                        .unwrap();
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .build(&patterns)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build(&patterns)
+                        .unwrap();
+
+                for _ in 0..50 {
+                    let haystack = rng.gen_string(alphabet, 50);
+                    if kind == MatchKind::Standard {
+                        let expected = triples(expected_pma.find_iter(&haystack));
+                        assert_eq!(expected, triples(pma.find_iter_filtered(&haystack, filter)));
+                        assert_eq!(
+                            expected,
+                            triples(charwise.find_iter_filtered(&haystack, filter))
+                        );
+
+                        let expected = triples(expected_pma.find_overlapping_iter(&haystack));
+                        assert_eq!(
+                            expected,
+                            triples(pma.find_overlapping_iter_filtered(&haystack, filter))
+                        );
+                        assert_eq!(
+                            expected,
+                            triples(charwise.find_overlapping_iter_filtered(&haystack, filter))
+                        );
+                    } else {
+                        let expected = triples(expected_pma.leftmost_find_iter(&haystack));
+                        assert_eq!(
+                            expected,
+                            triples(pma.leftmost_find_iter_filtered(&haystack, filter))
+                        );
+                        assert_eq!(
+                            expected,
+                            triples(charwise.leftmost_find_iter_filtered(&haystack, filter))
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+mod for_each {
+    use core::ops::ControlFlow;
+
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind,
+    };
+
+    use super::XorShift;
+
+    #[test]
+    fn test_for_each_match_random() {
+        let mut rng = XorShift(0x2468_ace0);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 30 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+
+            for kind in [
+                MatchKind::Standard,
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostFirst,
+                MatchKind::LeftmostShortest,
+            ] {
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .build(&patterns)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build(&patterns)
+                        .unwrap();
+
+                for _ in 0..50 {
+                    let haystack = rng.gen_string(alphabet, 30);
+                    let expected: Vec<_> = if kind == MatchKind::Standard {
+                        pma.find_overlapping_iter(&haystack)
+                            .map(|m| (m.start(), m.end(), m.value()))
+                            .collect()
+                    } else {
+                        pma.leftmost_find_iter(&haystack)
+                            .map(|m| (m.start(), m.end(), m.value()))
+                            .collect()
+                    };
+
+                    let mut matches = vec![];
+                    let result = pma.for_each_match(&haystack, |m| {
+                        matches.push((m.start(), m.end(), m.value()));
+                        ControlFlow::<()>::Continue(())
+                    });
+                    assert_eq!(ControlFlow::Continue(()), result);
+                    assert_eq!(expected, matches);
+
+                    let mut matches = vec![];
+                    let result = charwise.for_each_match(&haystack, |m| {
+                        matches.push((m.start(), m.end(), m.value()));
+                        ControlFlow::<()>::Continue(())
+                    });
+                    assert_eq!(ControlFlow::Continue(()), result);
+                    assert_eq!(expected, matches);
+
+                    // Stops at the k-th match.
+                    let k = usize::try_from(rng.next() % 4).unwrap();
+                    let mut count = 0;
+                    let result = pma.for_each_match(&haystack, |m| {
+                        count += 1;
+                        if count > k {
+                            ControlFlow::Break((m.start(), m.end(), m.value()))
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    });
+                    match expected.get(k) {
+                        Some(&m) => assert_eq!(ControlFlow::Break(m), result),
+                        None => assert_eq!(ControlFlow::Continue(()), result),
+                    }
+                    assert_eq!(expected.len().min(k + 1), count);
+                }
+            }
+        }
+    }
+}
+
+mod fuzzy {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, FuzzyMatch,
+    };
+
+    use super::XorShift;
+
+    fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, x) in a.iter().enumerate() {
+            let mut prev = row[0];
+            row[0] = i + 1;
+            for (j, y) in b.iter().enumerate() {
+                let d = (prev + usize::from(x != y))
+                    .min(row[j] + 1)
+                    .min(row[j + 1] + 1);
+                prev = row[j + 1];
+                row[j + 1] = d;
+            }
+        }
+        row[b.len()]
+    }
+
+    /// Returns (start, end, distance, value) of the naive approximate matches over the units,
+    /// where `offsets` maps unit positions to byte positions.
+    fn naive_fuzzy<T: PartialEq>(
+        patvals: &[(Vec<T>, u32)],
+        units: &[T],
+        offsets: &[usize],
+        max_distance: usize,
+    ) -> Vec<(usize, usize, usize, u32)> {
+        let mut matches = vec![];
+        for start in 0..units.len() {
+            for (pattern, value) in patvals {
+                let mut best: Option<(usize, usize)> = None;
+                for end in start + 1..=units.len() {
+                    let d = levenshtein(pattern, &units[start..end]);
+                    if d <= max_distance && best.map_or(true, |(_, best_d)| d <= best_d) {
+                        best = Some((end, d));
+                    }
+                }
+                if let Some((end, d)) = best {
+                    matches.push((offsets[start], offsets[end], d, *value));
+                }
+            }
+        }
+        matches.sort_unstable();
+        matches
+    }
+
+    fn sorted(matches: Vec<FuzzyMatch<u32>>) -> Vec<(usize, usize, usize, u32)> {
+        let tuples: Vec<_> = matches
+            .iter()
+            .map(|m| (m.start(), m.end(), m.distance(), m.value()))
+            .collect();
+        // Matches are ordered by their starting and ending positions.
+        assert!(tuples
+            .windows(2)
+            .all(|w| (w[0].0, w[0].1) <= (w[1].0, w[1].1)));
+        let mut tuples = tuples;
+        tuples.sort_unstable();
+        tuples
+    }
+
+    #[test]
+    fn test_find_fuzzy_random() {
+        let mut rng = XorShift(0x1f2e_3d4c);
+        for alphabet in [&['a', 'b', 'c', 'd'][..], &['あ', 'い', 'b', 'c']] {
+            for _ in 0..10 {
+                let mut patvals: Vec<(String, u32)> = vec![];
+                while patvals.len() < 8 {
+                    let pattern = rng.gen_string(alphabet, 6);
+                    if patvals.iter().all(|(p, _)| *p != pattern) {
+                        for _ in 0..=rng.next() % 2 {
+                            patvals.push((pattern.clone(), u32::try_from(rng.next() % 6).unwrap()));
+                        }
+                    }
+                }
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_values(patvals.clone())
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .duplicate_policy(DuplicatePolicy::KeepAll)
+                        .build_with_values(patvals.clone())
+                        .unwrap();
+                let byte_patvals: Vec<_> = patvals
+                    .iter()
+                    .map(|(p, v)| (p.as_bytes().to_vec(), *v))
+                    .collect();
+                let char_patvals: Vec<_> = patvals
+                    .iter()
+                    .map(|(p, v)| (p.chars().collect::<Vec<_>>(), *v))
+                    .collect();
+
+                for _ in 0..10 {
+                    let haystack = rng.gen_string(alphabet, 20);
+                    let byte_offsets: Vec<usize> = (0..=haystack.len()).collect();
+                    let chars: Vec<char> = haystack.chars().collect();
+                    let char_offsets: Vec<usize> = haystack
+                        .char_indices()
+                        .map(|(i, _)| i)
+                        .chain([haystack.len()])
+                        .collect();
+                    for max_distance in 0..=2 {
+                        assert_eq!(
+                            naive_fuzzy(
+                                &byte_patvals,
+                                haystack.as_bytes(),
+                                &byte_offsets,
+                                max_distance
+                            ),
+                            sorted(pma.find_fuzzy(&haystack, max_distance)),
+                        );
+                        assert_eq!(
+                            naive_fuzzy(&char_patvals, &chars, &char_offsets, max_distance),
+                            sorted(charwise.find_fuzzy(&haystack, max_distance)),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+mod group {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, GroupSet,
+    };
+
+    use super::XorShift;
+
+    #[test]
+    fn test_matched_groups_random() {
+        let mut rng = XorShift(0x1357_9bdf);
+        for alphabet in [&['a', 'b', 'c', 'd'][..], &['あ', 'い', 'b', 'c']] {
+            // Each pattern belongs to one or two of 70 groups, where the i-th value is mapped to
+            // value_groups[i].
+            let mut patgroups: Vec<(String, u32)> = vec![];
+            let mut value_groups = vec![];
+            while patgroups.len() < 100 {
+                let pattern = rng.gen_string(alphabet, 6);
+                if patgroups.iter().all(|(p, _)| *p != pattern) {
+                    for _ in 0..=rng.next() % 2 {
+                        let value = u32::try_from(value_groups.len()).unwrap();
+                        value_groups.push(usize::try_from(rng.next() % 70).unwrap());
+                        patgroups.push((pattern.clone(), value));
+                    }
+                }
+            }
+            let group_of = |v: u32| value_groups[usize::try_from(v).unwrap()];
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_values(patgroups.clone())
+                .unwrap();
+            let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_values(patgroups)
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 30);
+                for num_groups in [70, 10] {
+                    let mut expected = GroupSet::new(num_groups);
+                    for m in pma.find_overlapping_iter(&haystack) {
+                        let group = group_of(m.value());
+                        if group < num_groups {
+                            expected.insert(group);
+                        }
+                    }
+                    assert_eq!(
+                        expected,
+                        pma.matched_groups(&haystack, num_groups, group_of)
+                    );
+                    assert_eq!(
+                        expected,
+                        charwise.matched_groups(&haystack, num_groups, group_of)
+                    );
+                }
+            }
+        }
+    }
+}
+
+mod input {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, Input, MatchKind,
+    };
+
+    use super::XorShift;
+
+    /// Returns the anchored non-overlapping matches, each of which starts where the previous one
+    /// ends, choosing the index of one of the patterns matching at each position by `choose`.
+    fn naive_anchored<F>(
+        patterns: &[String],
+        haystack: &str,
+        mut pos: usize,
+        end: usize,
+        choose: F,
+    ) -> Vec<(usize, usize, u32)>
+    where
+        F: Fn(&[(usize, &String)]) -> usize,
+    {
+        let mut matches = vec![];
+        loop {
+            let candidates: Vec<_> = patterns
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| haystack[pos..end].starts_with(p.as_str()))
+                .collect();
+            if candidates.is_empty() {
+                return matches;
+            }
+            let i = choose(&candidates);
+            let p = &patterns[i];
+            matches.push((pos, pos + p.len(), u32::try_from(i).unwrap()));
+            pos += p.len();
+        }
+    }
+
+    #[test]
+    fn test_input_random() {
+        let mut rng = XorShift(0x3141_5926);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 20 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+
+            for kind in [
+                MatchKind::Standard,
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostFirst,
+                MatchKind::LeftmostShortest,
+            ] {
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .build(&patterns)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .build(&patterns)
+                        .unwrap();
+
+                for _ in 0..100 {
+                    let haystack = rng.gen_string(alphabet, 20);
+                    let a = rng.gen_boundary(&haystack);
+                    let b = rng.gen_boundary(&haystack);
+                    let (start, end) = (a.min(b), a.max(b));
+                    let sub = &haystack[start..end];
+                    let shift =
+                        |m: daachorse::Match<u32>| (start + m.start(), start + m.end(), m.value());
+                    let triple = |m: daachorse::Match<u32>| (m.start(), m.end(), m.value());
+                    let input = Input::new(haystack.as_str()).span(start..end);
+                    let anchored = input.clone().anchored(true);
+
+                    if kind == MatchKind::Standard {
+                        let expected: Vec<_> = pma.find_iter(sub).map(shift).collect();
+                        assert_eq!(
+                            expected,
+                            pma.find_iter(input.clone()).map(triple).collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .find_iter(input.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+
+                        let expected: Vec<_> = pma.find_overlapping_iter(sub).map(shift).collect();
+                        assert_eq!(
+                            expected,
+                            pma.find_overlapping_iter(input.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .find_overlapping_iter(input.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+
+                        let expected = naive_anchored(&patterns, &haystack, start, end, |c| {
+                            c.iter().min_by_key(|(_, p)| p.len()).unwrap().0
+                        });
+                        assert_eq!(
+                            expected,
+                            pma.find_iter(anchored.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .find_iter(anchored.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+
+                        let mut expected: Vec<_> = patterns
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, p)| sub.starts_with(p.as_str()))
+                            .map(|(i, p)| (start, start + p.len(), u32::try_from(i).unwrap()))
+                            .collect();
+                        expected.sort_unstable_by_key(|&(_, e, _)| e);
+                        assert_eq!(
+                            expected,
+                            pma.find_overlapping_iter(anchored.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .find_overlapping_iter(anchored)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                    } else {
+                        let expected: Vec<_> = pma.leftmost_find_iter(sub).map(shift).collect();
+                        assert_eq!(
+                            expected,
+                            pma.leftmost_find_iter(input.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .leftmost_find_iter(input)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+
+                        let expected =
+                            naive_anchored(&patterns, &haystack, start, end, |c| match kind {
+                                MatchKind::LeftmostLongest => {
+                                    c.iter().max_by_key(|(_, p)| p.len()).unwrap().0
+                                }
+                                MatchKind::LeftmostFirst => c[0].0,
+                                _ => c.iter().min_by_key(|(_, p)| p.len()).unwrap().0,
+                            });
+                        assert_eq!(
+                            expected,
+                            pma.leftmost_find_iter(anchored.clone())
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .leftmost_find_iter(anchored)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+mod lattice {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, Lattice,
+    };
+
+    use super::XorShift;
+
+    const UNKNOWN: u32 = u32::MAX;
+
+    fn edges(lattice: &Lattice<u32>) -> Vec<Vec<(usize, u32)>> {
+        (0..=lattice.len())
+            .map(|pos| {
+                let mut edges: Vec<_> = lattice
+                    .edges(pos)
+                    .iter()
+                    .map(|m| {
+                        assert_eq!(pos, m.start());
+                        (m.end(), m.value())
+                    })
+                    .collect();
+                // The values of a pattern are reported in any order.
+                edges.sort_unstable();
+                edges
+            })
+            .collect()
+    }
+
+    fn naive_edges(
+        patvals: &[(String, u32)],
+        haystack: &str,
+        unknown: bool,
+    ) -> Vec<Vec<(usize, u32)>> {
+        let mut edges = vec![vec![]; haystack.len() + 1];
+        let mut covered = vec![false; haystack.len()];
+        for (pattern, value) in patvals {
+            for start in 0..haystack.len() {
+                if haystack.as_bytes()[start..].starts_with(pattern.as_bytes()) {
+                    edges[start].push((start + pattern.len(), *value));
+                    covered[start..start + pattern.len()].fill(true);
+                }
+            }
+        }
+        if unknown {
+            let mut start = 0;
+            while start < haystack.len() {
+                if covered[start] {
+                    start += 1;
+                    continue;
+                }
+                let mut end = start;
+                while end < haystack.len() && !covered[end] {
+                    end += 1;
+                }
+                edges[start].push((end, UNKNOWN));
+                start = end;
+            }
+        }
+        for edges in &mut edges {
+            edges.sort_unstable();
+        }
+        edges
+    }
+
+    #[test]
+    fn test_build_lattice_random() {
+        let mut rng = XorShift(0x3a5d_8c21);
+        for alphabet in [&['a', 'b', 'c', 'd'][..], &['あ', 'い', 'b', 'c']] {
+            let mut patvals: Vec<(String, u32)> = vec![];
+            while patvals.len() < 10 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if patvals.iter().all(|(p, _)| *p != pattern) {
+                    for _ in 0..=rng.next() % 2 {
+                        patvals.push((pattern.clone(), u32::try_from(rng.next() % 6).unwrap()));
+                    }
+                }
+            }
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_values(patvals.clone())
+                .unwrap();
+            let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_values(patvals.clone())
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 40);
+
+                let expected = naive_edges(&patvals, &haystack, false);
+                let lattice = pma.build_lattice(&haystack);
+                assert_eq!(haystack.len(), lattice.len());
+                assert_eq!(expected, edges(&lattice));
+                assert_eq!(lattice, charwise.build_lattice(&haystack));
+
+                let expected = naive_edges(&patvals, &haystack, true);
+                let lattice = pma.build_lattice_with_unknown(&haystack, UNKNOWN);
+                assert_eq!(expected, edges(&lattice));
+                assert_eq!(
+                    lattice,
+                    charwise.build_lattice_with_unknown(&haystack, UNKNOWN)
+                );
+            }
+        }
+    }
+}
+
+mod leftmost_shortest {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, MatchKind,
+    };
+
+    use super::XorShift;
+
+    // Finds leftmost matches of the shortest patterns by brute force.
+    fn naive_leftmost_shortest(patterns: &[String], haystack: &str) -> Vec<(usize, usize, u32)> {
+        let mut matches = vec![];
+        let mut pos = 0;
+        while pos < haystack.len() {
+            let shortest = patterns
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| haystack[pos..].starts_with(p.as_str()))
+                .min_by_key(|(_, p)| p.len());
+            if let Some((i, p)) = shortest {
+                matches.push((pos, pos + p.len(), u32::try_from(i).unwrap()));
+                pos += p.len();
+            } else {
+                pos += haystack[pos..].chars().next().unwrap().len_utf8();
+            }
+        }
+        matches
+    }
+
+    #[test]
+    fn test_leftmost_shortest_random() {
+        let mut rng = XorShift(0x0f0f_1234);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 50 {
+                let pattern = rng.gen_string(alphabet, 6);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .match_kind(MatchKind::LeftmostShortest)
+                .build(&patterns)
+                .unwrap();
+            let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(MatchKind::LeftmostShortest)
+                    .build(&patterns)
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 50);
+                let expected = naive_leftmost_shortest(&patterns, &haystack);
+                let matches: Vec<_> = pma
+                    .leftmost_find_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                assert_eq!(expected, matches);
+                let matches: Vec<_> = charwise
+                    .leftmost_find_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                assert_eq!(expected, matches);
+            }
+        }
+    }
+}
+
+mod position {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, Match, MatchKind, PositionIterator, PositionedMatch,
+    };
+
+    use super::XorShift;
+
+    /// Returns (byte, char, line, column) of the byte offset computed from scratch.
+    fn naive_position(haystack: &str, byte: usize) -> (usize, usize, usize, usize) {
+        let prefix = &haystack[..byte];
+        let line_start = prefix.rfind('\n').map_or(0, |i| i + 1);
+        (
+            byte,
+            prefix.chars().count(),
+            prefix.matches('\n').count(),
+            prefix[line_start..].chars().count(),
+        )
+    }
+
+    type Positions = Vec<(
+        (usize, usize, usize, usize),
+        (usize, usize, usize, usize),
+        u32,
+    )>;
+
+    fn naive_positions(haystack: &str, matches: impl Iterator<Item = Match<u32>>) -> Positions {
+        matches
+            .map(|m| {
+                (
+                    naive_position(haystack, m.start()),
+                    naive_position(haystack, m.end()),
+                    m.value(),
+                )
+            })
+            .collect()
+    }
+
+    fn positions(matches: impl Iterator<Item = PositionedMatch<u32>>) -> Positions {
+        matches
+            .map(|m| {
+                let (s, e) = (m.start(), m.end());
+                (
+                    (s.byte(), s.char(), s.line(), s.column()),
+                    (e.byte(), e.char(), e.line(), e.column()),
+                    m.value(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_positions_random() {
+        let mut rng = XorShift(0x1618_0339);
+        for alphabet in [&['a', 'b', '\n'][..], &['あ', 'い', '\n', 'b']] {
+            let mut patterns: Vec<String> = vec![];
+            while patterns.len() < 20 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+            let standard: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasick::new(&patterns).unwrap();
+            let bytewise: DoubleArrayAhoCorasick<u32> =
+                DoubleArrayAhoCorasick::new(&patterns).unwrap();
+            let leftmost: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(MatchKind::LeftmostLongest)
+                    .build(&patterns)
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 40);
+                let haystack = haystack.as_str();
+
+                assert_eq!(
+                    naive_positions(haystack, standard.find_iter(haystack)),
+                    positions(standard.find_iter_with_positions(haystack))
+                );
+                assert_eq!(
+                    naive_positions(haystack, standard.find_overlapping_iter(haystack)),
+                    positions(standard.find_overlapping_iter_with_positions(haystack))
+                );
+                assert_eq!(
+                    naive_positions(haystack, leftmost.leftmost_find_iter(haystack)),
+                    positions(leftmost.leftmost_find_iter_with_positions(haystack))
+                );
+                assert_eq!(
+                    naive_positions(haystack, bytewise.find_overlapping_iter(haystack)),
+                    positions(PositionIterator::new(
+                        haystack,
+                        bytewise.find_overlapping_iter(haystack)
+                    ))
+                );
+            }
+        }
+    }
+}
+
+mod priority {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder,
+    };
+
+    use super::XorShift;
+
+    // Finds leftmost matches with the highest priorities by brute force.
+    fn naive_leftmost_priority(
+        patvalpris: &[(String, u32, u32)],
+        haystack: &str,
+    ) -> Vec<(usize, usize, u32)> {
+        let mut matches = vec![];
+        let mut pos = 0;
+        while pos < haystack.len() {
+            let best = patvalpris
+                .iter()
+                .filter(|(p, _, _)| haystack[pos..].starts_with(p.as_str()))
+                .max_by(|(p1, _, r1), (p2, _, r2)| {
+                    r1.cmp(r2).then_with(|| p1.len().cmp(&p2.len()))
+                });
+            if let Some((p, v, _)) = best {
+                matches.push((pos, pos + p.len(), *v));
+                pos += p.len();
+            } else {
+                pos += haystack[pos..].chars().next().unwrap().len_utf8();
+            }
+        }
+        matches
+    }
+
+    #[test]
+    fn test_priorities_random() {
+        let mut rng = XorShift(0x5555_aaaa);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patvalpris: Vec<(String, u32, u32)> = vec![];
+            while patvalpris.len() < 50 {
+                let pattern = rng.gen_string(alphabet, 5);
+                if patvalpris.iter().all(|(p, _, _)| *p != pattern) {
+                    let value = u32::try_from(patvalpris.len()).unwrap();
+                    let priority = u32::try_from(rng.next() % 4).unwrap();
+                    patvalpris.push((pattern, value, priority));
+                }
+            }
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .build_with_priorities(patvalpris.clone())
+                .unwrap();
+            let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .build_with_priorities(patvalpris.clone())
+                    .unwrap();
+
+            for _ in 0..100 {
+                let haystack = rng.gen_string(alphabet, 50);
+                let expected = naive_leftmost_priority(&patvalpris, &haystack);
+                let matches: Vec<_> = pma
+                    .leftmost_find_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                assert_eq!(expected, matches);
+                let matches: Vec<_> = charwise
+                    .leftmost_find_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                assert_eq!(expected, matches);
+            }
+        }
+    }
+}
+
+mod segment {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, Match, MatchKind,
+    };
+
+    use super::XorShift;
+
+    fn triple(m: Match<u32>) -> (usize, usize, u32) {
+        (m.start(), m.end(), m.value())
+    }
+
+    #[test]
+    fn test_segments_random() {
+        let mut rng = XorShift(0x2718_2818);
+        for alphabet in [&['a', 'b', 'c'][..], &['あ', 'い', 'b']] {
+            let mut patvals: Vec<(String, u32)> = vec![];
+            while patvals.len() < 30 {
+                let pattern = rng.gen_string(alphabet, 4);
+                if patvals.iter().all(|(p, _)| *p != pattern) {
+                    // Some patterns have two values.
+                    for _ in 0..=rng.next() % 2 {
+                        let value = u32::try_from(patvals.len()).unwrap();
+                        patvals.push((pattern.clone(), value));
+                    }
+                }
+            }
+
+            for kind in [
+                MatchKind::Standard,
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostFirst,
+                MatchKind::LeftmostShortest,
+            ] {
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(kind)
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_values(patvals.clone())
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(kind)
+                        .duplicate_policy(DuplicatePolicy::KeepAll)
+                        .build_with_values(patvals.clone())
+                        .unwrap();
+
+                for _ in 0..50 {
+                    let haystack = rng.gen_string(alphabet, 30);
+                    let segments = rng.split(haystack.as_bytes());
+
+                    if kind == MatchKind::Standard {
+                        let expected: Vec<_> = pma.find_iter(&haystack).map(triple).collect();
+                        assert_eq!(
+                            expected,
+                            pma.find_iter_from_segments(&segments)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            unsafe { charwise.find_iter_from_segments_unchecked(&segments) }
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+
+                        let expected: Vec<_> =
+                            pma.find_overlapping_iter(&haystack).map(triple).collect();
+                        assert_eq!(
+                            expected,
+                            pma.find_overlapping_iter_from_segments(&segments)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            unsafe {
+                                charwise.find_overlapping_iter_from_segments_unchecked(&segments)
+                            }
+                            .map(triple)
+                            .collect::<Vec<_>>()
+                        );
+                    } else {
+                        let expected: Vec<_> =
+                            pma.leftmost_find_iter(&haystack).map(triple).collect();
+                        assert_eq!(
+                            expected,
+                            charwise
+                                .leftmost_find_iter(&haystack)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            pma.leftmost_find_iter_from_segments(&segments)
+                                .map(triple)
+                                .collect::<Vec<_>>()
+                        );
+                        assert_eq!(
+                            expected,
+                            unsafe {
+                                charwise.leftmost_find_iter_from_segments_unchecked(&segments)
+                            }
+                            .map(triple)
+                            .collect::<Vec<_>>()
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+mod wildcard {
+    use daachorse::{
+        CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+        DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder, DuplicatePolicy, MatchKind,
+        WildcardPattern,
+    };
+
+    use super::XorShift;
+
+    /// Element of a pattern for the naive matcher, where `None` matches any unit.
+    type Element = Option<String>;
+
+    fn gen_pattern(
+        rng: &mut XorShift,
+        alphabet: &[char],
+        any: bool,
+    ) -> (WildcardPattern, Vec<Element>) {
+        let mut pattern = WildcardPattern::new();
+        let mut elements = vec![];
+        // At most one wildcard is used to keep the expansions small.
+        let mut any = any;
+        for _ in 0..=rng.next() % 3 {
+            match rng.next() % 4 {
+                0 if any => {
+                    any = false;
+                    pattern = pattern.any();
+                    elements.push(None);
+                }
+                1 => {
+                    let chars = rng.gen_string(alphabet, 3);
+                    pattern = pattern.one_of(&chars);
+                    elements.push(Some(chars));
+                }
+                _ => {
+                    let c = alphabet[(rng.next() % alphabet.len() as u64) as usize];
+                    pattern = pattern.literal(&c.to_string());
+                    elements.push(Some(c.to_string()));
+                }
+            }
+        }
+        (pattern, elements)
+    }
+
+    /// Returns the sorted (start, end, value) of the naive matches of the elements over the units.
+    fn naive_find<T: Copy + PartialEq>(
+        patterns: &[Vec<Vec<Option<T>>>],
+        units: &[T],
+        offsets: &[usize],
+    ) -> Vec<(usize, usize, u32)> {
+        let mut matches = vec![];
+        for (value, elements) in patterns.iter().enumerate() {
+            for start in 0..units.len() {
+                let end = start + elements.len();
+                if end <= units.len()
+                    && elements
+                        .iter()
+                        .zip(&units[start..end])
+                        .all(|(alts, u)| alts.iter().any(|alt| alt.map_or(true, |alt| alt == *u)))
+                {
+                    matches.push((offsets[start], offsets[end], u32::try_from(value).unwrap()));
+                }
+            }
+        }
+        matches.sort_unstable();
+        matches
+    }
+
+    /// Returns the concrete patterns in the order of the expansion.
+    fn expand<T: Clone>(elements: &[Vec<Vec<T>>]) -> Vec<Vec<T>> {
+        let mut patterns = vec![vec![]];
+        for alternatives in elements {
+            patterns = patterns
+                .iter()
+                .flat_map(|prefix| {
+                    alternatives.iter().map(move |alternative| {
+                        let mut pattern = prefix.clone();
+                        pattern.extend_from_slice(alternative);
+                        pattern
+                    })
+                })
+                .collect();
+        }
+        patterns
+    }
+
+    /// Returns the sorted characters of each element, where `None` is expanded into all bytes.
+    fn sorted_elements(elements: &[Element]) -> Vec<Option<Vec<char>>> {
+        elements
+            .iter()
+            .map(|e| {
+                e.as_ref().map(|chars| {
+                    let mut chars: Vec<char> = chars.chars().collect();
+                    chars.sort_unstable();
+                    chars.dedup();
+                    chars
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_with_wildcards_bytewise_random() {
+        let mut rng = XorShift(0x6d1c_b04f);
+        let alphabet = ['a', 'b', 'c'];
+        for _ in 0..20 {
+            let mut patterns = vec![];
+            let mut naive = vec![];
+            for _ in 0..5 {
+                let (pattern, elements) = gen_pattern(&mut rng, &alphabet, true);
+                patterns.push(pattern);
+                naive.push(
+                    elements
+                        .iter()
+                        .map(|e| match e {
+                            Some(chars) => chars.bytes().map(Some).collect(),
+                            None => vec![None],
+                        })
+                        .collect::<Vec<_>>(),
+                );
+            }
+            let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                .duplicate_policy(DuplicatePolicy::KeepAll)
+                .build_with_wildcards(patterns.iter().zip(0..))
+                .unwrap();
+            for _ in 0..20 {
+                let haystack = rng.gen_string(&alphabet, 20);
+                let offsets: Vec<usize> = (0..=haystack.len()).collect();
+                let mut actual: Vec<_> = pma
+                    .find_overlapping_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                actual.sort_unstable();
+                assert_eq!(naive_find(&naive, haystack.as_bytes(), &offsets), actual);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_with_wildcards_charwise_random() {
+        let mut rng = XorShift(0x2b9e_53a7);
+        let alphabet = ['あ', 'い', 'a', 'b'];
+        for _ in 0..20 {
+            let mut patterns = vec![];
+            let mut naive = vec![];
+            for _ in 0..5 {
+                let (pattern, elements) = gen_pattern(&mut rng, &alphabet, true);
+                patterns.push(pattern);
+                naive.push(
+                    elements
+                        .iter()
+                        .map(|e| match e {
+                            Some(chars) => chars.chars().map(Some).collect(),
+                            None => vec![None],
+                        })
+                        .collect::<Vec<_>>(),
+                );
+            }
+            let pma: CharwiseDoubleArrayAhoCorasick<u32> =
+                CharwiseDoubleArrayAhoCorasickBuilder::new()
+                    .duplicate_policy(DuplicatePolicy::KeepAll)
+                    .build_with_wildcards(patterns.iter().zip(0..))
+                    .unwrap();
+            for _ in 0..20 {
+                // Wildcards also match characters not in the patterns.
+                let haystack = rng.gen_string(&['あ', 'い', 'a', 'b', 'é', '🍣'], 20);
+                let chars: Vec<char> = haystack.chars().collect();
+                let offsets: Vec<usize> = haystack
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain([haystack.len()])
+                    .collect();
+                let mut actual: Vec<_> = pma
+                    .find_overlapping_iter(&haystack)
+                    .map(|m| (m.start(), m.end(), m.value()))
+                    .collect();
+                actual.sort_unstable();
+                assert_eq!(naive_find(&naive, &chars, &offsets), actual);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_with_wildcards_match_kinds_random() {
+        // The automata are the same as those built from the concrete patterns.
+        let mut rng = XorShift(0x4a7f_19c3);
+        let alphabet = ['a', 'b', 'é'];
+        for match_kind in [
+            MatchKind::Standard,
+            MatchKind::LeftmostLongest,
+            MatchKind::LeftmostFirst,
+            MatchKind::LeftmostShortest,
+        ] {
+            for _ in 0..20 {
+                let mut patterns = vec![];
+                let mut byte_concretes = vec![];
+                let mut char_concretes = vec![];
+                for value in 0..5 {
+                    let (pattern, elements) = gen_pattern(&mut rng, &alphabet, true);
+                    let elements = sorted_elements(&elements);
+                    let bytes: Vec<Vec<Vec<u8>>> = elements
+                        .iter()
+                        .map(|e| match e {
+                            Some(chars) => {
+                                chars.iter().map(|c| c.to_string().into_bytes()).collect()
+                            }
+                            None => (0..=u8::MAX).map(|b| vec![b]).collect(),
+                        })
+                        .collect();
+                    byte_concretes.extend(expand(&bytes).into_iter().map(|p| (p, value)));
+                    if elements.iter().all(Option::is_some) {
+                        let chars: Vec<Vec<Vec<char>>> = elements
+                            .iter()
+                            .map(|e| e.as_ref().unwrap().iter().map(|&c| vec![c]).collect())
+                            .collect();
+                        char_concretes.extend(
+                            expand(&chars)
+                                .into_iter()
+                                .map(|p| (p.into_iter().collect::<String>(), value)),
+                        );
+                        patterns.push((pattern, value, true));
+                    } else {
+                        patterns.push((pattern, value, false));
+                    }
+                }
+
+                let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(match_kind)
+                    .duplicate_policy(DuplicatePolicy::KeepFirst)
+                    .build_with_wildcards(patterns.iter().map(|(p, v, _)| (p, *v)))
+                    .unwrap();
+                let expected: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+                    .match_kind(match_kind)
+                    .duplicate_policy(DuplicatePolicy::KeepFirst)
+                    .build_with_values(byte_concretes)
+                    .unwrap();
+                let charwise: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(match_kind)
+                        .duplicate_policy(DuplicatePolicy::KeepFirst)
+                        .build_with_wildcards(
+                            patterns
+                                .iter()
+                                .filter(|(_, _, no_any)| *no_any)
+                                .map(|(p, v, _)| (p, *v)),
+                        )
+                        .unwrap();
+                let charwise_expected: CharwiseDoubleArrayAhoCorasick<u32> =
+                    CharwiseDoubleArrayAhoCorasickBuilder::new()
+                        .match_kind(match_kind)
+                        .duplicate_policy(DuplicatePolicy::KeepFirst)
+                        .build_with_values(char_concretes)
+                        .unwrap();
+
+                for _ in 0..20 {
+                    let haystack = rng.gen_string(&alphabet, 20);
+                    let tuples = |it: &mut dyn Iterator<Item = daachorse::Match<u32>>| {
+                        it.map(|m| (m.start(), m.end(), m.value()))
+                            .collect::<Vec<_>>()
+                    };
+                    if match_kind == MatchKind::Standard {
+                        assert_eq!(
+                            tuples(&mut expected.find_overlapping_iter(&haystack)),
+                            tuples(&mut pma.find_overlapping_iter(&haystack))
+                        );
+                        assert_eq!(
+                            tuples(&mut charwise_expected.find_overlapping_iter(&haystack)),
+                            tuples(&mut charwise.find_overlapping_iter(&haystack))
+                        );
+                    } else {
+                        assert_eq!(
+                            tuples(&mut expected.leftmost_find_iter(&haystack)),
+                            tuples(&mut pma.leftmost_find_iter(&haystack))
+                        );
+                        assert_eq!(
+                            tuples(&mut charwise_expected.leftmost_find_iter(&haystack)),
+                            tuples(&mut charwise.leftmost_find_iter(&haystack))
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
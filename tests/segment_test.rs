@@ -0,0 +1,46 @@
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    Match, MatchKind,
+};
+
+fn triple(m: Match<u32>) -> (usize, usize, u32) {
+    (m.start(), m.end(), m.value())
+}
+
+#[test]
+fn test_segments_str() {
+    let pma = DoubleArrayAhoCorasick::<u32>::new(["lo w", "world"]).unwrap();
+    let rope = vec![
+        String::from("hello"),
+        String::from(" "),
+        String::from("world"),
+    ];
+    let matches: Vec<_> = pma
+        .find_overlapping_iter_from_segments(&rope)
+        .map(triple)
+        .collect();
+    assert_eq!(vec![(3, 7, 0), (6, 11, 1)], matches);
+}
+
+#[test]
+fn test_segments_str_charwise() {
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build::<_, _, u32>(["世界", "界中", "に"])
+        .unwrap();
+    let rope = vec![String::from("全世"), String::new(), String::from("界中に")];
+    let matches: Vec<_> = pma
+        .leftmost_find_iter_from_segments(&rope)
+        .map(triple)
+        .collect();
+    assert_eq!(vec![(3, 9, 0), (12, 15, 2)], matches);
+
+    let pma = CharwiseDoubleArrayAhoCorasick::<u32>::new(["世界", "界中", "に"]).unwrap();
+    let matches: Vec<_> = pma
+        .find_overlapping_iter_from_segments(&rope)
+        .map(triple)
+        .collect();
+    assert_eq!(vec![(3, 9, 0), (6, 12, 1), (12, 15, 2)], matches);
+    let matches: Vec<_> = pma.find_iter_from_segments(&rope).map(triple).collect();
+    assert_eq!(vec![(3, 9, 0), (12, 15, 2)], matches);
+}
@@ -0,0 +1,118 @@
+use daachorse::errors::{DaachorseError, PatternProblemKind};
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick,
+    DoubleArrayAhoCorasickBuilder, MatchKind,
+};
+
+#[test]
+fn test_report() {
+    let patvals = vec![
+        ("ab", 0),
+        ("abc", 1),
+        ("a", 2),
+        ("abd", 3),
+        ("ac", 4),
+        ("b", 5),
+    ];
+    let (pma, report) = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build_with_values_and_report(patvals.clone())
+        .unwrap();
+    let report: Vec<_> = report
+        .iter()
+        .map(|p| (p.index(), p.pattern(), p.kind()))
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                1,
+                &b"abc"[..],
+                PatternProblemKind::Unreachable { prefix: 0 }
+            ),
+            (
+                3,
+                &b"abd"[..],
+                PatternProblemKind::Unreachable { prefix: 2 }
+            ),
+            (4, &b"ac"[..], PatternProblemKind::Unreachable { prefix: 2 }),
+        ],
+        report
+    );
+    let values: Vec<_> = pma
+        .leftmost_find_iter("abcacb")
+        .map(|m| m.value())
+        .collect();
+    assert_eq!(vec![0, 2, 5], values);
+
+    // The report agrees with the validation.
+    let patterns: Vec<_> = patvals.iter().map(|&(p, _)| p).collect();
+    let problems = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .validate(patterns);
+    assert_eq!(3, problems.len());
+
+    let (_, report) = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build_with_values_and_report(patvals)
+        .unwrap();
+    assert!(report.is_empty());
+}
+
+#[test]
+fn test_report_charwise() {
+    let patvals = vec![("世界", 0), ("世界中", 1), ("世", 2)];
+    let (_, report) = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build_with_values_and_report(patvals)
+        .unwrap();
+    assert_eq!(1, report.len());
+    assert_eq!(1, report[0].index());
+    assert_eq!("世界中".as_bytes(), report[0].pattern());
+    assert_eq!(
+        PatternProblemKind::Unreachable { prefix: 0 },
+        report[0].kind()
+    );
+}
+
+#[test]
+fn test_reject() {
+    let patterns = vec!["ab", "b", "abc"];
+    let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .reject_unreachable(true)
+        .build(&patterns);
+    match result {
+        Err(DaachorseError::UnreachablePattern(e)) => {
+            assert_eq!((2, &b"abc"[..], 0), (e.index(), e.pattern(), e.prefix()));
+            assert_eq!(
+                "UnreachablePatternError: pattern 2 [97, 98, 99] is shadowed by pattern 0",
+                e.to_string()
+            );
+        }
+        _ => panic!("UnreachablePatternError is expected"),
+    }
+
+    let result: Result<CharwiseDoubleArrayAhoCorasick<u32>, _> =
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .reject_unreachable(true)
+            .build(&patterns);
+    assert!(matches!(result, Err(DaachorseError::UnreachablePattern(_))));
+
+    // Shadowed patterns are allowed under the other match kinds.
+    for kind in [MatchKind::Standard, MatchKind::LeftmostLongest] {
+        let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+            .match_kind(kind)
+            .reject_unreachable(true)
+            .build(&patterns);
+        assert!(result.is_ok());
+    }
+
+    // Patterns shadowed by later ones are reachable.
+    let patterns = vec!["abc", "ab", "b"];
+    let result: Result<DoubleArrayAhoCorasick<u32>, _> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .reject_unreachable(true)
+        .build(&patterns);
+    assert!(result.is_ok());
+}
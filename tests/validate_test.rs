@@ -0,0 +1,164 @@
+use daachorse::errors::PatternProblemKind;
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasickBuilder, DuplicatePolicy,
+    MatchKind,
+};
+
+fn problems(
+    problems: Vec<daachorse::errors::PatternProblem>,
+) -> Vec<(usize, Vec<u8>, PatternProblemKind)> {
+    problems
+        .into_iter()
+        .map(|p| (p.index(), p.pattern().to_vec(), p.kind()))
+        .collect()
+}
+
+#[test]
+fn test_validate_standard() {
+    let patterns: Vec<&[u8]> = vec![b"ab", b"", b"\xff\xfe", b"a", b"ab", b"", b"\xff\xfe"];
+    let builder = DoubleArrayAhoCorasickBuilder::new();
+    assert_eq!(
+        vec![
+            (1, vec![], PatternProblemKind::Empty),
+            (
+                4,
+                b"ab".to_vec(),
+                PatternProblemKind::Duplicate { first: 0 }
+            ),
+            (5, vec![], PatternProblemKind::Empty),
+            (
+                6,
+                b"\xff\xfe".to_vec(),
+                PatternProblemKind::Duplicate { first: 2 }
+            ),
+        ],
+        problems(builder.validate(&patterns))
+    );
+    assert!(builder.build::<_, _, u32>(&patterns).is_err());
+}
+
+#[test]
+fn test_validate_keep_all() {
+    let patterns = vec!["ab", "a", "ab"];
+    let builder = DoubleArrayAhoCorasickBuilder::new().duplicate_policy(DuplicatePolicy::KeepAll);
+    assert!(builder.validate(&patterns).is_empty());
+    assert!(builder.build::<_, _, u32>(&patterns).is_ok());
+}
+
+#[test]
+fn test_validate_keep_first() {
+    // Duplicates are accepted by the build, so they are not reported.
+    let patterns = vec!["ab", "a", "ab"];
+    let builder = DoubleArrayAhoCorasickBuilder::new().duplicate_policy(DuplicatePolicy::KeepFirst);
+    assert!(builder.validate(&patterns).is_empty());
+    assert!(builder.build::<_, _, u32>(&patterns).is_ok());
+
+    let builder = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .duplicate_policy(DuplicatePolicy::KeepFirst);
+    assert_eq!(
+        vec![
+            (
+                1,
+                b"ab".to_vec(),
+                PatternProblemKind::Unreachable { prefix: 0 }
+            ),
+            (
+                3,
+                b"ab".to_vec(),
+                PatternProblemKind::Unreachable { prefix: 0 }
+            ),
+        ],
+        problems(builder.validate(["a", "ab", "a", "ab"]))
+    );
+}
+
+#[test]
+fn test_validate_keep_last() {
+    let patterns = vec!["ab", "a", "ab"];
+    let builder = DoubleArrayAhoCorasickBuilder::new().duplicate_policy(DuplicatePolicy::KeepLast);
+    assert!(builder.validate(&patterns).is_empty());
+    assert!(builder.build::<_, _, u32>(&patterns).is_ok());
+
+    let builder =
+        CharwiseDoubleArrayAhoCorasickBuilder::new().duplicate_policy(DuplicatePolicy::KeepLast);
+    assert!(builder.validate(["あ", "い", "あ", "あ"]).is_empty());
+}
+
+#[test]
+fn test_validate_leftmost_first() {
+    let patterns = vec!["abc", "a", "abcd", "ab", "b", "abc"];
+    let builder = DoubleArrayAhoCorasickBuilder::new().match_kind(MatchKind::LeftmostFirst);
+    assert_eq!(
+        vec![
+            (
+                2,
+                b"abcd".to_vec(),
+                PatternProblemKind::Unreachable { prefix: 1 }
+            ),
+            (
+                3,
+                b"ab".to_vec(),
+                PatternProblemKind::Unreachable { prefix: 1 }
+            ),
+            (
+                5,
+                b"abc".to_vec(),
+                PatternProblemKind::Duplicate { first: 0 }
+            ),
+        ],
+        problems(builder.validate(&patterns))
+    );
+
+    let builder = DoubleArrayAhoCorasickBuilder::new().match_kind(MatchKind::LeftmostLongest);
+    assert_eq!(
+        vec![(
+            5,
+            b"abc".to_vec(),
+            PatternProblemKind::Duplicate { first: 0 }
+        )],
+        problems(builder.validate(&patterns))
+    );
+
+    // Without the problematic patterns, all the patterns can be reported.
+    let patterns = vec!["abc", "a", "b"];
+    let pma = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build(&patterns)
+        .unwrap();
+    for (i, &pattern) in patterns.iter().enumerate() {
+        let m = pma.leftmost_find_iter(pattern).next().unwrap();
+        assert_eq!(i, m.value());
+    }
+}
+
+#[test]
+fn test_validate_charwise() {
+    let patterns = vec!["全世界", "", "全世界中", "全世界"];
+    let builder = CharwiseDoubleArrayAhoCorasickBuilder::new().match_kind(MatchKind::LeftmostFirst);
+    assert_eq!(
+        vec![
+            (1, vec![], PatternProblemKind::Empty),
+            (
+                2,
+                "全世界中".as_bytes().to_vec(),
+                PatternProblemKind::Unreachable { prefix: 0 }
+            ),
+            (
+                3,
+                "全世界".as_bytes().to_vec(),
+                PatternProblemKind::Duplicate { first: 0 }
+            ),
+        ],
+        problems(builder.validate(&patterns))
+    );
+}
+
+#[test]
+fn test_display() {
+    let problems = DoubleArrayAhoCorasickBuilder::new().validate(["ab", "ab"]);
+    assert_eq!(
+        "pattern 1 [97, 98]: duplicate of pattern 0",
+        problems[0].to_string()
+    );
+}
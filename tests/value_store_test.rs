@@ -0,0 +1,148 @@
+use std::rc::Rc;
+
+use daachorse::store::{CharwiseDoubleArrayAhoCorasickStore, DoubleArrayAhoCorasickStore};
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasickBuilder, DoubleArrayAhoCorasick, DoubleArrayAhoCorasickBuilder,
+    Input, MatchKind,
+};
+
+#[test]
+fn test_with_values() {
+    let patvals = vec![
+        ("bcd", "BCD".to_string()),
+        ("ab", "AB".to_string()),
+        ("a", "A".to_string()),
+    ];
+    let pma = DoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+
+    let matches: Vec<_> = pma
+        .find_overlapping_iter("abcd")
+        .map(|m| (m.start(), m.end(), m.value().as_str()))
+        .collect();
+    assert_eq!(vec![(0, 1, "A"), (0, 2, "AB"), (1, 4, "BCD")], matches);
+
+    let matches: Vec<_> = pma
+        .find_iter("abcd")
+        .map(|m| (m.start(), m.end(), m.value().as_str()))
+        .collect();
+    assert_eq!(vec![(0, 1, "A"), (1, 4, "BCD")], matches);
+
+    let matches: Vec<_> = pma
+        .find_overlapping_no_suffix_iter("abcd")
+        .map(|m| (m.start(), m.end(), m.value().as_str()))
+        .collect();
+    assert_eq!(vec![(0, 1, "A"), (0, 2, "AB"), (1, 4, "BCD")], matches);
+}
+
+#[test]
+fn test_shared_values() {
+    let values = vec![Rc::new(vec!["fruit"]), Rc::new(vec!["animal", "pet"])];
+    let patids = vec![("apple", 0), ("banana", 0), ("cat", 1), ("dog", 1)];
+    let pma: DoubleArrayAhoCorasick<u32> = DoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build_with_values(patids)
+        .unwrap();
+    let pma = DoubleArrayAhoCorasickStore::new(pma, values).unwrap();
+    assert_eq!(2, pma.values().len());
+
+    let matches: Vec<_> = pma
+        .leftmost_find_iter("a cat and a dog eat a banana")
+        .map(|m| (m.start(), m.end(), m.value().clone()))
+        .collect();
+    assert_eq!(
+        vec![
+            (2, 5, Rc::new(vec!["animal", "pet"])),
+            (12, 15, Rc::new(vec!["animal", "pet"])),
+            (22, 28, Rc::new(vec!["fruit"])),
+        ],
+        matches
+    );
+}
+
+#[test]
+fn test_input() {
+    let patvals = vec![("bcd", "BCD"), ("ab", "AB"), ("a", "A")];
+    let pma = DoubleArrayAhoCorasickStore::with_values(patvals.clone()).unwrap();
+
+    let matches: Vec<_> = pma
+        .find_overlapping_iter(Input::new("xabcd").span(1..4))
+        .map(|m| (m.start(), m.end(), *m.value()))
+        .collect();
+    assert_eq!(vec![(1, 2, "A"), (1, 3, "AB")], matches);
+
+    let matches: Vec<_> = pma
+        .find_iter(Input::new("xabcd").span(2..5).anchored(true))
+        .map(|m| (m.start(), m.end(), *m.value()))
+        .collect();
+    assert_eq!(vec![(2, 5, "BCD")], matches);
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostShortest)
+        .build_with_values(vec![("世界", 0), ("世", 1)])
+        .unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasickStore::new(pma, vec!["world", "generation"]).unwrap();
+    let matches: Vec<_> = pma
+        .leftmost_find_iter(Input::new("世界世界").span(6..12))
+        .map(|m| (m.start(), m.end(), *m.value()))
+        .collect();
+    assert_eq!(vec![(6, 9, "generation")], matches);
+}
+
+#[test]
+fn test_invalid_index() {
+    let pma = DoubleArrayAhoCorasick::with_values(vec![("a", 0), ("b", 2)]).unwrap();
+    assert!(DoubleArrayAhoCorasickStore::new(pma, vec!["x", "y"]).is_err());
+}
+
+#[test]
+fn test_charwise() {
+    let patvals = vec![
+        ("全世界", "everyone".to_string()),
+        ("世界", "world".to_string()),
+        ("に", "to".to_string()),
+    ];
+    let pma = CharwiseDoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+
+    let matches: Vec<_> = pma
+        .find_iter("全世界中に")
+        .map(|m| (m.start(), m.end(), m.value().as_str()))
+        .collect();
+    assert_eq!(vec![(0, 9, "everyone"), (12, 15, "to")], matches);
+
+    let pma = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .build_with_values(vec![("世界中", 0), ("世界", 1)])
+        .unwrap();
+    let pma = CharwiseDoubleArrayAhoCorasickStore::new(pma, vec!["a".to_string()]);
+    assert!(pma.is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let patvals = vec![
+        ("bcd", "BCD".to_string()),
+        ("ab", String::new()),
+        ("a", "エー".to_string()),
+    ];
+    let pma = DoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+    let serialized = serde_json::to_string(&pma).unwrap();
+    let other: DoubleArrayAhoCorasickStore<String> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(pma, other);
+    let values: Vec<_> = other
+        .find_iter("abcd")
+        .map(|m| m.value().as_str())
+        .collect();
+    assert_eq!(vec!["エー", "BCD"], values);
+
+    let patvals = vec![
+        ("全世界", "ZENSEKAI".to_string()),
+        ("世界", "SEKAI".to_string()),
+        ("に", "NI".to_string()),
+    ];
+    let pma = CharwiseDoubleArrayAhoCorasickStore::with_values(patvals).unwrap();
+    let serialized = serde_json::to_string(&pma).unwrap();
+    let other: CharwiseDoubleArrayAhoCorasickStore<String> =
+        serde_json::from_str(&serialized).unwrap();
+    assert_eq!(pma, other);
+}
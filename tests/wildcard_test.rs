@@ -0,0 +1,110 @@
+use daachorse::errors::DaachorseError;
+
+use daachorse::{
+    CharwiseDoubleArrayAhoCorasick, CharwiseDoubleArrayAhoCorasickBuilder,
+    DoubleArrayAhoCorasickBuilder, MatchKind, WildcardPattern,
+};
+
+#[test]
+fn test_build_with_wildcards_scale_error() {
+    // 10 + 100 + 100 * 5 states are created.
+    let pattern = WildcardPattern::new()
+        .class(['0'..='9'])
+        .class(['0'..='9'])
+        .literal("-item");
+    let result = DoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(609)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0)]);
+    assert!(matches!(result, Err(DaachorseError::AutomatonScale(_))));
+    assert!(DoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(610)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0)])
+        .is_ok());
+
+    // The states are counted over all the patterns.
+    let other = WildcardPattern::new().literal("#").class(['0'..='9']);
+    let result = DoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(620)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0), (&other, 1)]);
+    assert!(matches!(result, Err(DaachorseError::AutomatonScale(_))));
+    assert!(DoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(621)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0), (&other, 1)])
+        .is_ok());
+
+    // In the charwise automaton, the digits share an edge, so 1 + 1 + 5 and 1 + 1 states are
+    // created.
+    let result = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(8)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0), (&other, 1)]);
+    assert!(matches!(result, Err(DaachorseError::AutomatonScale(_))));
+    assert!(CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .max_wildcard_states(9)
+        .build_with_wildcards::<_, _, u32>([(&pattern, 0), (&other, 1)])
+        .is_ok());
+}
+
+#[test]
+fn test_build_with_wildcards_charwise_any() {
+    // Characters not distinguished by the patterns share an edge, so the default limit suffices.
+    let patvals = [
+        (WildcardPattern::new().any().any().literal("x"), 0),
+        (WildcardPattern::new().literal("a").any(), 1),
+    ];
+    let pma: CharwiseDoubleArrayAhoCorasick<u32> = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .build_with_wildcards(patvals)
+        .unwrap();
+    let matches: Vec<_> = pma
+        .find_overlapping_iter("a🍣xaé\u{10FFFF}x")
+        .map(|m| (m.start(), m.end(), m.value()))
+        .collect();
+    assert_eq!(vec![(0, 5, 1), (0, 6, 0), (6, 9, 1), (7, 14, 0)], matches);
+}
+
+#[test]
+fn test_build_with_wildcards_empty_class() {
+    let patvals = [
+        (WildcardPattern::new().literal("a"), 0),
+        (WildcardPattern::new().literal("b").class([]), 1),
+    ];
+    assert!(matches!(
+        DoubleArrayAhoCorasickBuilder::new()
+            .build_with_wildcards::<_, _, u32>(patvals.iter().map(|(p, v)| (p, *v))),
+        Err(DaachorseError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        CharwiseDoubleArrayAhoCorasickBuilder::new()
+            .build_with_wildcards::<_, _, u32>(patvals.iter().map(|(p, v)| (p, *v))),
+        Err(DaachorseError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn test_build_with_wildcards_duplicate() {
+    let patvals = [
+        (WildcardPattern::new().one_of("ab").literal("c"), 0),
+        (WildcardPattern::new().literal("b").one_of("cd"), 1),
+    ];
+    let result = DoubleArrayAhoCorasickBuilder::new().build_with_wildcards::<_, _, u32>(patvals);
+    assert!(matches!(result, Err(DaachorseError::DuplicatePattern(_))));
+}
+
+#[test]
+fn test_build_with_wildcards_unreachable_index() {
+    let patvals = [
+        (WildcardPattern::new().literal("x"), 0),
+        (WildcardPattern::new().literal("a"), 1),
+        (WildcardPattern::new().one_of("ab").literal("c"), 2),
+    ];
+    let result = CharwiseDoubleArrayAhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostFirst)
+        .reject_unreachable(true)
+        .build_with_wildcards::<_, _, u32>(patvals);
+    match result {
+        Err(DaachorseError::UnreachablePattern(e)) => {
+            assert_eq!(2, e.index());
+            assert_eq!(1, e.prefix());
+        }
+        _ => panic!("unexpected result"),
+    }
+}